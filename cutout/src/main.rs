@@ -7,9 +7,13 @@ mod story;
 #[derive(Debug, Default)]
 pub struct CutoutApp {
     domain: CutoutDomain,
-    story: Option<CutoutStory>,
+    /// The stories of the last generation, shown side by side.
+    stories: Vec<CutoutStory>,
     event_draft: String,
     viewing_story: bool,
+    story_count: usize,
+    /// How different the events of each pair of stories must be, from 0 to 1.
+    min_difference: f32,
 }
 
 impl CutoutApp {
@@ -52,8 +56,10 @@ impl CutoutApp {
         Self {
             event_draft: String::new(),
             domain: CutoutDomain::default(),
-            story: None,
+            stories: Vec::new(),
             viewing_story: false,
+            story_count: 1,
+            min_difference: 0.3,
         }
     }
 }
@@ -82,7 +88,9 @@ impl eframe::App for CutoutApp {
                     );
 
                     if ui.button(generate_text).clicked() {
-                        self.story = self.domain.generate_story();
+                        self.stories = self
+                            .domain
+                            .generate_stories(self.story_count, self.min_difference);
                         self.viewing_story = true;
                     }
                 });
@@ -94,6 +102,23 @@ impl eframe::App for CutoutApp {
                     ui.label("Story length");
                 });
 
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().slider_width = 300.;
+                    ui.add(egui::Slider::new(&mut self.story_count, 1..=5));
+                    ui.add_space(12.);
+                    ui.label("Stories");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().slider_width = 300.;
+                    ui.add_enabled(
+                        self.story_count > 1,
+                        egui::Slider::new(&mut self.min_difference, 0.0..=1.0),
+                    );
+                    ui.add_space(12.);
+                    ui.label("Difference between stories");
+                });
+
                 ui.horizontal(|ui| {
                     ui.add_sized(
                         [ui.available_width() - 30., 20.],
@@ -145,7 +170,9 @@ impl eframe::App for CutoutApp {
             self.event_draft.clear();
         }
 
-        egui::Window::new("My story")
+        let title = if self.stories.len() > 1 { "My stories" } else { "My story" };
+        egui::Window::new(title)
+            .id(egui::Id::new("stories"))
             .collapsible(false)
             .fixed_size(ctx.viewport_rect().size() - Vec2::new(50., 100.))
             .resizable(false)
@@ -153,9 +180,16 @@ impl eframe::App for CutoutApp {
             .show(ctx, |ui| {
                 ui.set_width(ui.available_width());
                 ui.set_height(ui.available_height());
-                if let Some(CutoutStory(text)) = &self.story {
-                    ui.label(text);
+                if self.stories.is_empty() {
+                    ui.label("No stories different enough from each other were found.");
+                    return;
                 }
+
+                ui.columns(self.stories.len(), |columns| {
+                    for (ui, CutoutStory(text)) in columns.iter_mut().zip(&self.stories) {
+                        ui.label(text);
+                    }
+                });
             });
     }
 }
@@ -1,3 +1,8 @@
+use std::collections::HashSet;
+
+/// How many stories are generated while looking for ones different enough from the others.
+pub const GENERATION_ATTEMPTS: usize = 1000;
+
 #[derive(Debug, Default)]
 pub struct CutoutDomain {
     pub events: Vec<String>,
@@ -8,7 +13,13 @@ pub struct CutoutDomain {
 pub struct CutoutStory(pub String);
 
 impl CutoutDomain {
+    /// Strings `story_length` random events together, or returns `None` if there are no events
+    /// to pick from.
     pub fn generate_story(&self) -> Option<CutoutStory> {
+        if self.events.is_empty() && self.story_length > 0 {
+            return None;
+        }
+
         let mut story = String::new();
 
         for _ in 0..self.story_length {
@@ -19,5 +30,87 @@ impl CutoutDomain {
 
         Some(CutoutStory(story))
     }
+
+    /// Generates up to `count` stories, each with a Jaccard distance of at least `min_distance`
+    /// between its events and those of every other story, so that no two are near-identical.
+    /// Unlike the predicate improviser, this runs on the calling thread: drawing a story is a
+    /// handful of random picks, so the attempts take no noticeable time.
+    pub fn generate_stories(&self, count: usize, min_distance: f32) -> Vec<CutoutStory> {
+        let mut stories: Vec<CutoutStory> = Vec::new();
+
+        for _ in 0..GENERATION_ATTEMPTS {
+            if stories.len() >= count {
+                break;
+            }
+
+            let Some(story) = self.generate_story() else {
+                continue;
+            };
+            if stories
+                .iter()
+                .all(|other| story.distance(other) >= min_distance)
+            {
+                stories.push(story);
+            }
+        }
+
+        stories
+    }
 }
 
+impl CutoutStory {
+    /// The Jaccard distance between the events of the stories: 0 if they use the same events, 1
+    /// if they have none in common.
+    pub fn distance(&self, other: &CutoutStory) -> f32 {
+        let events: HashSet<&str> = self.0.lines().collect();
+        let other_events: HashSet<&str> = other.0.lines().collect();
+
+        let union = events.union(&other_events).count();
+        if union == 0 {
+            return 0.;
+        }
+        1. - events.intersection(&other_events).count() as f32 / union as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story(events: &[&str]) -> CutoutStory {
+        CutoutStory(events.iter().map(|event| format!("{event}\n")).collect())
+    }
+
+    #[test]
+    fn measures_the_distance_between_events() {
+        let a = story(&["a knight arrives", "a dragon wakes"]);
+        let b = story(&["a dragon wakes", "a dragon wakes", "the village burns"]);
+
+        assert_eq!(a.distance(&a), 0.);
+        assert_eq!(a.distance(&b), 1. - 1. / 3.);
+        assert_eq!(a.distance(&story(&["the village burns"])), 1.);
+        assert_eq!(story(&[]).distance(&story(&[])), 0.);
+    }
+
+    #[test]
+    fn generates_distinct_stories() {
+        let mut domain = CutoutDomain {
+            events: Vec::new(),
+            story_length: 2,
+        };
+        assert!(domain.generate_story().is_none());
+        assert!(domain.generate_stories(3, 0.).is_empty());
+
+        domain.events = vec!["a knight arrives".into(), "a dragon wakes".into()];
+        let stories = domain.generate_stories(3, 0.6);
+        // Two events make only three sets of events, and only the two single events are far
+        // enough apart.
+        assert!(!stories.is_empty() && stories.len() <= 2);
+        for (i, story) in stories.iter().enumerate() {
+            assert_eq!(story.0.lines().count(), 2);
+            for other in &stories[..i] {
+                assert!(story.distance(other) >= 0.6);
+            }
+        }
+    }
+}
@@ -1,24 +1,150 @@
 use std::mem::take;
 
-use eframe::egui::{self, Color32, Frame, Margin, RichText, Vec2};
+use eframe::egui::{
+    self, Color32, Frame, Margin, RichText, Stroke, TextFormat, Vec2, text::LayoutJob,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    app::{PredicateImprovApp, typed_object_input},
-    story::{LogicExpr, Symbol},
+    app::{PredicateImprovApp, name_taken, symbol_candidates, symbol_input, typed_object_input},
+    history::{Change, Edit},
+    logic_parser::{ParseError, parse_logic_expr},
+    story::{
+        Action, BeliefUpdate, Comparison, FluentUpdate, LogicExpr, NumericExpr, PredicateDomain,
+        PredicateSignature, Symbol, TypeName, TypedSymbol,
+    },
 };
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ActionDraft {
     pub name: String,
     pub parameters: Vec<(String, String)>,
+    /// The names of the parameters that are consenting characters.
+    #[serde(default)]
+    pub agents: Vec<String>,
     pub precondition: LogicExpr,
     pub effect: LogicExpr,
+    #[serde(skip)]
+    pub precondition_editor: LogicExprEditor,
+    #[serde(skip)]
+    pub effect_editor: LogicExprEditor,
+}
+
+/// A text field for a `LogicExpr`, kept in sync with the tree editor: edits to the text replace
+/// the expression whenever they parse, and changes to the expression made elsewhere replace the
+/// text.
+#[derive(Debug, Default, Clone)]
+pub struct LogicExprEditor {
+    text: String,
+    /// The printed form of the expression when the text was last synced with it.
+    synced: String,
+    error: Option<ParseError>,
+}
+
+impl LogicExprEditor {
+    pub fn show(&mut self, ui: &mut egui::Ui, domain: &PredicateDomain, expr: &mut LogicExpr) {
+        let printed = expr.to_string();
+        if printed != self.synced {
+            self.text = printed.clone();
+            self.synced = printed;
+            self.error = None;
+        }
+
+        let error_span = self.error.as_ref().map(|err| err.span.clone());
+        let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+            let text = text.as_str();
+            let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+            let color = ui.visuals().text_color();
+            let mut job = LayoutJob::default();
+
+            let mut append = |text: &str, underline: bool| {
+                let mut format = TextFormat::simple(font_id.clone(), color);
+                if underline {
+                    format.underline = Stroke::new(2., Color32::LIGHT_RED);
+                }
+                job.append(text, 0., format);
+            };
+
+            match &error_span {
+                // Errors at the end of the input have nothing to underline, so mark a space there.
+                Some(span) if span.is_empty() && span.start == text.len() => {
+                    append(text, false);
+                    append(" ", true);
+                }
+                // The span comes from parsing the text before this frame's edit, so it may no
+                // longer fall on character boundaries.
+                Some(span)
+                    if span.end <= text.len()
+                        && text.is_char_boundary(span.start)
+                        && text.is_char_boundary(span.end) =>
+                {
+                    append(&text[..span.start], false);
+                    append(&text[span.clone()], true);
+                    append(&text[span.end..], false);
+                }
+                _ => append(text, false),
+            }
+
+            job.wrap.max_width = wrap_width;
+            ui.fonts_mut(|fonts| fonts.layout_job(job))
+        };
+
+        ui.vertical(|ui| {
+            let response = egui::TextEdit::singleline(&mut self.text)
+                .desired_width(ui.available_width())
+                .layouter(&mut layouter)
+                .show(ui)
+                .response;
+
+            if response.changed() {
+                match parse_logic_expr(domain, &self.text) {
+                    Ok(parsed) => {
+                        *expr = parsed;
+                        self.synced = expr.to_string();
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+
+            if let Some(err) = &self.error {
+                ui.label(RichText::new(err.to_string()).color(Color32::LIGHT_RED));
+            }
+        });
+    }
+}
+
+impl ActionDraft {
+    pub fn into_action(self) -> (Symbol, Action) {
+        let agents = self
+            .agents
+            .into_iter()
+            .filter(|agent| self.parameters.iter().any(|(param, _)| param == agent))
+            .map(Symbol)
+            .collect();
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|(param, ty)| TypedSymbol {
+                name: Symbol(param),
+                r#type: TypeName(ty),
+            })
+            .collect();
+        let action = Action {
+            parameters,
+            agents,
+            precondition: self.precondition,
+            effect: self.effect,
+        };
+
+        (Symbol(self.name), action)
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct ActionPanel {
     pub action_draft: ActionDraft,
-    backup_action_draft: Option<ActionDraft>,
+    pub backup_action_draft: Option<ActionDraft>,
     pub add_action: bool,
     show_action_modal: bool,
 }
@@ -90,6 +216,11 @@ impl ActionPanel {
                                                                 )
                                                             })
                                                             .collect();
+                                                    app.action_panel.action_draft.agents = action
+                                                        .agents
+                                                        .iter()
+                                                        .map(|agent| agent.0.clone())
+                                                        .collect();
                                                     app.action_panel.action_draft.precondition =
                                                         take(&mut action.precondition);
                                                     app.action_panel.action_draft.effect =
@@ -103,7 +234,12 @@ impl ActionPanel {
                                                     true
                                                 };
 
-                                            retain = retain && !ui.button("Delete").clicked();
+                                            if ui.button("Delete").clicked() {
+                                                app.history.record(Edit::Action(Box::new(
+                                                    Change::removed((name.clone(), action.clone())),
+                                                )));
+                                                retain = false;
+                                            }
 
                                             retain
                                         })
@@ -152,21 +288,87 @@ fn action_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         }
     });
 
+    let parameters = app.action_panel.action_draft.parameters.clone();
+
+    ui.horizontal(|ui| {
+        ui.label("agents:").on_hover_text(
+            "Characters who must have a reason of their own to take part in the action",
+        );
+
+        let agents = &mut app.action_panel.action_draft.agents;
+        for (param, _) in parameters.iter().filter(|(param, _)| !param.is_empty()) {
+            let mut checked = agents.contains(param);
+            if ui.checkbox(&mut checked, param.as_str()).changed() {
+                if checked {
+                    agents.push(param.clone());
+                } else {
+                    agents.retain(|agent| agent != param);
+                }
+            }
+        }
+    });
+
     ui.horizontal(|ui| {
         ui.label("precondition:");
-        let expr = logic_expr(app, app.action_panel.action_draft.precondition.clone(), ui);
+        let expr = logic_expr(
+            app,
+            app.action_panel.action_draft.precondition.clone(),
+            &parameters,
+            ui,
+        );
         app.action_panel.action_draft.precondition = expr;
     });
 
+    let draft = &mut app.action_panel.action_draft;
+    draft
+        .precondition_editor
+        .show(ui, &app.domain, &mut draft.precondition);
+
     ui.horizontal(|ui| {
         ui.label("effect:");
-        let expr = logic_expr(app, app.action_panel.action_draft.effect.clone(), ui);
+        let expr = logic_expr(
+            app,
+            app.action_panel.action_draft.effect.clone(),
+            &parameters,
+            ui,
+        );
         app.action_panel.action_draft.effect = expr;
     });
 
+    let draft = &mut app.action_panel.action_draft;
+    draft.effect_editor.show(ui, &app.domain, &mut draft.effect);
+
+    let mut derived = None;
+    draft.effect.for_each_literal(&mut |signature, _, _| {
+        if app.domain.is_derived(signature) {
+            derived = Some(signature);
+        }
+    });
+    if let Some(signature) = derived {
+        ui.label(
+            RichText::new(format!(
+                "`{}/{}` is derived and cannot be changed by effects",
+                signature.function.0, signature.arity
+            ))
+            .color(Color32::LIGHT_RED),
+        );
+    }
+    let valid = derived.is_none();
+
+    // Outlines and methods call actions and compound tasks by name, so they share their names.
+    let name = Symbol(app.action_panel.action_draft.name.clone());
+    let taken = name_taken(
+        ui,
+        app.domain.actions.contains_key(&name) || app.domain.tasks.contains_key(&name),
+        name.0,
+    );
+
     ui.horizontal(|ui| {
         if ui
-            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .add_enabled(
+                valid && !taken,
+                egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)),
+            )
             .clicked()
         {
             app.action_panel.show_action_modal = false;
@@ -178,11 +380,23 @@ fn action_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
             .clicked()
         {
             app.action_panel.show_action_modal = false;
+
+            if let Some(draft) = app.action_panel.backup_action_draft.clone() {
+                app.action_panel.action_draft = draft;
+                app.action_panel.add_action = true;
+            }
         }
     });
 }
 
-fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui) -> LogicExpr {
+/// Tree editor for a logic expression. Predicate arguments are completed from the given action
+/// parameters, the domain constants and the problem objects.
+pub fn logic_expr(
+    app: &mut PredicateImprovApp,
+    expr: LogicExpr,
+    parameters: &[(String, String)],
+    ui: &mut egui::Ui,
+) -> LogicExpr {
     match expr {
         LogicExpr::True => {
             let mut out_expr = LogicExpr::True;
@@ -209,6 +423,54 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
                 if ui.button("Or").clicked() {
                     out_expr = LogicExpr::Or(Box::new(LogicExpr::True), Box::new(LogicExpr::True));
                 }
+
+                let next_fluent = app.domain.fluents.keys().next();
+                let fluent = || {
+                    let signature = next_fluent.unwrap().clone();
+                    let variables = vec![Symbol::default(); signature.arity as usize];
+                    (signature, variables)
+                };
+
+                if ui
+                    .add_enabled(next_fluent.is_some(), egui::Button::new("Comparison"))
+                    .clicked()
+                {
+                    let (signature, variables) = fluent();
+                    out_expr = LogicExpr::Compare(
+                        Comparison::GreaterOrEqual,
+                        NumericExpr::Fluent(signature, variables),
+                        NumericExpr::Number(0),
+                    );
+                }
+
+                for (kind, label) in [
+                    (FluentUpdate::Assign, "Assign"),
+                    (FluentUpdate::Increase, "Increase"),
+                    (FluentUpdate::Decrease, "Decrease"),
+                ] {
+                    if ui
+                        .add_enabled(next_fluent.is_some(), egui::Button::new(label))
+                        .clicked()
+                    {
+                        let (signature, variables) = fluent();
+                        out_expr =
+                            LogicExpr::Update(kind, signature, variables, NumericExpr::Number(1));
+                    }
+                }
+
+                if ui.button("Believes").clicked() {
+                    out_expr = LogicExpr::Believes(Symbol::default(), Box::new(LogicExpr::True));
+                }
+
+                for (kind, label) in [
+                    (BeliefUpdate::Observe, "Observe"),
+                    (BeliefUpdate::Inform, "Inform"),
+                ] {
+                    if ui.button(label).clicked() {
+                        out_expr =
+                            LogicExpr::Learn(kind, Symbol::default(), Box::new(LogicExpr::True));
+                    }
+                }
             });
 
             out_expr
@@ -216,7 +478,7 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
         LogicExpr::Predicate(mut predicate_signature, mut variables) => {
             let retain = ui
                 .menu_button(predicate_signature.function.0.clone(), |ui| {
-                    for ps in app.domain.predicates.keys() {
+                    for ps in app.domain.predicates.keys().chain(app.domain.axioms.keys()) {
                         if ui
                             .button(format!("{}/{}", ps.function.0, ps.arity))
                             .clicked()
@@ -230,10 +492,23 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
                 .inner
                 .unwrap_or(true);
 
-            for vc in &mut variables {
-                egui::TextEdit::singleline(&mut vc.0)
-                    .desired_width(20.0)
-                    .show(ui);
+            variables.resize(predicate_signature.arity as usize, Symbol::default());
+
+            let variable_types = app.domain.predicates.get(&predicate_signature).or_else(|| {
+                app.domain
+                    .axioms
+                    .get(&predicate_signature)
+                    .map(|axiom| &axiom.variables)
+            });
+            for (i, vc) in variables.iter_mut().enumerate() {
+                let expected = variable_types
+                    .and_then(|types| types.get(i))
+                    .map(|var| var.r#type.clone())
+                    .unwrap_or_default();
+                let candidates =
+                    symbol_candidates(&app.domain, app.problem(), parameters, &expected);
+
+                symbol_input(ui, &mut vc.0, &candidates, 80.0);
             }
 
             if retain {
@@ -261,7 +536,7 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
             });
 
             ui.label("(");
-            *v = logic_expr(app, (*v).clone(), ui);
+            *v = logic_expr(app, (*v).clone(), parameters, ui);
             ui.label(")");
 
             match out {
@@ -282,7 +557,7 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
             let mut out = Out::And;
 
             ui.label("(");
-            *lhs = logic_expr(app, (*lhs).clone(), ui);
+            *lhs = logic_expr(app, (*lhs).clone(), parameters, ui);
 
             ui.menu_button("and", |ui| {
                 if ui.button("Convert to Or").clicked() {
@@ -299,7 +574,7 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
                 }
             });
 
-            *rhs = logic_expr(app, (*rhs).clone(), ui);
+            *rhs = logic_expr(app, (*rhs).clone(), parameters, ui);
             ui.label(")");
 
             match out {
@@ -310,16 +585,103 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
                 Out::Delete => LogicExpr::True,
             }
         }
+        LogicExpr::Compare(mut comparison, lhs, rhs) => {
+            let lhs = numeric_expr(app, lhs, parameters, ui);
+
+            let retain = ui
+                .menu_button(comparison.symbol(), |ui| {
+                    for other in Comparison::ALL {
+                        if ui.button(other.symbol()).clicked() {
+                            comparison = other;
+                        }
+                    }
+
+                    !ui.button("Delete").clicked()
+                })
+                .inner
+                .unwrap_or(true);
+
+            let rhs = numeric_expr(app, rhs, parameters, ui);
+
+            if retain {
+                LogicExpr::Compare(comparison, lhs, rhs)
+            } else {
+                LogicExpr::True
+            }
+        }
+        LogicExpr::Update(mut kind, signature, variables, value) => {
+            let retain = ui
+                .menu_button(kind.keyword(), |ui| {
+                    for other in FluentUpdate::ALL {
+                        if ui.button(other.keyword()).clicked() {
+                            kind = other;
+                        }
+                    }
+
+                    !ui.button("Delete").clicked()
+                })
+                .inner
+                .unwrap_or(true);
+
+            ui.label("(");
+            let (signature, variables) =
+                fluent_term(app, signature, variables, parameters, ui, |_| {});
+            ui.label(",");
+            let value = numeric_expr(app, value, parameters, ui);
+            ui.label(")");
+
+            if retain {
+                LogicExpr::Update(kind, signature, variables, value)
+            } else {
+                LogicExpr::True
+            }
+        }
+        LogicExpr::Believes(character, expr) => {
+            let retain = ui
+                .menu_button("believes", |ui| !ui.button("Delete").clicked())
+                .inner
+                .unwrap_or(true);
+
+            let (character, expr) = belief_expr(app, character, *expr, parameters, ui);
+
+            if retain {
+                LogicExpr::Believes(character, Box::new(expr))
+            } else {
+                LogicExpr::True
+            }
+        }
+        LogicExpr::Learn(mut kind, character, expr) => {
+            let retain = ui
+                .menu_button(kind.keyword(), |ui| {
+                    for other in BeliefUpdate::ALL {
+                        if ui.button(other.keyword()).clicked() {
+                            kind = other;
+                        }
+                    }
+
+                    !ui.button("Delete").clicked()
+                })
+                .inner
+                .unwrap_or(true);
+
+            let (character, expr) = belief_expr(app, character, *expr, parameters, ui);
+
+            if retain {
+                LogicExpr::Learn(kind, character, Box::new(expr))
+            } else {
+                LogicExpr::True
+            }
+        }
         LogicExpr::Or(lhs, rhs) => {
             ui.label("(");
-            let new_lhs = Box::new(logic_expr(app, (*lhs).clone(), ui));
+            let new_lhs = Box::new(logic_expr(app, (*lhs).clone(), parameters, ui));
 
             let retain = ui
                 .menu_button("or", |ui| !ui.button("Delete").clicked())
                 .inner
                 .unwrap_or(true);
 
-            let new_rhs = Box::new(logic_expr(app, (*rhs).clone(), ui));
+            let new_rhs = Box::new(logic_expr(app, (*rhs).clone(), parameters, ui));
             ui.label(")");
 
             if retain {
@@ -330,3 +692,189 @@ fn logic_expr(app: &mut PredicateImprovApp, expr: LogicExpr, ui: &mut egui::Ui)
         }
     }
 }
+
+/// Editors for the character and the facts of `believes`, `observe` and `inform`.
+fn belief_expr(
+    app: &mut PredicateImprovApp,
+    mut character: Symbol,
+    expr: LogicExpr,
+    parameters: &[(String, String)],
+    ui: &mut egui::Ui,
+) -> (Symbol, LogicExpr) {
+    let candidates =
+        symbol_candidates(&app.domain, app.problem(), parameters, &TypeName::default());
+
+    ui.label("(");
+    symbol_input(ui, &mut character.0, &candidates, 80.0);
+    ui.label(",");
+    let expr = logic_expr(app, expr, parameters, ui);
+    ui.label(")");
+
+    (character, expr)
+}
+
+/// Tree editor for a numeric expression. Right-clicking a number turns it into a fluent or wraps
+/// it in an arithmetic operation.
+fn numeric_expr(
+    app: &mut PredicateImprovApp,
+    expr: NumericExpr,
+    parameters: &[(String, String)],
+    ui: &mut egui::Ui,
+) -> NumericExpr {
+    match expr {
+        NumericExpr::Number(mut value) => {
+            let response = ui
+                .add(egui::DragValue::new(&mut value))
+                .on_hover_text("Right-click for more options");
+            let mut out_expr = NumericExpr::Number(value);
+
+            response.context_menu(|ui| {
+                let next_fluent = app.domain.fluents.keys().next();
+                if ui
+                    .add_enabled(next_fluent.is_some(), egui::Button::new("Fluent"))
+                    .clicked()
+                {
+                    let signature = next_fluent.unwrap().clone();
+                    let variables = vec![Symbol::default(); signature.arity as usize];
+                    out_expr = NumericExpr::Fluent(signature, variables);
+                }
+
+                if let Some(wrapped) = arithmetic_buttons(ui, &out_expr) {
+                    out_expr = wrapped;
+                }
+            });
+
+            out_expr
+        }
+        NumericExpr::Fluent(signature, variables) => {
+            let mut to_number = false;
+            let mut wrap = None;
+
+            let (signature, variables) =
+                fluent_term(app, signature, variables, parameters, ui, |ui| {
+                    if ui.button("Number").clicked() {
+                        to_number = true;
+                    }
+
+                    wrap = arithmetic_buttons(ui, &NumericExpr::Number(0));
+                });
+
+            let out_expr = NumericExpr::Fluent(signature, variables);
+            match wrap {
+                _ if to_number => NumericExpr::Number(0),
+                // Put the fluent in place of the placeholder on the left side.
+                Some(NumericExpr::Add(_, rhs)) => NumericExpr::Add(Box::new(out_expr), rhs),
+                Some(NumericExpr::Sub(_, rhs)) => NumericExpr::Sub(Box::new(out_expr), rhs),
+                Some(NumericExpr::Mul(_, rhs)) => NumericExpr::Mul(Box::new(out_expr), rhs),
+                _ => out_expr,
+            }
+        }
+        NumericExpr::Add(lhs, rhs) => arithmetic_expr(app, *lhs, "+", *rhs, parameters, ui),
+        NumericExpr::Sub(lhs, rhs) => arithmetic_expr(app, *lhs, "-", *rhs, parameters, ui),
+        NumericExpr::Mul(lhs, rhs) => arithmetic_expr(app, *lhs, "*", *rhs, parameters, ui),
+    }
+}
+
+fn arithmetic_expr(
+    app: &mut PredicateImprovApp,
+    lhs: NumericExpr,
+    mut operator: &str,
+    rhs: NumericExpr,
+    parameters: &[(String, String)],
+    ui: &mut egui::Ui,
+) -> NumericExpr {
+    enum Out {
+        Keep,
+        UnwrapLeft,
+        UnwrapRight,
+    }
+
+    let mut out = Out::Keep;
+
+    ui.label("(");
+    let lhs = Box::new(numeric_expr(app, lhs, parameters, ui));
+
+    ui.menu_button(operator, |ui| {
+        for other in ["+", "-", "*"] {
+            if ui.button(other).clicked() {
+                operator = other;
+            }
+        }
+        if ui.button("Unwrap left").clicked() {
+            out = Out::UnwrapLeft;
+        }
+        if ui.button("Unwrap right").clicked() {
+            out = Out::UnwrapRight;
+        }
+    });
+
+    let rhs = Box::new(numeric_expr(app, rhs, parameters, ui));
+    ui.label(")");
+
+    match (out, operator) {
+        (Out::UnwrapLeft, _) => *lhs,
+        (Out::UnwrapRight, _) => *rhs,
+        (Out::Keep, "+") => NumericExpr::Add(lhs, rhs),
+        (Out::Keep, "-") => NumericExpr::Sub(lhs, rhs),
+        (Out::Keep, _) => NumericExpr::Mul(lhs, rhs),
+    }
+}
+
+/// Menu entries wrapping the expression in an arithmetic operation with 0 on the right side.
+fn arithmetic_buttons(ui: &mut egui::Ui, expr: &NumericExpr) -> Option<NumericExpr> {
+    type Operation = fn(Box<NumericExpr>, Box<NumericExpr>) -> NumericExpr;
+    let operations: [(&str, Operation); 3] = [
+        ("Add", NumericExpr::Add),
+        ("Subtract", NumericExpr::Sub),
+        ("Multiply", NumericExpr::Mul),
+    ];
+
+    let mut out = None;
+    for (label, operation) in operations {
+        if ui.button(label).clicked() {
+            out = Some(operation(
+                Box::new(expr.clone()),
+                Box::new(NumericExpr::Number(0)),
+            ));
+        }
+    }
+    out
+}
+
+/// Editor for a fluent and its arguments. `menu` adds entries to the menu for choosing the fluent.
+fn fluent_term(
+    app: &mut PredicateImprovApp,
+    mut signature: PredicateSignature,
+    mut variables: Vec<Symbol>,
+    parameters: &[(String, String)],
+    ui: &mut egui::Ui,
+    menu: impl FnOnce(&mut egui::Ui),
+) -> (PredicateSignature, Vec<Symbol>) {
+    ui.menu_button(signature.function.0.clone(), |ui| {
+        for fs in app.domain.fluents.keys() {
+            if ui
+                .button(format!("{}/{}", fs.function.0, fs.arity))
+                .clicked()
+            {
+                signature = fs.clone();
+            }
+        }
+
+        menu(ui);
+    });
+
+    variables.resize(signature.arity as usize, Symbol::default());
+
+    let variable_types = app.domain.fluents.get(&signature);
+    for (i, vc) in variables.iter_mut().enumerate() {
+        let expected = variable_types
+            .and_then(|types| types.get(i))
+            .map(|var| var.r#type.clone())
+            .unwrap_or_default();
+        let candidates = symbol_candidates(&app.domain, app.problem(), parameters, &expected);
+
+        symbol_input(ui, &mut vc.0, &candidates, 80.0);
+    }
+
+    (signature, variables)
+}
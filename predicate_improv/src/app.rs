@@ -1,28 +1,112 @@
-use std::mem::take;
+use std::{fmt::Display, mem::take, sync::Arc};
 
-use eframe::egui::{self, Frame, Margin, Stroke, Vec2, WidgetText};
+use eframe::egui::{
+    self, Color32, Frame, Margin, RichText, Stroke, TextFormat, Vec2, WidgetText, text::LayoutJob,
+};
 
 use crate::{
-    action_panel::ActionPanel,
-    predicate_panel::PredicatePanel,
-    problem_panel::ProblemPanel,
-    story::{
-        Action, PredicateDomain, PredicateProblem, PredicateSignature, PredicateStory, Symbol,
-        TypeName, TypedSymbol,
-    },
-    types_and_constants_panel::TypesAndConstantsPanel,
+    action_panel::{ActionDraft, ActionPanel},
+    diversity::{DiversitySettings, StoryDistance},
+    generation::{Generation, TENSION_CANDIDATES, default_threads},
+    history::{Change, Edit, History},
+    metrics::{Ranking, StoryMetric},
+    predicate_panel::{PredicateDraft, PredicatePanel},
+    problem_panel::{BoundPredicateDraft, FluentValueDraft, ProblemPanel},
+    rename::{RenameError, RenameTarget, rename},
+    search::{Heuristic, SearchAlgorithm, SearchSettings, SearchStats},
+    session::{SESSION_KEY, Session, recovery_modal},
+    story::{PredicateDomain, PredicateProblem, PredicateStory, TypeName},
+    story_graph_panel::StoryGraphPanel,
+    story_inspector_panel::StoryInspectorPanel,
+    task_panel::{TaskDraft, TaskPanel},
+    types_and_constants_panel::{ConstantDraft, TypeDraft, TypesAndConstantsPanel},
+    validator_panel::ValidatorPanel,
 };
 
 #[derive(Debug, Default)]
 pub struct PredicateImprovApp {
     pub domain: PredicateDomain,
-    pub problem: PredicateProblem,
+    /// Never empty, so that there always is a selected problem.
+    pub problems: Vec<PredicateProblem>,
+    pub selected_problem: usize,
     pub story: Option<PredicateStory>,
     pub action_panel: ActionPanel,
     pub predicate_panel: PredicatePanel,
     pub types_and_constants_panel: TypesAndConstantsPanel,
     pub problem_panel: ProblemPanel,
+    pub task_panel: TaskPanel,
+    pub story_inspector_panel: StoryInspectorPanel,
+    pub story_graph_panel: StoryGraphPanel,
+    pub validator_panel: ValidatorPanel,
+    pub rename_draft: Option<RenameDraft>,
+    pub history: History,
+    /// A session saved by an earlier run, until the user chooses to restore or discard it.
+    pub recovered_session: Option<Session>,
     pub viewing_story: bool,
+    pub generation_mode: GenerationMode,
+    pub search_settings: SearchSettings,
+    /// What the last heuristic search did.
+    pub search_stats: Option<SearchStats>,
+    /// The generation running in the background, if any.
+    pub generation: Option<Generation>,
+    /// How many threads generation runs on.
+    pub threads: usize,
+    pub diversity: DiversitySettings,
+    /// The stories found by the last generation, one of which is `story`.
+    pub stories: Vec<PredicateStory>,
+    /// The domain and problem the last generation started from, which its stories are ranked
+    /// against even after later edits.
+    pub generated_for: Option<(Arc<PredicateDomain>, Arc<PredicateProblem>)>,
+    pub selected_story: usize,
+    /// How the stories are ordered once a generation is done.
+    pub ranking: Ranking,
+}
+
+/// How the Generate button finds a story.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GenerationMode {
+    /// Random applicable steps, or the decomposition of the outline if the problem has one.
+    #[default]
+    Improvise,
+    /// A partial-order plan with causal links, put in one order at random.
+    PartialOrder,
+    /// Heuristic search towards the goal.
+    Search,
+}
+
+impl GenerationMode {
+    pub const ALL: [GenerationMode; 3] = [
+        GenerationMode::Improvise,
+        GenerationMode::PartialOrder,
+        GenerationMode::Search,
+    ];
+}
+
+impl Display for GenerationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationMode::Improvise => write!(f, "Improvise"),
+            GenerationMode::PartialOrder => write!(f, "Partial-order plan"),
+            GenerationMode::Search => write!(f, "Heuristic search"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RenameDraft {
+    pub target: RenameTarget,
+    pub new_name: String,
+    error: Option<RenameError>,
+}
+
+impl RenameDraft {
+    pub fn new(target: RenameTarget) -> Self {
+        Self {
+            new_name: target.name().into(),
+            target,
+            error: None,
+        }
+    }
 }
 
 impl PredicateImprovApp {
@@ -64,13 +148,87 @@ impl PredicateImprovApp {
 
         Self {
             domain: PredicateDomain::default(),
-            problem: PredicateProblem::default(),
+            problems: vec![PredicateProblem {
+                name: "Problem 1".into(),
+                ..Default::default()
+            }],
+            selected_problem: 0,
             action_panel: ActionPanel::default(),
             predicate_panel: PredicatePanel::default(),
             types_and_constants_panel: TypesAndConstantsPanel::default(),
             problem_panel: ProblemPanel::default(),
+            task_panel: TaskPanel::default(),
+            story_inspector_panel: StoryInspectorPanel::default(),
+            story_graph_panel: StoryGraphPanel::default(),
+            validator_panel: ValidatorPanel::default(),
+            rename_draft: None,
+            history: History::default(),
+            recovered_session: cc
+                .storage
+                .and_then(|storage| eframe::get_value::<Session>(storage, SESSION_KEY))
+                .filter(|session| !session.is_empty()),
             story: None,
             viewing_story: false,
+            generation_mode: GenerationMode::default(),
+            search_settings: SearchSettings::default(),
+            search_stats: None,
+            generation: None,
+            threads: default_threads(),
+            diversity: DiversitySettings::default(),
+            stories: Vec::new(),
+            generated_for: None,
+            selected_story: 0,
+            ranking: Ranking::default(),
+        }
+    }
+
+    /// The problem selected in the problem panel, which stories are generated and checked against.
+    pub fn problem(&self) -> &PredicateProblem {
+        &self.problems[self.selected_problem]
+    }
+
+    pub fn problem_mut(&mut self) -> &mut PredicateProblem {
+        &mut self.problems[self.selected_problem]
+    }
+
+    /// Shows one of the stories found by the last generation.
+    pub fn select_story(&mut self, index: usize) {
+        self.selected_story = index;
+        self.story = self.stories.get(index).cloned();
+        self.story_inspector_panel.selected_step = 0;
+    }
+
+    /// Orders the stories from the best to the worst by the ranking, and shows the best.
+    pub fn rank_stories(&mut self) {
+        let Some((domain, problem)) = &self.generated_for else {
+            return;
+        };
+        domain.rank_stories(problem, &mut self.stories, &self.ranking);
+        self.select_story(0);
+    }
+
+    /// Takes the stories the running generation found since the last frame, showing the first,
+    /// and ranks them once the generation is done.
+    fn receive_stories(&mut self) {
+        let Some(generation) = &self.generation else {
+            return;
+        };
+        let update = generation.poll();
+
+        let first = self.stories.is_empty() && !update.stories.is_empty();
+        self.stories.extend(update.stories);
+        if first {
+            self.select_story(0);
+            self.viewing_story = true;
+        }
+
+        if update.finished {
+            self.search_stats = update.stats;
+            self.generation = None;
+            self.viewing_story |= self.stories.is_empty();
+            if self.stories.len() > 1 {
+                self.rank_stories();
+            }
         }
     }
 }
@@ -79,6 +237,26 @@ impl eframe::App for PredicateImprovApp {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
         let style = ctx.style();
 
+        // Text fields have their own undo, and items being edited in a modal are temporarily
+        // removed from the domain, so only undo when neither is in use.
+        if !ctx.wants_keyboard_input() && ctx.memory(|mem| mem.top_modal_layer().is_none()) {
+            use egui::{Key, KeyboardShortcut, Modifiers};
+
+            let redo = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+            let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+
+            if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+                self.history.redo(&mut self.domain, &mut self.problems);
+            } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+                self.history.undo(&mut self.domain, &mut self.problems);
+            }
+        }
+
+        self.receive_stories();
+
+        // Undoing the addition of a problem may remove the selected one.
+        self.selected_problem = self.selected_problem.min(self.problems.len() - 1);
+
         egui::SidePanel::left("predicate_panel")
             .frame(Frame::window(&style).stroke(Stroke::NONE).inner_margin(16))
             .default_width(ctx.viewport_rect().width() / 4.)
@@ -94,6 +272,11 @@ impl eframe::App for PredicateImprovApp {
             .default_height(ctx.viewport_rect().height() / 4.)
             .show(ctx, |ui| ActionPanel::show(ui, self));
 
+        egui::TopBottomPanel::bottom("task_panel")
+            .frame(Frame::window(&style).stroke(Stroke::NONE).inner_margin(16))
+            .default_height(ctx.viewport_rect().height() / 4.)
+            .show(ctx, |ui| TaskPanel::show(ui, self));
+
         egui::TopBottomPanel::bottom("types_and_constants_panel")
             .frame(Frame::window(&style).stroke(Stroke::NONE).inner_margin(16))
             .default_height(ctx.viewport_rect().height() / 4.)
@@ -107,8 +290,32 @@ impl eframe::App for PredicateImprovApp {
                 ui.horizontal(|ui| {
                     ui.heading("Predicate Improvizer");
 
-                    let generate_text =
-                        format!("Generate {}", egui_material_icons::icons::ICON_CASINO);
+                    if ui
+                        .add_enabled(
+                            self.history.can_undo(),
+                            egui::Button::new(egui_material_icons::icons::ICON_UNDO),
+                        )
+                        .on_hover_text("Undo (Ctrl+Z)")
+                        .clicked()
+                    {
+                        self.history.undo(&mut self.domain, &mut self.problems);
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.history.can_redo(),
+                            egui::Button::new(egui_material_icons::icons::ICON_REDO),
+                        )
+                        .on_hover_text("Redo (Ctrl+Shift+Z)")
+                        .clicked()
+                    {
+                        self.history.redo(&mut self.domain, &mut self.problems);
+                    }
+
+                    let generate_text = match self.generation {
+                        Some(_) => format!("Cancel {}", egui_material_icons::icons::ICON_CLOSE),
+                        None => format!("Generate {}", egui_material_icons::icons::ICON_CASINO),
+                    };
 
                     let generate_response =
                         ui.add_visible(false, egui::Button::new(&generate_text));
@@ -118,21 +325,75 @@ impl eframe::App for PredicateImprovApp {
                     );
 
                     if ui.button(generate_text).clicked() {
-                        self.story = self.domain.generate_story();
-                        self.viewing_story = true;
+                        match &self.generation {
+                            Some(generation) => generation.progress.cancel(),
+                            None => {
+                                self.search_stats = None;
+                                self.story = None;
+                                self.stories.clear();
+                                let domain = Arc::new(self.domain.clone());
+                                let problem = Arc::new(self.problem().clone());
+                                self.generated_for = Some((domain.clone(), problem.clone()));
+                                self.generation = Some(Generation::start(
+                                    ui.ctx(),
+                                    domain,
+                                    problem,
+                                    self.generation_mode,
+                                    self.search_settings.clone(),
+                                    self.diversity,
+                                    self.threads,
+                                ));
+                            }
+                        }
                     }
                 });
 
                 ui.horizontal(|ui| {
                     ui.spacing_mut().slider_width = 300.;
-                    ui.add(egui::Slider::new(
-                        &mut self.problem.max_story_length,
-                        0..=10,
-                    ));
+                    let problem = &mut self.problems[self.selected_problem];
+                    let lengths = (problem.max_story_length, problem.min_story_length);
+                    let response = ui.add(egui::Slider::new(&mut problem.max_story_length, 0..=10));
+                    problem.min_story_length =
+                        problem.min_story_length.min(problem.max_story_length);
+                    if let Some(before) = finished_edit(ui, &response, lengths) {
+                        let after = (problem.max_story_length, problem.min_story_length);
+                        self.history.record(Edit::StoryLength(
+                            self.selected_problem,
+                            Change {
+                                before: Some(before),
+                                after: Some(after),
+                            },
+                        ));
+                    }
                     ui.add_space(12.);
                     ui.label("Story length");
                 });
 
+                ui.horizontal(|ui| {
+                    let problem = &mut self.problems[self.selected_problem];
+                    ui.spacing_mut().slider_width = 300.;
+                    let lengths = (problem.max_story_length, problem.min_story_length);
+                    let response = ui.add(egui::Slider::new(
+                        &mut problem.min_story_length,
+                        0..=problem.max_story_length,
+                    ));
+                    if let Some(before) = finished_edit(ui, &response, lengths) {
+                        let after = (problem.max_story_length, problem.min_story_length);
+                        self.history.record(Edit::StoryLength(
+                            self.selected_problem,
+                            Change {
+                                before: Some(before),
+                                after: Some(after),
+                            },
+                        ));
+                    }
+                    ui.add_space(12.);
+                    ui.label("Minimum length");
+                });
+
+                generation_settings(ui, self);
+                generation_progress(ui, self);
+
                 // ui.horizontal(|ui| {
                 //     ui.add_sized(
                 //         [ui.available_width() - 30., 20.],
@@ -152,6 +413,8 @@ impl eframe::App for PredicateImprovApp {
                             .show(ui, |ui| {
                                 ui.spacing_mut().item_spacing.y = 6.;
 
+                                ValidatorPanel::show(ui, self);
+
                                 // self.domain.predicates.retain(|predicate, variables| {
                                 //     Frame::new()
                                 //         .fill(Color32::from_rgb(36, 36, 36))
@@ -178,134 +441,501 @@ impl eframe::App for PredicateImprovApp {
                     });
             });
 
+        let mut viewing_story = self.viewing_story;
         egui::Window::new("My story")
             .collapsible(false)
             .fixed_size(ctx.viewport_rect().size() - Vec2::new(50., 100.))
             .resizable(false)
-            .open(&mut self.viewing_story)
+            .open(&mut viewing_story)
             .show(ctx, |ui| {
                 ui.set_width(ui.available_width());
                 ui.set_height(ui.available_height());
-                if let Some(story) = &self.story {
-                    ui.label(&story.text);
-                }
+                StoryInspectorPanel::show(ui, self);
             });
+        self.viewing_story = viewing_story;
+
+        if self.recovered_session.is_some() {
+            egui::Modal::new("recovery_modal".into()).show(ctx, |ui| recovery_modal(self, ui));
+        }
+
+        if self.rename_draft.is_some() {
+            egui::Modal::new("rename_modal".into()).show(ctx, |ui| rename_modal(self, ui));
+        }
+
+        if self.predicate_panel.add_predicate
+            && self.predicate_panel.predicate_draft.derived.is_some()
+        {
+            let (signature, axiom) = take(&mut self.predicate_panel.predicate_draft).into_axiom();
+            let before = take(&mut self.predicate_panel.backup_predicate_draft)
+                .map(PredicateDraft::into_axiom);
+
+            self.domain.axioms.insert(signature.clone(), axiom.clone());
+            self.history.record(Edit::Axiom(Box::new(Change {
+                before,
+                after: Some((signature, axiom)),
+            })));
+
+            self.predicate_panel.add_predicate = false;
+        }
 
         if self.predicate_panel.add_predicate {
-            let signature = PredicateSignature {
-                function: Symbol(take(&mut (self.predicate_panel.predicate_draft.function))),
-                arity: self.predicate_panel.predicate_draft.variables.len() as u32,
+            let numeric = self.predicate_panel.predicate_draft.numeric;
+            let (signature, variables) =
+                take(&mut self.predicate_panel.predicate_draft).into_predicate();
+            let before = take(&mut self.predicate_panel.backup_predicate_draft)
+                .map(PredicateDraft::into_predicate);
+            let change = Change {
+                before,
+                after: Some((signature.clone(), variables.clone())),
             };
 
-            let variables = take(&mut self.predicate_panel.predicate_draft.variables)
-                .iter_mut()
-                .map(|(var, ty)| TypedSymbol {
-                    name: Symbol(take(var)),
-                    r#type: TypeName(take(ty)),
-                })
-                .collect();
-
-            self.domain.predicates.insert(signature, variables);
+            if numeric {
+                self.domain.fluents.insert(signature, variables);
+                self.history.record(Edit::Fluent(change));
+            } else {
+                self.domain.predicates.insert(signature, variables);
+                self.history.record(Edit::Predicate(change));
+            }
 
             self.predicate_panel.add_predicate = false;
         }
 
         if self.action_panel.add_action {
-            let name = Symbol(take(&mut self.action_panel.action_draft.name));
-            let parameters = self
-                .action_panel
-                .action_draft
-                .parameters
-                .iter_mut()
-                .map(|(param, ty)| TypedSymbol {
-                    name: Symbol(take(param)),
-                    r#type: TypeName(take(ty)),
-                })
-                .collect();
-            let action = Action {
-                parameters,
-                precondition: take(&mut self.action_panel.action_draft.precondition),
-                effect: take(&mut self.action_panel.action_draft.effect),
-            };
+            let (name, action) = take(&mut self.action_panel.action_draft).into_action();
+            let before =
+                take(&mut self.action_panel.backup_action_draft).map(ActionDraft::into_action);
 
-            self.domain.actions.insert(name, action);
+            self.domain.actions.insert(name.clone(), action.clone());
+            self.history.record(Edit::Action(Box::new(Change {
+                before,
+                after: Some((name, action)),
+            })));
 
             self.action_panel.add_action = false;
         }
 
+        if self.task_panel.add_task {
+            let (name, task) = take(&mut self.task_panel.task_draft).into_task();
+            let before = take(&mut self.task_panel.backup_task_draft).map(TaskDraft::into_task);
+
+            self.domain.tasks.insert(name.clone(), task.clone());
+            self.history.record(Edit::Task(Box::new(Change {
+                before,
+                after: Some((name, task)),
+            })));
+
+            self.task_panel.add_task = false;
+        }
+
         if self.types_and_constants_panel.add_type {
-            let name = TypeName(take(&mut self.types_and_constants_panel.type_draft.name));
-            let supertypes = self
-                .types_and_constants_panel
-                .type_draft
-                .supertypes
-                .iter_mut()
-                .map(|ty| TypeName(take(ty)))
-                .collect();
+            let (name, supertypes) =
+                take(&mut self.types_and_constants_panel.type_draft).into_type();
+            let before = take(&mut self.types_and_constants_panel.backup_type_draft)
+                .map(TypeDraft::into_type);
 
-            self.domain.types.insert(name, supertypes);
+            self.domain.types.insert(name.clone(), supertypes.clone());
+            self.history.record(Edit::Type(Change {
+                before,
+                after: Some((name, supertypes)),
+            }));
 
             self.types_and_constants_panel.add_type = false;
         }
 
         if self.types_and_constants_panel.add_constant {
-            let name = Symbol(take(
-                &mut self.types_and_constants_panel.constant_draft.name,
-            ));
-            let r#type = TypeName(take(
-                &mut self.types_and_constants_panel.constant_draft.r#type,
-            ));
+            let (name, r#type) =
+                take(&mut self.types_and_constants_panel.constant_draft).into_constant();
+            let before = take(&mut self.types_and_constants_panel.backup_constant_draft)
+                .map(ConstantDraft::into_constant);
 
-            self.domain.constants.insert(name, r#type);
+            self.domain.constants.insert(name.clone(), r#type.clone());
+            self.history.record(Edit::Constant(Change {
+                before,
+                after: Some((name, r#type)),
+            }));
 
             self.types_and_constants_panel.add_constant = false;
         }
 
         if self.problem_panel.add_bound_predicate {
-            let signature = PredicateSignature {
-                function: Symbol(take(
-                    &mut self.problem_panel.bound_predicate_draft.predicate_name,
-                )),
-                arity: self.problem_panel.bound_predicate_draft.bound_objects.len() as u32,
-            };
-            let bindings = take(
-                &mut self
-                    .problem_panel
-                    .bound_predicate_draft
-                    .bound_objects
-                    .iter_mut(),
-            )
-            .map(|b| Symbol(take(b)))
-            .collect();
-
-            self.problem
+            let bound_predicate =
+                take(&mut self.problem_panel.bound_predicate_draft).into_bound_predicate();
+            let before = take(&mut self.problem_panel.backup_bound_predicate_draft)
+                .map(BoundPredicateDraft::into_bound_predicate);
+
+            self.problem_mut()
                 .initial_state
                 .bound_predicates
-                .insert(signature, bindings);
+                .push(bound_predicate.clone());
+            self.history.record(Edit::BoundPredicate(
+                self.selected_problem,
+                Change {
+                    before,
+                    after: Some(bound_predicate),
+                },
+            ));
 
             self.problem_panel.add_bound_predicate = false;
         }
+
+        if self.problem_panel.add_fluent_value {
+            let fluent_value = take(&mut self.problem_panel.fluent_value_draft).into_fluent_value();
+            let before = take(&mut self.problem_panel.backup_fluent_value_draft)
+                .map(FluentValueDraft::into_fluent_value);
+
+            self.problem_mut()
+                .initial_state
+                .fluent_values
+                .push(fluent_value.clone());
+            self.history.record(Edit::FluentValue(
+                self.selected_problem,
+                Change {
+                    before,
+                    after: Some(fluent_value),
+                },
+            ));
+
+            self.problem_panel.add_fluent_value = false;
+        }
+    }
+
+    /// Called by eframe periodically and on exit.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // Don't overwrite the recovered session before the user had a chance to restore it.
+        match &self.recovered_session {
+            Some(session) => eframe::set_value(storage, SESSION_KEY, session),
+            None => eframe::set_value(storage, SESSION_KEY, &Session::capture(self)),
+        }
+    }
+
+    fn auto_save_interval(&self) -> std::time::Duration {
+        AUTO_SAVE_INTERVAL
+    }
+}
+
+/// Picks how stories are generated, along with the settings of heuristic search.
+fn generation_settings(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("generation_mode")
+            .selected_text(app.generation_mode.to_string())
+            .show_ui(ui, |ui| {
+                for mode in GenerationMode::ALL {
+                    ui.selectable_value(&mut app.generation_mode, mode, mode.to_string());
+                }
+            });
+
+        ui.add(egui::DragValue::new(&mut app.threads).range(1..=256));
+        ui.label("threads");
+
+        if app.generation_mode == GenerationMode::PartialOrder {
+            ui.label(RichText::new("only actions with conjunctions of literals").weak());
+        }
+    });
+
+    let diversity = &mut app.diversity;
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut diversity.count).range(1..=20));
+        ui.label("stories, at least");
+        ui.add(
+            egui::DragValue::new(&mut diversity.min_distance)
+                .range(0.0..=1.0)
+                .speed(0.01),
+        );
+        egui::ComboBox::from_id_salt("story_distance")
+            .selected_text(diversity.distance.to_string().to_lowercase())
+            .show_ui(ui, |ui| {
+                for distance in StoryDistance::ALL {
+                    ui.selectable_value(
+                        &mut diversity.distance,
+                        distance,
+                        distance.to_string().to_lowercase(),
+                    );
+                }
+            });
+    });
+
+    if !app.problem().tension.is_empty() {
+        ui.label(
+            RichText::new(format!(
+                "Stories are picked from up to {} candidates by how well their tension follows \
+                 the arc",
+                app.diversity.count * TENSION_CANDIDATES
+            ))
+            .weak(),
+        );
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Rank by");
+        for (metric, weight) in StoryMetric::ALL.iter().zip(&mut app.ranking.weights) {
+            ui.add(
+                egui::DragValue::new(weight)
+                    .range(-10.0..=10.0)
+                    .speed(0.05)
+                    .max_decimals(2),
+            );
+            ui.label(metric.to_string().to_lowercase());
+        }
+    });
+
+    if app.generation_mode != GenerationMode::Search {
+        return;
+    }
+
+    let settings = &mut app.search_settings;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("search_algorithm")
+            .selected_text(settings.algorithm.to_string())
+            .show_ui(ui, |ui| {
+                for algorithm in SearchAlgorithm::ALL {
+                    ui.selectable_value(&mut settings.algorithm, algorithm, algorithm.to_string());
+                }
+            });
+
+        egui::ComboBox::from_id_salt("search_heuristic")
+            .selected_text(settings.heuristic.to_string())
+            .show_ui(ui, |ui| {
+                for heuristic in Heuristic::ALL {
+                    ui.selectable_value(&mut settings.heuristic, heuristic, heuristic.to_string());
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut settings.max_expansions)
+                .range(1..=10_000_000)
+                .speed(1000),
+        );
+        ui.label("expansions at most");
+
+        let mut seconds = settings.time_limit.as_secs_f32();
+        ui.add(
+            egui::DragValue::new(&mut seconds)
+                .range(0.1..=600.)
+                .speed(0.1)
+                .suffix(" s"),
+        );
+        settings.time_limit = std::time::Duration::from_secs_f32(seconds);
+        ui.label("time limit");
+    });
+
+    if let Some(stats) = &app.search_stats {
+        ui.label(RichText::new(stats.to_string()).weak());
+        ui.label(RichText::new(stats.grounding.to_string()).weak());
+    }
+}
+
+/// Shows how far the running generation got, and lets the user pick one of the stories found.
+fn generation_progress(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    if let Some(generation) = &app.generation {
+        let done = match generation.mode {
+            GenerationMode::Search => "states expanded",
+            GenerationMode::Improvise | GenerationMode::PartialOrder => "attempts",
+        };
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(format!(
+                "{} {done}, {} stories found in {:.1} s",
+                generation.progress.done(),
+                app.stories.len(),
+                generation.started.elapsed().as_secs_f32()
+            ));
+        });
+        // Progress is not an input event, so keep repainting to show it.
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_millis(100));
+    }
+
+    if app.stories.len() > 1 {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Stories");
+            for i in 0..app.stories.len() {
+                if ui
+                    .selectable_label(app.selected_story == i, (i + 1).to_string())
+                    .clicked()
+                {
+                    app.select_story(i);
+                }
+            }
+            if app.generation.is_none() && ui.button("Rank").clicked() {
+                app.rank_stories();
+            }
+        });
+    }
+}
+
+/// How often the session is saved while the app is running.
+const AUTO_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn rename_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
+    let Some(draft) = &mut app.rename_draft else {
+        return;
+    };
+    let mut close = false;
+
+    ui.horizontal(|ui| {
+        ui.label(format!("rename `{}` to:", draft.target.name()));
+        ui.text_edit_singleline(&mut draft.new_name);
+    });
+
+    if let Some(err) = &draft.error {
+        ui.label(RichText::new(err.to_string()).color(Color32::LIGHT_RED));
+    }
+
+    ui.horizontal(|ui| {
+        if ui
+            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .clicked()
+        {
+            let before = (app.domain.clone(), app.problems.clone());
+
+            match rename(
+                &mut app.domain,
+                &mut app.problems,
+                &draft.target,
+                &draft.new_name,
+            ) {
+                Ok(()) => {
+                    app.history.record(Edit::Snapshot(Box::new(Change {
+                        before: Some(before),
+                        after: Some((app.domain.clone(), app.problems.clone())),
+                    })));
+                    close = true;
+                }
+                Err(err) => draft.error = Some(err),
+            }
+        }
+
+        if ui
+            .add(egui::Button::new("Cancel").min_size(Vec2::new(ui.available_width(), 0.)))
+            .clicked()
+        {
+            close = true;
+        }
+    });
+
+    if close {
+        app.rename_draft = None;
     }
 }
 
 pub fn untyped_object_input(
     ui: &mut egui::Ui,
     var: &mut String,
+    candidates: &[String],
     text: impl Into<WidgetText>,
 ) -> bool {
     ui.horizontal(|ui| {
         ui.label(text);
 
         let input_width = (ui.available_width() - 150.) / 2.;
-        egui::TextEdit::singleline(var)
-            .desired_width(input_width)
-            .show(ui);
+        symbol_input(ui, var, candidates, input_width);
 
         !ui.button(egui_material_icons::icons::ICON_REMOVE).clicked()
     })
     .inner
 }
 
+/// A text field for a symbol that suggests the candidates containing the typed text while it is
+/// focused, and underlines the symbol if it is not one of the candidates.
+pub fn symbol_input(
+    ui: &mut egui::Ui,
+    symbol: &mut String,
+    candidates: &[String],
+    width: f32,
+) -> egui::Response {
+    let unknown = !symbol.is_empty() && !candidates.contains(symbol);
+
+    let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+        let mut format = TextFormat::simple(
+            egui::TextStyle::Body.resolve(ui.style()),
+            ui.visuals().text_color(),
+        );
+        if unknown {
+            format.underline = Stroke::new(2., Color32::LIGHT_RED);
+        }
+
+        let mut job = LayoutJob::single_section(text.as_str().to_owned(), format);
+        job.wrap.max_width = wrap_width;
+        ui.fonts_mut(|fonts| fonts.layout_job(job))
+    };
+
+    let mut response = egui::TextEdit::singleline(symbol)
+        .desired_width(width)
+        .layouter(&mut layouter)
+        .show(ui)
+        .response;
+
+    if unknown {
+        response = response.on_hover_text(format!("`{symbol}` is not a known symbol of this type"));
+    }
+
+    let matches: Vec<&String> = candidates
+        .iter()
+        .filter(|candidate| *candidate != symbol && candidate.contains(symbol.as_str()))
+        .collect();
+
+    // Clicking a suggestion takes the focus away from the text field, so keep the popup open
+    // while the pointer is over it.
+    let popup = egui::Popup::from_response(&response);
+    let open_id = response.id.with("completion_open");
+    let was_open = ui.data(|data| data.get_temp(open_id).unwrap_or(false));
+    let hovered = was_open
+        && popup
+            .get_popup_rect()
+            .is_some_and(|rect| ui.rect_contains_pointer(rect));
+    let open = !matches.is_empty() && (response.has_focus() || hovered);
+    ui.data_mut(|data| data.insert_temp(open_id, open));
+
+    popup
+        .open(open)
+        .width(width.max(100.))
+        .close_behavior(egui::PopupCloseBehavior::IgnoreClicks)
+        .show(|ui| {
+            for candidate in matches {
+                if ui.button(candidate).clicked() {
+                    *symbol = candidate.clone();
+                    response.mark_changed();
+                }
+            }
+        });
+
+    response
+}
+
+/// The symbols of the expected type that can be used as arguments: the given action parameters,
+/// the domain constants and the problem objects.
+pub fn symbol_candidates(
+    domain: &PredicateDomain,
+    problem: &PredicateProblem,
+    parameters: &[(String, String)],
+    expected: &TypeName,
+) -> Vec<String> {
+    let parameters = parameters
+        .iter()
+        .map(|(name, ty)| (name.clone(), TypeName(ty.clone())));
+    let constants = domain
+        .constants
+        .iter()
+        .map(|(name, ty)| (name.0.clone(), ty.clone()));
+    let objects = problem
+        .objects
+        .iter()
+        .map(|obj| (obj.name.0.clone(), obj.r#type.clone()));
+
+    let mut candidates: Vec<String> = parameters
+        .chain(constants)
+        .chain(objects)
+        .filter(|(name, ty)| !name.is_empty() && domain.is_subtype(ty, expected))
+        .map(|(name, _)| name)
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
 pub fn type_button(domain: &PredicateDomain, ui: &mut egui::Ui, ty: &mut String) {
     let ty_text = if ty.is_empty() {
         String::from("<object>")
@@ -332,6 +962,38 @@ pub fn type_button(domain: &PredicateDomain, ui: &mut egui::Ui, ty: &mut String)
         });
 }
 
+/// Returns the value from before an edit through the widget once the edit is done: when a drag
+/// ends, or right away for clicks and typing. `value` is the value at the start of this frame and
+/// is kept in memory while the widget is dragged.
+pub fn finished_edit<T: Clone + Default + Send + Sync + 'static>(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    value: T,
+) -> Option<T> {
+    if response.drag_started() {
+        ui.data_mut(|data| data.insert_temp(response.id, value.clone()));
+    }
+
+    if response.drag_stopped() {
+        ui.data_mut(|data| data.remove_temp(response.id))
+    } else if response.changed() && !response.dragged() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Shows that an item of the same name already exists, if `taken`. Submitting the draft would
+/// replace that item, so edit modals disable their submit button while this returns true.
+pub fn name_taken(ui: &mut egui::Ui, taken: bool, name: String) -> bool {
+    if taken {
+        ui.label(
+            RichText::new(RenameError::AlreadyExists(name).to_string()).color(Color32::LIGHT_RED),
+        );
+    }
+    taken
+}
+
 pub fn typed_object_input(
     domain: &PredicateDomain,
     ui: &mut egui::Ui,
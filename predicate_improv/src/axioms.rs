@@ -0,0 +1,424 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::story::{
+    Atom, Axiom, Bindings, LogicExpr, PredicateDomain, PredicateSignature, StoryState, Symbol,
+    TypeName, TypedSymbol,
+};
+
+/// Why the axioms of a domain cannot be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AxiomError {
+    /// The derived predicate depends on its own negation, possibly through other derived
+    /// predicates, so whether it holds is not well defined.
+    NegativeCycle(PredicateSignature),
+    /// The effect of the action sets a derived predicate, which only its axiom may do.
+    DerivedEffect {
+        action: Symbol,
+        predicate: PredicateSignature,
+    },
+}
+
+impl Display for AxiomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AxiomError::NegativeCycle(signature) => write!(
+                f,
+                "`{}/{}` depends on its own negation",
+                signature.function.0, signature.arity
+            ),
+            AxiomError::DerivedEffect { action, predicate } => write!(
+                f,
+                "the effect of `{}` changes `{}/{}`, which is derived",
+                action.0, predicate.function.0, predicate.arity
+            ),
+        }
+    }
+}
+
+/// The axioms of a domain prepared for the states of one story, so the strata and the atoms each
+/// variable ranges over are only worked out once.
+#[derive(Debug, Default)]
+pub struct Derivation {
+    strata: Vec<Vec<PreparedAxiom>>,
+    /// Every atom of the story, which the free variables of the bodies range over.
+    everything: Vec<Atom>,
+}
+
+#[derive(Debug)]
+struct PreparedAxiom {
+    signature: PredicateSignature,
+    axiom: Axiom,
+    /// The atoms each variable of the derived predicate ranges over.
+    candidates: Vec<Vec<Atom>>,
+    /// The variables that only appear in the body.
+    free: Vec<Symbol>,
+}
+
+impl PredicateDomain {
+    /// Prepares the axioms for deriving facts in the given state and those following from it.
+    pub fn derivation(&self, state: &StoryState) -> Derivation {
+        // Axioms that depend on their own negation are rejected by the validator, but still derive
+        // what they can.
+        let strata = self
+            .axiom_strata()
+            .unwrap_or_else(|_| vec![self.axioms.keys().collect()]);
+
+        let strata = strata
+            .into_iter()
+            .map(|stratum| {
+                stratum
+                    .into_iter()
+                    .map(|signature| {
+                        let axiom = &self.axioms[signature];
+                        PreparedAxiom {
+                            signature: signature.clone(),
+                            axiom: axiom.clone(),
+                            candidates: axiom
+                                .variables
+                                .iter()
+                                .map(|var| state.atoms_of_type(self, &var.r#type))
+                                .collect(),
+                            free: free_variables(&axiom.body, &axiom.variables),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Derivation {
+            strata,
+            everything: state.atoms_of_type(self, &TypeName::default()),
+        }
+    }
+
+    /// Replaces the derived facts of the state with those following from the axioms of the
+    /// domain. Derived predicates may be defined in terms of each other, as long as none depends
+    /// on its own negation: each stratum is completed before the strata that negate it.
+    ///
+    /// Uses the derivation prepared by `initial_story_state` if the state has one.
+    pub fn derive(&self, state: &mut StoryState) {
+        if self.axioms.is_empty() {
+            return;
+        }
+
+        match state.derivation.clone() {
+            Some(derivation) => derivation.derive(state),
+            None => self.derivation(state).derive(state),
+        }
+    }
+
+    /// Groups the derived predicates into strata, so that every derived predicate comes in the
+    /// same stratum as or after those its body relies on, and strictly after those its body
+    /// negates. Fails if a derived predicate depends on its own negation.
+    pub fn axiom_strata(&self) -> Result<Vec<Vec<&PredicateSignature>>, AxiomError> {
+        let dependencies: Vec<_> = self
+            .axioms
+            .iter()
+            .map(|(signature, axiom)| {
+                let mut dependencies = Vec::new();
+                derived_dependencies(self, &axiom.body, false, &mut dependencies);
+                (signature, dependencies)
+            })
+            .collect();
+
+        // Raise the stratum of each predicate until it is above those it negates. Without a cycle
+        // through negation, no stratum needs to go beyond the number of derived predicates.
+        let mut strata: HashMap<&PredicateSignature, usize> =
+            self.axioms.keys().map(|signature| (signature, 0)).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (signature, dependencies) in &dependencies {
+                for (dependency, negated) in dependencies {
+                    let stratum = strata[dependency] + *negated as usize;
+                    if stratum > strata[signature] {
+                        if stratum >= self.axioms.len() {
+                            return Err(AxiomError::NegativeCycle((*signature).clone()));
+                        }
+                        strata.insert(signature, stratum);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut grouped = vec![Vec::new(); strata.values().max().map_or(0, |max| max + 1)];
+        for (signature, stratum) in strata {
+            grouped[stratum].push(signature);
+        }
+        Ok(grouped)
+    }
+
+    /// Checks that the axioms can be stratified and that no effect changes a derived predicate.
+    pub fn check_axioms(&self) -> Result<(), AxiomError> {
+        self.axiom_strata()?;
+
+        for (name, action) in &self.actions {
+            let mut derived = None;
+            action.effect.for_each_literal(&mut |signature, _, _| {
+                if self.is_derived(signature) {
+                    derived = Some(signature);
+                }
+            });
+            if let Some(predicate) = derived {
+                return Err(AxiomError::DerivedEffect {
+                    action: name.clone(),
+                    predicate: predicate.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the signature belongs to a derived predicate rather than a declared one.
+    pub fn is_derived(&self, signature: &PredicateSignature) -> bool {
+        self.axioms.contains_key(signature)
+    }
+}
+
+impl Derivation {
+    fn derive(&self, state: &mut StoryState) {
+        for prepared in self.strata.iter().flatten() {
+            state.bound_predicates.remove(&prepared.signature);
+        }
+
+        for stratum in &self.strata {
+            // Derived facts only ever get added, so repeat until nothing new follows.
+            let mut changed = true;
+            while changed {
+                changed = false;
+
+                for prepared in stratum {
+                    let mut arguments = Vec::with_capacity(prepared.candidates.len());
+                    changed |= self.derive_groundings(state, prepared, &mut arguments);
+                }
+            }
+        }
+    }
+
+    /// Sets the derived predicate for every grounding starting with `arguments` that satisfies its
+    /// body. Returns whether anything new was derived.
+    fn derive_groundings(
+        &self,
+        state: &mut StoryState,
+        prepared: &PreparedAxiom,
+        arguments: &mut Vec<Atom>,
+    ) -> bool {
+        let Some(atoms) = prepared.candidates.get(arguments.len()) else {
+            if state.holds(&prepared.signature, arguments) {
+                return false;
+            }
+
+            let mut bindings: Bindings = prepared
+                .axiom
+                .variables
+                .iter()
+                .map(|var| var.name.clone())
+                .zip(arguments.iter().copied())
+                .collect();
+            if !self.holds_for_some(state, &prepared.axiom.body, &prepared.free, &mut bindings) {
+                return false;
+            }
+
+            state.set_fact(&prepared.signature, arguments.clone(), true);
+            return true;
+        };
+
+        let mut changed = false;
+        for atom in atoms {
+            arguments.push(*atom);
+            changed |= self.derive_groundings(state, prepared, arguments);
+            arguments.pop();
+        }
+        changed
+    }
+
+    /// Whether the body holds for some binding of the free variables, stopping at the first one.
+    fn holds_for_some(
+        &self,
+        state: &StoryState,
+        body: &LogicExpr,
+        free: &[Symbol],
+        bindings: &mut Bindings,
+    ) -> bool {
+        let Some((var, rest)) = free.split_first() else {
+            return state.evaluate(body, bindings);
+        };
+
+        self.everything.iter().any(|atom| {
+            bindings.insert(var.clone(), *atom);
+            self.holds_for_some(state, body, rest, bindings)
+        })
+    }
+}
+
+/// Collects the derived predicates the truth of the expression depends on, along with whether
+/// they appear under a negation. Beliefs about derived predicates are facts of their own, so they
+/// are skipped.
+fn derived_dependencies<'a>(
+    domain: &PredicateDomain,
+    expr: &'a LogicExpr,
+    negated: bool,
+    out: &mut Vec<(&'a PredicateSignature, bool)>,
+) {
+    match expr {
+        LogicExpr::Predicate(signature, _) if domain.is_derived(signature) => {
+            out.push((signature, negated));
+        }
+        LogicExpr::Not(inner) => derived_dependencies(domain, inner, !negated, out),
+        LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+            derived_dependencies(domain, lhs, negated, out);
+            derived_dependencies(domain, rhs, negated, out);
+        }
+        LogicExpr::True
+        | LogicExpr::Predicate(..)
+        | LogicExpr::Compare(..)
+        | LogicExpr::Update(..)
+        | LogicExpr::Believes(..)
+        | LogicExpr::Learn(..) => {}
+    }
+}
+
+/// The variables of the body that are not variables of the derived predicate, in order of first
+/// appearance.
+pub fn free_variables(body: &LogicExpr, variables: &[TypedSymbol]) -> Vec<Symbol> {
+    let mut free = Vec::new();
+    let mut collect = |symbol: &mut Symbol| {
+        if symbol.0.starts_with('?')
+            && !variables.iter().any(|var| var.name == *symbol)
+            && !free.contains(symbol)
+        {
+            free.push(symbol.clone());
+        }
+    };
+
+    let mut body = body.clone();
+    body.for_each_predicate_mut(&mut |_, symbols| symbols.iter_mut().for_each(&mut collect));
+    body.for_each_fluent_mut(&mut |_, symbols| symbols.iter_mut().for_each(&mut collect));
+    body.for_each_character_mut(&mut collect);
+    free
+}
+
+/// Every way of picking one atom from each list of candidates.
+pub fn groundings(candidates: &[Vec<Atom>]) -> Vec<Vec<Atom>> {
+    candidates
+        .iter()
+        .fold(vec![Vec::new()], |groundings, atoms| {
+            groundings
+                .iter()
+                .flat_map(|prefix| {
+                    atoms.iter().map(move |atom| {
+                        let mut grounding = prefix.clone();
+                        grounding.push(*atom);
+                        grounding
+                    })
+                })
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        story::PredicateProblem,
+        test_util::{add_actions, domain_with, sym},
+    };
+
+    #[test]
+    fn derives_facts_from_other_predicates() {
+        let at = PredicateSignature::new(sym("at"), 2);
+        let lit = PredicateSignature::new(sym("lit"), 1);
+        let can_see = PredicateSignature::new(sym("can-see"), 2);
+        let mut domain = PredicateDomain::default();
+        domain.predicates.insert(at.clone(), Vec::new());
+        domain.predicates.insert(lit.clone(), Vec::new());
+
+        let variables = ["?a", "?b"]
+            .map(|name| TypedSymbol {
+                name: sym(name),
+                r#type: TypeName("person".into()),
+            })
+            .into();
+        let body = "at(?a, ?l) and at(?b, ?l) and lit(?l)";
+        let axiom = Axiom {
+            variables,
+            body: parse_logic_expr(&domain, body).unwrap(),
+        };
+        domain.axioms.insert(can_see.clone(), axiom);
+
+        let mut problem = PredicateProblem::default();
+        for (name, r#type) in [("alice", "person"), ("bob", "person"), ("hall", "place")] {
+            problem.objects.push(TypedSymbol {
+                name: sym(name),
+                r#type: TypeName(r#type.into()),
+            });
+        }
+        let initial_state = &mut problem.initial_state;
+        initial_state
+            .bound_predicates
+            .push((at.clone(), vec![sym("alice"), sym("hall")]));
+        initial_state
+            .bound_predicates
+            .push((at.clone(), vec![sym("bob"), sym("hall")]));
+
+        let mut state = domain.initial_story_state(&problem);
+        let atom = |name| state.get_atom(&sym(name)).unwrap();
+        let (alice, bob, hall) = (atom("alice"), atom("bob"), atom("hall"));
+        assert!(!state.holds(&can_see, &[alice, bob]));
+
+        state.set_fact(&lit, vec![hall], true);
+        domain.derive(&mut state);
+        assert!(state.holds(&can_see, &[alice, bob]));
+        assert!(state.holds(&can_see, &[bob, bob]));
+        // Places are not people, so they cannot see.
+        assert!(!state.holds(&can_see, &[hall, hall]));
+    }
+
+    #[test]
+    fn derives_negated_predicates_first() {
+        let mut domain = domain_with(&[("torch", 0), ("moon", 0)], &[]);
+        let lit = PredicateSignature::new(sym("lit"), 0);
+        let dark = PredicateSignature::new(sym("dark"), 0);
+        let axiom = |domain: &PredicateDomain, body| Axiom {
+            variables: Vec::new(),
+            body: parse_logic_expr(domain, body).unwrap(),
+        };
+        domain.axioms.insert(lit.clone(), Axiom::default());
+        domain.axioms.insert(dark.clone(), Axiom::default());
+        // Whichever axiom comes first in the map, `dark` must wait for `lit` to be complete.
+        domain
+            .axioms
+            .insert(dark.clone(), axiom(&domain, "not lit()"));
+        domain
+            .axioms
+            .insert(lit.clone(), axiom(&domain, "torch() or moon()"));
+        assert_eq!(domain.axiom_strata().unwrap(), [vec![&lit], vec![&dark]]);
+
+        let mut state = domain.initial_story_state(&PredicateProblem::default());
+        assert!(state.holds(&dark, &[]));
+        state.set_fact(&PredicateSignature::new(sym("moon"), 0), Vec::new(), true);
+        domain.derive(&mut state);
+        assert!(state.holds(&lit, &[]));
+        assert!(!state.holds(&dark, &[]));
+
+        domain
+            .axioms
+            .insert(lit.clone(), axiom(&domain, "torch() and not dark()"));
+        assert!(matches!(
+            domain.check_axioms(),
+            Err(AxiomError::NegativeCycle(_))
+        ));
+
+        domain.axioms.insert(lit.clone(), axiom(&domain, "torch()"));
+        add_actions(&mut domain, &[("light", "true", "lit()")]);
+        assert_eq!(
+            domain.check_axioms(),
+            Err(AxiomError::DerivedEffect {
+                action: sym("light"),
+                predicate: lit,
+            })
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::story::{Atom, PredicateSignature, StoryState};
+
+/// Hands out a dense index for each distinct value, so values can be stored and compared as
+/// numbers.
+#[derive(Debug, Clone)]
+pub struct Interner<T> {
+    values: Vec<T>,
+    indices: HashMap<T, usize>,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Interner<T> {
+    pub fn intern(&mut self, value: &T) -> usize {
+        if let Some(index) = self.indices.get(value) {
+            return *index;
+        }
+        let index = self.values.len();
+        self.values.push(value.clone());
+        self.indices.insert(value.clone(), index);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The value with the index, which must come from `intern`.
+    pub fn resolve(&self, index: usize) -> &T {
+        &self.values[index]
+    }
+}
+
+/// A set of small numbers, one bit each.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    /// Never ends in a zero word, so equal sets are stored the same way.
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (index % 64);
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+}
+
+/// Numbers the ground facts of a story, so states can be stored as sets of numbers.
+#[derive(Debug, Clone, Default)]
+pub struct FactTable {
+    signatures: Interner<PredicateSignature>,
+    facts: Interner<(usize, Vec<Atom>)>,
+}
+
+impl FactTable {
+    pub fn fact_index(&mut self, signature: &PredicateSignature, arguments: &[Atom]) -> usize {
+        let signature = self.signatures.intern(signature);
+        self.facts.intern(&(signature, arguments.to_vec()))
+    }
+
+    /// How many facts have an index.
+    pub fn len(&self) -> usize {
+        self.facts.len()
+    }
+
+    /// The signature and arguments of the fact with the index.
+    pub fn fact(&self, index: usize) -> (&PredicateSignature, &[Atom]) {
+        let (signature, arguments) = self.facts.resolve(index);
+        (self.signatures.resolve(*signature), arguments)
+    }
+
+    /// The state with each fact replaced by its index.
+    pub fn compact(&mut self, state: &StoryState) -> CompactState {
+        let mut compact = CompactState::default();
+        for (signature, arguments) in state.facts() {
+            compact.facts.insert(self.fact_index(signature, arguments));
+        }
+        for (signature, arguments, value) in state.fluent_values() {
+            // An unset fluent reads as zero, so a zero is left out like an unset one.
+            if value != 0 {
+                let fact = self.fact_index(signature, arguments);
+                compact.fluents.push((fact, value));
+            }
+        }
+        for (character, signature, arguments, value) in state.beliefs() {
+            let fact = self.fact_index(signature, arguments);
+            compact.beliefs.push((character, fact, value));
+        }
+        compact.fluents.sort_unstable();
+        compact.beliefs.sort_unstable();
+        compact
+    }
+}
+
+/// A story state as indices from a `FactTable`, which is much cheaper to hash and compare.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CompactState {
+    /// The facts that hold.
+    pub facts: BitSet,
+    /// The value of each fluent that is not zero, ordered by fact.
+    pub fluents: Vec<(usize, i64)>,
+    /// What each character believes, ordered by character and fact.
+    pub beliefs: Vec<(Atom, usize, bool)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{story::TypeName, test_util::sym};
+
+    #[test]
+    fn equal_states_compact_to_equal_sets() {
+        let at = PredicateSignature::new(sym("at"), 2);
+        let names = ["alice", "bob", "carol", "market"].map(sym);
+        let mut first = StoryState::default();
+        for name in &names {
+            first.get_or_insert_atom(name, &TypeName::default());
+        }
+        let atoms = names.clone().map(|name| first.get_atom(&name).unwrap());
+        let mut second = first.clone();
+        let mut third = first.clone();
+
+        for (state, order) in [(&mut first, [0, 1, 2]), (&mut second, [2, 0, 1])] {
+            for i in order {
+                state.set_fact(&at, vec![atoms[i], atoms[3]], true);
+            }
+        }
+        third.set_fact(&at, vec![atoms[0], atoms[3]], true);
+
+        let mut table = FactTable::default();
+        let compact = table.compact(&first);
+        assert_eq!(compact, table.compact(&second));
+        assert_ne!(compact, table.compact(&third));
+        assert!(
+            compact
+                .facts
+                .contains(table.fact_index(&at, &[atoms[1], atoms[3]]))
+        );
+        assert!(
+            !compact
+                .facts
+                .contains(table.fact_index(&at, &[atoms[3], atoms[1]]))
+        );
+    }
+}
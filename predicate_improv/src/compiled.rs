@@ -0,0 +1,394 @@
+use crate::{
+    compact::{BitSet, CompactState, FactTable},
+    grounding::GroundingStats,
+    story::{
+        Bindings, LogicExpr, PredicateDomain, PredicateProblem, StoryState, StoryStep, Symbol,
+    },
+};
+
+/// One instruction of a compiled condition. Each pushes a truth value onto a stack, after
+/// popping its operands for `Not`, `And` and `Or`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    True,
+    False,
+    /// Whether the fact with the index in the `FactTable` holds.
+    Fact(usize),
+    /// A part only the tree form can evaluate, i.e. a comparison or belief.
+    Tree(usize),
+    Not,
+    And,
+    Or,
+}
+
+/// A grounded condition as a flat list of instructions over fact indices.
+#[derive(Debug, Clone, Default)]
+pub struct CompiledExpr {
+    code: Vec<Instruction>,
+    trees: Vec<LogicExpr>,
+    bindings: Bindings,
+}
+
+impl CompiledExpr {
+    pub fn new(
+        expr: &LogicExpr,
+        bindings: &Bindings,
+        state: &StoryState,
+        table: &mut FactTable,
+    ) -> Self {
+        let mut compiled = Self {
+            code: Vec::new(),
+            trees: Vec::new(),
+            bindings: bindings.clone(),
+        };
+        compiled.compile(expr, state, table);
+        compiled
+    }
+
+    fn compile(&mut self, expr: &LogicExpr, state: &StoryState, table: &mut FactTable) {
+        let instruction = match expr {
+            LogicExpr::True | LogicExpr::Update(..) | LogicExpr::Learn(..) => Instruction::True,
+            LogicExpr::Predicate(signature, symbols) => {
+                match state.resolve_all(symbols, &self.bindings) {
+                    Some(arguments) => Instruction::Fact(table.fact_index(signature, &arguments)),
+                    None => Instruction::False,
+                }
+            }
+            LogicExpr::Compare(..) | LogicExpr::Believes(..) => {
+                self.trees.push(expr.clone());
+                Instruction::Tree(self.trees.len() - 1)
+            }
+            LogicExpr::Not(inner) => {
+                self.compile(inner, state, table);
+                Instruction::Not
+            }
+            LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+                self.compile(lhs, state, table);
+                self.compile(rhs, state, table);
+                match expr {
+                    LogicExpr::And(..) => Instruction::And,
+                    _ => Instruction::Or,
+                }
+            }
+        };
+        self.code.push(instruction);
+    }
+
+    /// Evaluates the condition like `StoryState::evaluate`. `compact` must be `state` compacted
+    /// with the table the condition was compiled with.
+    pub fn evaluate(&self, state: &StoryState, compact: &CompactState) -> bool {
+        let mut stack: Vec<bool> = Vec::with_capacity(self.code.len());
+        for instruction in &self.code {
+            let value = match instruction {
+                Instruction::True => true,
+                Instruction::False => false,
+                Instruction::Fact(fact) => compact.facts.contains(*fact),
+                Instruction::Tree(tree) => state.evaluate(&self.trees[*tree], &self.bindings),
+                Instruction::Not => !stack.pop().unwrap_or_default(),
+                Instruction::And => {
+                    let rhs = stack.pop().unwrap_or_default();
+                    stack.pop().unwrap_or_default() && rhs
+                }
+                Instruction::Or => {
+                    let rhs = stack.pop().unwrap_or_default();
+                    stack.pop().unwrap_or_default() || rhs
+                }
+            };
+            stack.push(value);
+        }
+        stack.pop().unwrap_or(true)
+    }
+
+    /// Whether the condition may hold once the facts in `reached` have been reached, ignoring
+    /// that steps also delete facts. Negations, comparisons and beliefs are assumed to hold.
+    pub fn may_hold(&self, reached: &BitSet) -> bool {
+        let mut stack: Vec<bool> = Vec::with_capacity(self.code.len());
+        for instruction in &self.code {
+            let value = match instruction {
+                Instruction::True | Instruction::Tree(_) => true,
+                Instruction::False => false,
+                Instruction::Fact(fact) => reached.contains(*fact),
+                Instruction::Not => {
+                    stack.pop();
+                    true
+                }
+                Instruction::And => {
+                    let rhs = stack.pop().unwrap_or_default();
+                    stack.pop().unwrap_or_default() && rhs
+                }
+                Instruction::Or => {
+                    let rhs = stack.pop().unwrap_or_default();
+                    stack.pop().unwrap_or_default() || rhs
+                }
+            };
+            stack.push(value);
+        }
+        stack.pop().unwrap_or(true)
+    }
+}
+
+/// A grounded effect as the facts it deletes and adds. Fluent and belief updates depend on the
+/// state they are applied in, so effects with those keep their tree.
+#[derive(Debug, Clone)]
+struct CompiledEffect {
+    deletions: Vec<usize>,
+    additions: Vec<usize>,
+    tree: Option<LogicExpr>,
+}
+
+impl CompiledEffect {
+    fn new(
+        effect: &LogicExpr,
+        bindings: &Bindings,
+        state: &StoryState,
+        table: &mut FactTable,
+    ) -> Self {
+        let mut deletions = Vec::new();
+        let mut additions = Vec::new();
+        let literals_only = collect_literals(
+            effect,
+            bindings,
+            state,
+            table,
+            true,
+            &mut deletions,
+            &mut additions,
+        );
+        CompiledEffect {
+            deletions,
+            additions,
+            tree: (!literals_only).then(|| effect.clone()),
+        }
+    }
+}
+
+/// Collects the facts the effect deletes and adds like `StoryState::apply_effect`. Returns false
+/// if the effect also updates fluents or beliefs.
+fn collect_literals(
+    effect: &LogicExpr,
+    bindings: &Bindings,
+    state: &StoryState,
+    table: &mut FactTable,
+    positive: bool,
+    deletions: &mut Vec<usize>,
+    additions: &mut Vec<usize>,
+) -> bool {
+    match effect {
+        LogicExpr::Update(..) | LogicExpr::Learn(..) => false,
+        LogicExpr::True | LogicExpr::Or(..) | LogicExpr::Compare(..) | LogicExpr::Believes(..) => {
+            true
+        }
+        LogicExpr::Predicate(signature, symbols) => {
+            if let Some(arguments) = state.resolve_all(symbols, bindings) {
+                let fact = table.fact_index(signature, &arguments);
+                match positive {
+                    true => additions.push(fact),
+                    false => deletions.push(fact),
+                }
+            }
+            true
+        }
+        LogicExpr::Not(inner) => collect_literals(
+            inner, bindings, state, table, !positive, deletions, additions,
+        ),
+        LogicExpr::And(lhs, rhs) => {
+            let lhs = collect_literals(lhs, bindings, state, table, positive, deletions, additions);
+            let rhs = collect_literals(rhs, bindings, state, table, positive, deletions, additions);
+            lhs && rhs
+        }
+    }
+}
+
+/// A step with its precondition and effect compiled.
+#[derive(Debug, Clone)]
+pub struct CompiledStep {
+    pub step: StoryStep,
+    pub precondition: CompiledExpr,
+    effect: CompiledEffect,
+    pub bindings: Bindings,
+}
+
+impl CompiledStep {
+    /// The facts the step adds.
+    pub fn additions(&self) -> &[usize] {
+        &self.effect.additions
+    }
+}
+
+/// Every step a story of the problem may take, along with its goal, compiled once so that
+/// searching does not evaluate expression trees or look up symbols.
+#[derive(Debug, Clone, Default)]
+pub struct CompiledProblem {
+    pub steps: Vec<CompiledStep>,
+    pub goal: CompiledExpr,
+    pub grounding: GroundingStats,
+}
+
+impl CompiledProblem {
+    /// The steps whose precondition holds in the state.
+    pub fn applicable_steps(
+        &self,
+        state: &StoryState,
+        compact: &CompactState,
+    ) -> Vec<&CompiledStep> {
+        self.steps
+            .iter()
+            .filter(|step| step.precondition.evaluate(state, compact))
+            .collect()
+    }
+}
+
+impl PredicateDomain {
+    /// Grounds the actions the problem allows for the atoms of the initial state, leaving out
+    /// those with agents that have no goals, like `candidate_steps`. Groundings ruled out by
+    /// static predicates are never enumerated, and steps that can't become applicable from the
+    /// initial state are dropped.
+    pub fn compile_problem(
+        &self,
+        problem: &PredicateProblem,
+        state: &StoryState,
+        table: &mut FactTable,
+    ) -> CompiledProblem {
+        let statics = self.static_predicates();
+        let mut grounding = GroundingStats::default();
+        let mut steps = Vec::new();
+        for (name, action) in &self.actions {
+            if problem.excludes(name) {
+                continue;
+            }
+
+            let candidates: Vec<_> = action
+                .parameters
+                .iter()
+                .map(|param| state.atoms_of_type(self, &param.r#type))
+                .collect();
+            grounding.naive += candidates.iter().map(Vec::len).product::<usize>();
+
+            let groundings = self.static_groundings(action, state, &candidates, &statics);
+            grounding.static_pruned += candidates.iter().map(Vec::len).product::<usize>();
+            grounding.static_pruned -= groundings.len();
+
+            for arguments in groundings {
+                let step = StoryStep {
+                    action: name.clone(),
+                    arguments: arguments
+                        .iter()
+                        .map(|atom| Symbol(state.get_atom_name(*atom).unwrap_or_default().into()))
+                        .collect(),
+                };
+                if !self
+                    .step_agents(&step)
+                    .iter()
+                    .all(|agent| problem.has_goals(agent))
+                {
+                    continue;
+                }
+
+                let bindings = action.bind(&arguments);
+                steps.push(CompiledStep {
+                    step,
+                    precondition: CompiledExpr::new(&action.precondition, &bindings, state, table),
+                    effect: CompiledEffect::new(&action.effect, &bindings, state, table),
+                    bindings,
+                });
+            }
+        }
+
+        let goal = CompiledExpr::new(&problem.goal, &Bindings::new(), state, table);
+        let reachable = self.reachable_steps(&steps, state, table);
+        grounding.unreachable = steps.len() - reachable.len();
+        grounding.kept = reachable.len();
+
+        CompiledProblem {
+            steps: reachable,
+            goal,
+            grounding,
+        }
+    }
+
+    /// Applies the effect of a step whose precondition holds to a copy of the state, like
+    /// `apply_step`.
+    pub fn apply_compiled_step(
+        &self,
+        state: &StoryState,
+        step: &CompiledStep,
+        table: &FactTable,
+    ) -> StoryState {
+        let mut next = state.clone();
+        let effect = &step.effect;
+        match &effect.tree {
+            Some(tree) => {
+                next.apply_effect_deriving(tree, &step.bindings, |state| self.derive(state))
+            }
+            None => {
+                for (facts, value) in [(&effect.deletions, false), (&effect.additions, true)] {
+                    for fact in facts {
+                        let (signature, arguments) = table.fact(*fact);
+                        next.set_fact(signature, arguments.to_vec(), value);
+                    }
+                }
+            }
+        }
+        self.derive(&mut next);
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        story::PredicateSignature,
+        test_util::{add_actions, domain_with, problem_with, sym},
+    };
+
+    #[test]
+    fn compiled_steps_match_the_expression_trees() {
+        let mut domain = domain_with(&[("has-key", 1), ("open", 1), ("brave", 1)], &[]);
+        domain
+            .fluents
+            .insert(PredicateSignature::new(sym("courage"), 1), Vec::new());
+        add_actions(
+            &mut domain,
+            &[
+                ("take-key", "not has-key(?c)", "has-key(?c)"),
+                (
+                    "open-door",
+                    "has-key(?c) or brave(?c)",
+                    "open(?c) and not has-key(?c)",
+                ),
+                ("cheer", "courage(?c) < 2", "increase(courage(?c), 1)"),
+                ("steel", "courage(?c) >= 2 and not brave(?c)", "brave(?c)"),
+            ],
+        );
+
+        let problem = problem_with(&domain, &["alice", "bob"], "open(alice) and open(bob)");
+
+        let mut table = FactTable::default();
+        let mut state = domain.initial_story_state(&problem);
+        let compiled = domain.compile_problem(&problem, &state, &mut table);
+        for _ in 0..8 {
+            let compact = table.compact(&state);
+            let mut expected = domain.applicable_steps(&state);
+            let mut applicable = compiled.applicable_steps(&state, &compact);
+            expected.sort_by_key(|step| step.to_string());
+            applicable.sort_by_key(|step| step.step.to_string());
+            assert_eq!(
+                applicable.iter().map(|step| &step.step).collect::<Vec<_>>(),
+                expected.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                compiled.goal.evaluate(&state, &compact),
+                state.evaluate(&problem.goal, &Bindings::new())
+            );
+
+            let Some(step) = applicable.last() else {
+                break;
+            };
+            let next = domain.apply_compiled_step(&state, step, &table);
+            let expected = domain.apply_step(&state, &step.step).unwrap();
+            assert_eq!(table.compact(&next), table.compact(&expected));
+            state = next;
+        }
+    }
+}
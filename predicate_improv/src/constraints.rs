@@ -0,0 +1,229 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::story::{Bindings, LogicExpr, PredicateProblem, StoryState, StoryStep, Symbol};
+
+/// A condition on the whole course of a story rather than on its end, as in PDDL3.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Constraint {
+    /// Holds in every state of the story.
+    Always(LogicExpr),
+    /// Holds in at least one state of the story.
+    Sometime(LogicExpr),
+    /// Holds during at most one stretch of the story: once it stops holding, it never holds
+    /// again.
+    AtMostOnce(LogicExpr),
+    /// Whenever the first condition holds, the second held in an earlier state, as in PDDL3's
+    /// `(sometime-before φ ψ)`.
+    SometimeBefore(LogicExpr, LogicExpr),
+    /// Holds in some state within the given percentage of the story, e.g. before the midpoint.
+    Within(u8, LogicExpr),
+    /// The story has a step of the action.
+    Includes(Symbol),
+    /// The story has no step of the action.
+    Excludes(Symbol),
+}
+
+impl Constraint {
+    /// Whether the constraint holds for the story made of `steps`, where `states` holds the state
+    /// before the first step followed by the state after each step.
+    pub fn holds(&self, steps: &[StoryStep], states: &[StoryState]) -> bool {
+        let no_bindings = Bindings::new();
+        let holds = |expr, state: &StoryState| state.evaluate(expr, &no_bindings);
+
+        match self {
+            Constraint::Always(expr) => states.iter().all(|state| holds(expr, state)),
+            Constraint::Sometime(expr) => states.iter().any(|state| holds(expr, state)),
+            Constraint::AtMostOnce(expr) => {
+                let changes = states
+                    .windows(2)
+                    .filter(|pair| !holds(expr, &pair[0]) && holds(expr, &pair[1]))
+                    .count();
+                let starts_true = states.first().is_some_and(|state| holds(expr, state));
+
+                changes + starts_true as usize <= 1
+            }
+            Constraint::SometimeBefore(later, earlier) => {
+                let earlier = states.iter().position(|state| holds(earlier, state));
+                match states.iter().position(|state| holds(later, state)) {
+                    Some(later) => earlier.is_some_and(|earlier| earlier < later),
+                    None => true,
+                }
+            }
+            Constraint::Within(percent, expr) => {
+                let last = steps.len() * usize::from(*percent) / 100;
+                states.iter().take(last + 1).any(|state| holds(expr, state))
+            }
+            Constraint::Includes(action) => steps.iter().any(|step| step.action == *action),
+            Constraint::Excludes(action) => steps.iter().all(|step| step.action != *action),
+        }
+    }
+
+    /// The conditions of the constraint, for editors and renames.
+    pub fn exprs_mut(&mut self) -> Vec<&mut LogicExpr> {
+        match self {
+            Constraint::Always(expr)
+            | Constraint::Sometime(expr)
+            | Constraint::AtMostOnce(expr)
+            | Constraint::Within(_, expr) => vec![expr],
+            Constraint::SometimeBefore(later, earlier) => vec![later, earlier],
+            Constraint::Includes(_) | Constraint::Excludes(_) => Vec::new(),
+        }
+    }
+
+    /// One constraint of each kind, with empty conditions and actions.
+    pub fn templates() -> [Constraint; 7] {
+        [
+            Constraint::Always(LogicExpr::True),
+            Constraint::Sometime(LogicExpr::True),
+            Constraint::AtMostOnce(LogicExpr::True),
+            Constraint::SometimeBefore(LogicExpr::True, LogicExpr::True),
+            Constraint::Within(50, LogicExpr::True),
+            Constraint::Includes(Symbol::default()),
+            Constraint::Excludes(Symbol::default()),
+        ]
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Constraint::Always(_) => "always",
+            Constraint::Sometime(_) => "sometime",
+            Constraint::AtMostOnce(_) => "at-most-once",
+            Constraint::SometimeBefore(_, _) => "sometime-before",
+            Constraint::Within(_, _) => "within",
+            Constraint::Includes(_) => "include",
+            Constraint::Excludes(_) => "exclude",
+        }
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.keyword())?;
+        match self {
+            Constraint::Always(expr)
+            | Constraint::Sometime(expr)
+            | Constraint::AtMostOnce(expr) => write!(f, "{expr}")?,
+            Constraint::SometimeBefore(later, earlier) => write!(f, "{later}, {earlier}")?,
+            Constraint::Within(percent, expr) => write!(f, "{percent}%, {expr}")?,
+            Constraint::Includes(action) | Constraint::Excludes(action) => {
+                write!(f, "{}", action.0)?
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+impl PredicateProblem {
+    /// The first constraint of the problem the story breaks, if any. The minimum story length
+    /// is checked separately.
+    pub fn violated_constraint(
+        &self,
+        steps: &[StoryStep],
+        states: &[StoryState],
+    ) -> Option<&Constraint> {
+        self.constraints
+            .iter()
+            .find(|constraint| !constraint.holds(steps, states))
+    }
+
+    /// Whether steps of the action may be taken at all.
+    pub fn excludes(&self, action: &Symbol) -> bool {
+        self.constraints.iter().any(
+            |constraint| matches!(constraint, Constraint::Excludes(excluded) if excluded == action),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        test_util::{domain_with, problem_with, step, sym},
+        validator::StoryError,
+    };
+
+    #[test]
+    fn checks_constraints_over_the_whole_story() {
+        let domain = domain_with(
+            &[("present", 1)],
+            &[
+                ("enter", "true", "present(?c)"),
+                ("leave", "true", "not present(?c)"),
+                ("wait", "true", "true"),
+            ],
+        );
+
+        let parse = |text| parse_logic_expr(&domain, text).unwrap();
+        let mut problem = problem_with(&domain, &["villain"], "true");
+        problem.min_story_length = 3;
+        problem.constraints = vec![
+            Constraint::Within(50, parse("present(villain)")),
+            Constraint::AtMostOnce(parse("present(villain)")),
+            Constraint::Excludes(sym("leave")),
+        ];
+
+        let check = |steps: &[&str]| {
+            let steps = steps
+                .iter()
+                .map(|action| step(action, &["villain"]))
+                .collect();
+            domain.validate_story(&problem, steps).map(|_| ())
+        };
+
+        assert_eq!(check(&["wait", "enter", "wait", "wait"]), Ok(()));
+        assert_eq!(
+            check(&["enter", "wait"]),
+            Err(StoryError::TooShort { length: 2, min: 3 })
+        );
+        assert_eq!(
+            check(&["wait", "wait", "enter", "wait"]),
+            Err(StoryError::ConstraintViolated {
+                constraint: "within(50%, present(villain))".into(),
+            })
+        );
+        assert_eq!(
+            check(&["enter", "leave", "enter", "wait"]),
+            Err(StoryError::ConstraintViolated {
+                constraint: "at-most-once(present(villain))".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn reads_sometime_before_like_pddl3() {
+        let domain = domain_with(
+            &[("warned", 1), ("attacked", 1)],
+            &[
+                ("warn", "true", "warned(?c)"),
+                ("attack", "true", "attacked(?c)"),
+            ],
+        );
+        let parse = |text| parse_logic_expr(&domain, text).unwrap();
+        let mut problem = problem_with(&domain, &["village"], "true");
+        // The village is attacked only after it was warned.
+        problem.constraints = vec![Constraint::SometimeBefore(
+            parse("attacked(village)"),
+            parse("warned(village)"),
+        )];
+
+        let check = |steps: &[&str]| {
+            let steps = steps
+                .iter()
+                .map(|action| step(action, &["village"]))
+                .collect();
+            domain.validate_story(&problem, steps).map(|_| ())
+        };
+
+        assert_eq!(check(&["warn", "attack"]), Ok(()));
+        assert_eq!(check(&["warn"]), Ok(()));
+        assert_eq!(
+            check(&["attack", "warn"]),
+            Err(StoryError::ConstraintViolated {
+                constraint: "sometime-before(attacked(village), warned(village))".into(),
+            })
+        );
+    }
+}
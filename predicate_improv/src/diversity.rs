@@ -0,0 +1,174 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::story::{PredicateStory, StoryState};
+
+/// How different two stories are, from 0 for the same story to 1 for nothing in common.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StoryDistance {
+    /// The Jaccard distance between the sets of steps the stories take.
+    #[default]
+    ActionSet,
+    /// The Jaccard distance between the facts of the states the stories pass through, averaged
+    /// over the steps. The shorter story is taken to stay in its last state.
+    StateTrajectory,
+}
+
+impl StoryDistance {
+    pub const ALL: [StoryDistance; 2] = [StoryDistance::ActionSet, StoryDistance::StateTrajectory];
+
+    pub fn between(self, a: &PredicateStory, b: &PredicateStory) -> f32 {
+        match self {
+            StoryDistance::ActionSet => {
+                jaccard_distance(&a.steps.iter().collect(), &b.steps.iter().collect())
+            }
+            StoryDistance::StateTrajectory => {
+                let length = a.states.len().max(b.states.len());
+                if length == 0 {
+                    return 0.;
+                }
+
+                // Atoms are compared by name, since the stories may not share a state.
+                let facts = |states: &[StoryState], i: usize| {
+                    states
+                        .get(i.min(states.len().saturating_sub(1)))
+                        .map(|state| {
+                            state
+                                .facts()
+                                .map(|(signature, arguments)| {
+                                    state.fact_label(signature, arguments)
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                };
+                let total: f32 = (0..length)
+                    .map(|i| jaccard_distance(&facts(&a.states, i), &facts(&b.states, i)))
+                    .sum();
+                total / length as f32
+            }
+        }
+    }
+}
+
+impl Display for StoryDistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoryDistance::ActionSet => write!(f, "Different steps"),
+            StoryDistance::StateTrajectory => write!(f, "Different states"),
+        }
+    }
+}
+
+fn jaccard_distance<T: Eq + std::hash::Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.;
+    }
+    1. - a.intersection(b).count() as f32 / union as f32
+}
+
+/// How many stories a generation looks for and how different they have to be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiversitySettings {
+    pub count: usize,
+    pub distance: StoryDistance,
+    /// The distance each story keeps from all those found before it.
+    pub min_distance: f32,
+}
+
+impl Default for DiversitySettings {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            distance: StoryDistance::default(),
+            min_distance: 0.3,
+        }
+    }
+}
+
+impl DiversitySettings {
+    /// Whether the story is far enough from every story already chosen.
+    pub fn is_diverse(&self, story: &PredicateStory, chosen: &[PredicateStory]) -> bool {
+        chosen
+            .iter()
+            .all(|other| self.distance.between(story, other) >= self.min_distance)
+    }
+
+    /// Picks up to `count` stories far enough apart from each other, preferring those that fit
+    /// best and, among equally fitting ones, those found first.
+    pub fn select_best(
+        &self,
+        candidates: Vec<PredicateStory>,
+        fit: impl Fn(&PredicateStory) -> f32,
+    ) -> Vec<PredicateStory> {
+        let mut scored: Vec<_> = candidates
+            .into_iter()
+            .map(|story| (fit(&story), story))
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let mut chosen = Vec::new();
+        for (_, story) in scored {
+            if chosen.len() == self.count {
+                break;
+            }
+            if self.is_diverse(&story, &chosen) {
+                chosen.push(story);
+            }
+        }
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{domain_with, problem_with, step};
+
+    #[test]
+    fn measures_steps_and_states_in_common() {
+        let domain = domain_with(
+            &[("sang", 1), ("danced", 1)],
+            &[
+                ("sing", "true", "sang(?c)"),
+                ("dance", "true", "danced(?c)"),
+            ],
+        );
+        let problem = problem_with(&domain, &["alice"], "true");
+        let story = |actions: &[&str]| {
+            let steps = actions
+                .iter()
+                .map(|action| step(action, &["alice"]))
+                .collect();
+            domain.validate_story(&problem, steps).unwrap()
+        };
+
+        let sing_then_dance = story(&["sing", "dance"]);
+        let dance_then_sing = story(&["dance", "sing"]);
+        let sing = story(&["sing"]);
+
+        let steps = StoryDistance::ActionSet;
+        assert_eq!(steps.between(&sing_then_dance, &dance_then_sing), 0.);
+        assert_eq!(steps.between(&sing_then_dance, &sing), 0.5);
+
+        // The states differ only after the first step: {sang} against {danced}.
+        let states = StoryDistance::StateTrajectory;
+        assert_eq!(states.between(&sing_then_dance, &dance_then_sing), 1. / 3.);
+        assert_eq!(states.between(&sing, &sing), 0.);
+
+        let settings = DiversitySettings {
+            count: 2,
+            distance: steps,
+            min_distance: 0.5,
+        };
+        let chosen = [sing_then_dance.clone()];
+        assert!(settings.is_diverse(&sing, &chosen));
+        assert!(!settings.is_diverse(&dance_then_sing, &chosen));
+
+        // The longest stories fit best, but the second is too close to the first.
+        let candidates = vec![sing.clone(), sing_then_dance, dance_then_sing];
+        let chosen = settings.select_best(candidates, |story| story.steps.len() as f32);
+        let texts: Vec<_> = chosen.iter().map(|story| story.text.as_str()).collect();
+        assert_eq!(texts, ["sing(alice)\ndance(alice)\n", "sing(alice)\n"]);
+    }
+}
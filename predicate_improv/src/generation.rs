@@ -0,0 +1,310 @@
+use std::{
+    collections::HashSet,
+    num::NonZero,
+    panic::resume_unwind,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::Instant,
+};
+
+use eframe::egui;
+
+use crate::{
+    app::GenerationMode,
+    diversity::DiversitySettings,
+    search::{SearchSettings, SearchStats},
+    story::{PredicateDomain, PredicateProblem, PredicateStory},
+};
+
+/// Shared between a generation and whoever started it, to follow and cancel it.
+#[derive(Debug, Default)]
+pub struct Progress {
+    cancelled: AtomicBool,
+    /// States expanded by a search, or stories attempted otherwise.
+    done: AtomicUsize,
+}
+
+impl Progress {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn advance(&self, amount: usize) {
+        self.done.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+}
+
+/// The number of threads to generate with when the user hasn't picked one: one per core.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZero::get)
+}
+
+/// Calls `attempt` up to `attempts` times, spread over `threads` threads, passing each story it
+/// returns that wasn't found before to `on_story`, until that returns false or `progress` is
+/// cancelled. Stories are passed on from the calling thread, in the order they are found.
+pub fn stream_attempts(
+    attempts: usize,
+    threads: usize,
+    progress: &Progress,
+    on_story: &mut impl FnMut(PredicateStory) -> bool,
+    attempt: impl Fn() -> Option<PredicateStory> + Sync,
+) {
+    let started = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (started, stopped, attempt) = (&started, &stopped, &attempt);
+            scope.spawn(move || {
+                while !stopped.load(Ordering::Relaxed)
+                    && !progress.is_cancelled()
+                    && started.fetch_add(1, Ordering::Relaxed) < attempts
+                {
+                    progress.advance(1);
+                    if let Some(story) = attempt()
+                        && sender.send(story).is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut found = HashSet::new();
+        for story in receiver {
+            if found.insert(story.steps.clone()) && !on_story(story) {
+                stopped.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+}
+
+/// Maps the items on up to `threads` threads, keeping their order.
+pub fn parallel_map<T: Sync, U: Send>(
+    threads: usize,
+    items: &[T],
+    f: impl Fn(&T) -> U + Sync,
+) -> Vec<U> {
+    if threads <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let chunks: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(&f).collect::<Vec<_>>()))
+            .collect();
+        chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.join().unwrap_or_else(|panic| resume_unwind(panic)))
+            .collect()
+    })
+}
+
+#[derive(Debug)]
+enum GenerationEvent {
+    Story(PredicateStory),
+    /// The worker is done, with the statistics of the search if it searched.
+    Finished(Option<SearchStats>),
+}
+
+/// What a generation sent since it was last polled.
+#[derive(Debug, Default)]
+pub struct GenerationUpdate {
+    pub stories: Vec<PredicateStory>,
+    pub finished: bool,
+    pub stats: Option<SearchStats>,
+}
+
+/// Stories being generated on a worker thread, so the UI stays responsive. Dropping it cancels
+/// the generation.
+#[derive(Debug)]
+pub struct Generation {
+    pub mode: GenerationMode,
+    pub progress: Arc<Progress>,
+    pub started: Instant,
+    receiver: Receiver<GenerationEvent>,
+}
+
+/// How many stories are gathered for each story asked for when the problem has a tension arc, to
+/// pick those whose tension follows it best.
+pub const TENSION_CANDIDATES: usize = 10;
+
+impl Generation {
+    /// Starts generating up to `diversity.count` stories, each different enough from those found
+    /// before it, for a copy of the domain and problem. If the problem has a tension arc, the
+    /// stories are instead picked from up to `TENSION_CANDIDATES` times as many by how well they
+    /// follow the arc, and only sent once the generation is done. Every story sent and the end of
+    /// the generation request a repaint of `ctx`.
+    pub fn start(
+        ctx: &egui::Context,
+        domain: Arc<PredicateDomain>,
+        problem: Arc<PredicateProblem>,
+        mode: GenerationMode,
+        settings: SearchSettings,
+        diversity: DiversitySettings,
+        threads: usize,
+    ) -> Self {
+        let progress = Arc::new(Progress::default());
+        let (sender, receiver) = mpsc::channel();
+
+        let ctx = ctx.clone();
+        let worker_progress = progress.clone();
+        thread::spawn(move || {
+            let progress = &*worker_progress;
+            let tension = &problem.tension;
+            let mut chosen = Vec::new();
+            let mut candidates = Vec::new();
+            let mut on_story = |story: PredicateStory| {
+                if !tension.is_empty() {
+                    candidates.push(story);
+                    return candidates.len() < diversity.count * TENSION_CANDIDATES;
+                }
+                if !diversity.is_diverse(&story, &chosen) {
+                    return true;
+                }
+                chosen.push(story.clone());
+                let sent = sender.send(GenerationEvent::Story(story)).is_ok();
+                ctx.request_repaint();
+                sent && chosen.len() < diversity.count
+            };
+
+            let stats = match mode {
+                GenerationMode::Improvise => {
+                    domain.generate_stories(&problem, threads, progress, &mut on_story);
+                    None
+                }
+                GenerationMode::PartialOrder => {
+                    domain.generate_partial_order_stories(
+                        &problem,
+                        threads,
+                        progress,
+                        &mut on_story,
+                    );
+                    None
+                }
+                GenerationMode::Search => Some(domain.search_stories(
+                    &problem,
+                    &settings,
+                    threads,
+                    progress,
+                    &mut on_story,
+                )),
+            };
+
+            let best =
+                diversity.select_best(candidates, |story| tension.fit(&tension.curve(story)));
+            for story in best {
+                let _ = sender.send(GenerationEvent::Story(story));
+            }
+            let _ = sender.send(GenerationEvent::Finished(stats));
+            ctx.request_repaint();
+        });
+
+        Self {
+            mode,
+            progress,
+            started: Instant::now(),
+            receiver,
+        }
+    }
+
+    /// Collects what the worker sent without waiting for it.
+    pub fn poll(&self) -> GenerationUpdate {
+        let mut update = GenerationUpdate::default();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(GenerationEvent::Story(story)) => update.stories.push(story),
+                Ok(GenerationEvent::Finished(stats)) => {
+                    update.finished = true;
+                    update.stats = stats;
+                }
+                Err(TryRecvError::Empty) => break,
+                // The worker panicked, so nothing more is coming.
+                Err(TryRecvError::Disconnected) => {
+                    update.finished = true;
+                    break;
+                }
+            }
+        }
+        update
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use crate::story::{StoryStep, Symbol};
+
+    #[test]
+    fn streams_each_story_once() {
+        let story = |action: &str| {
+            let steps = vec![StoryStep {
+                action: Symbol(action.into()),
+                arguments: Vec::new(),
+            }];
+            PredicateStory::new(steps, Vec::new())
+        };
+        let actions = ["a", "a", "b", "a", "c", "d"];
+
+        for threads in [1, 4] {
+            let attempts = Mutex::new(actions.into_iter().map(story));
+            let progress = Progress::default();
+            let mut found = Vec::new();
+            stream_attempts(
+                10,
+                threads,
+                &progress,
+                &mut |story| {
+                    found.push(story.text);
+                    true
+                },
+                || attempts.lock().unwrap().next(),
+            );
+
+            found.sort();
+            assert_eq!(found, ["a()\n", "b()\n", "c()\n", "d()\n"]);
+            assert_eq!(progress.done(), 10);
+        }
+
+        // Only the first attempts run on one thread, in order, until `on_story` stops them.
+        let attempts = Mutex::new(actions.into_iter().map(story));
+        let mut found = Vec::new();
+        stream_attempts(
+            10,
+            1,
+            &Progress::default(),
+            &mut |story| {
+                found.push(story.text);
+                found.len() < 2
+            },
+            || attempts.lock().unwrap().next(),
+        );
+        assert_eq!(found, ["a()\n", "b()\n"]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    compact::{BitSet, FactTable},
+    compiled::CompiledStep,
+    story::{Action, Atom, PredicateDomain, PredicateSignature, StoryState, Symbol},
+};
+
+/// How many ground actions were ruled out while compiling a problem, and why.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroundingStats {
+    /// Every grounding of every action the problem allows.
+    pub naive: usize,
+    /// Groundings whose precondition contradicts a static predicate.
+    pub static_pruned: usize,
+    /// Ground actions that can't become applicable from the initial state.
+    pub unreachable: usize,
+    pub kept: usize,
+}
+
+impl Display for GroundingStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} ground actions kept ({} ruled out by static predicates, {} unreachable)",
+            self.kept, self.naive, self.static_pruned, self.unreachable
+        )
+    }
+}
+
+/// A literal of the top-level conjunction of a precondition over a static predicate. It holds in
+/// every state exactly when it holds in the initial one.
+struct StaticCondition<'a> {
+    signature: &'a PredicateSignature,
+    symbols: &'a [Symbol],
+    holds: bool,
+    /// How many parameters must be bound before it can be checked.
+    bound_after: usize,
+}
+
+impl StaticCondition<'_> {
+    fn check(&self, action: &Action, state: &StoryState, arguments: &[Atom]) -> bool {
+        match state.resolve_all(self.symbols, &action.bind(arguments)) {
+            Some(arguments) => state.holds(self.signature, &arguments) == self.holds,
+            None => !self.holds,
+        }
+    }
+}
+
+impl PredicateDomain {
+    /// The predicates no effect changes and that are not derived, whose facts are the same in
+    /// every state of a story.
+    pub fn static_predicates(&self) -> HashSet<PredicateSignature> {
+        let mut statics: HashSet<_> = self
+            .predicates
+            .keys()
+            .filter(|signature| !self.is_derived(signature))
+            .cloned()
+            .collect();
+        for action in self.actions.values() {
+            action.effect.for_each_literal(&mut |signature, _, _| {
+                statics.remove(signature);
+            });
+        }
+        statics
+    }
+
+    /// The groundings of the action over `candidates` that don't contradict a static predicate in
+    /// its precondition. Each static literal is checked as soon as its parameters are bound, so
+    /// the groundings it rules out are never enumerated.
+    pub fn static_groundings(
+        &self,
+        action: &Action,
+        state: &StoryState,
+        candidates: &[Vec<Atom>],
+        statics: &HashSet<PredicateSignature>,
+    ) -> Vec<Vec<Atom>> {
+        let mut conditions = Vec::new();
+        action
+            .precondition
+            .for_each_literal(&mut |signature, symbols, holds| {
+                if !statics.contains(signature) {
+                    return;
+                }
+                let bound_after = symbols
+                    .iter()
+                    .filter_map(|symbol| {
+                        action
+                            .parameters
+                            .iter()
+                            .position(|param| &param.name == symbol)
+                    })
+                    .map(|position| position + 1)
+                    .max()
+                    .unwrap_or_default();
+                conditions.push(StaticCondition {
+                    signature,
+                    symbols,
+                    holds,
+                    bound_after,
+                });
+            });
+
+        let mut groundings = Vec::new();
+        if conditions
+            .iter()
+            .filter(|condition| condition.bound_after == 0)
+            .all(|condition| condition.check(action, state, &[]))
+        {
+            extend_groundings(
+                action,
+                state,
+                candidates,
+                &conditions,
+                &mut Vec::new(),
+                &mut groundings,
+            );
+        }
+        groundings
+    }
+
+    /// The steps that may become applicable from the state when deletes are ignored, found by
+    /// adding the facts of every step that may fire until nothing new is reached.
+    pub fn reachable_steps(
+        &self,
+        steps: &[CompiledStep],
+        state: &StoryState,
+        table: &mut FactTable,
+    ) -> Vec<CompiledStep> {
+        let mut reached = table.compact(state).facts;
+        // Derived facts follow from the others in ways this analysis doesn't track.
+        for fact in 0..table.len() {
+            if self.is_derived(table.fact(fact).0) {
+                reached.insert(fact);
+            }
+        }
+
+        let mut fired = vec![false; steps.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (step, fired) in steps.iter().zip(&mut fired) {
+                if !*fired && step.precondition.may_hold(&reached) {
+                    *fired = true;
+                    changed = true;
+                    add_all(&mut reached, step.additions());
+                }
+            }
+        }
+
+        steps
+            .iter()
+            .zip(fired)
+            .filter(|(_, fired)| *fired)
+            .map(|(step, _)| step.clone())
+            .collect()
+    }
+}
+
+fn add_all(set: &mut BitSet, facts: &[usize]) {
+    for fact in facts {
+        set.insert(*fact);
+    }
+}
+
+fn extend_groundings(
+    action: &Action,
+    state: &StoryState,
+    candidates: &[Vec<Atom>],
+    conditions: &[StaticCondition],
+    arguments: &mut Vec<Atom>,
+    out: &mut Vec<Vec<Atom>>,
+) {
+    let Some(atoms) = candidates.get(arguments.len()) else {
+        out.push(arguments.clone());
+        return;
+    };
+
+    for atom in atoms {
+        arguments.push(*atom);
+        if conditions
+            .iter()
+            .filter(|condition| condition.bound_after == arguments.len())
+            .all(|condition| condition.check(action, state, arguments))
+        {
+            extend_groundings(action, state, candidates, conditions, arguments, out);
+        }
+        arguments.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        story::PredicateProblem,
+        test_util::{action, domain_with, objects, sym},
+    };
+
+    #[test]
+    fn prunes_groundings_by_roads_and_reachability() {
+        let mut domain = domain_with(&[("road", 2), ("at", 1)], &[]);
+        let road = PredicateSignature::new(sym("road"), 2);
+        let go = action(
+            &domain,
+            &["?from", "?to"],
+            "at(?from) and road(?from, ?to)",
+            "at(?to) and not at(?from)",
+        );
+        domain.actions.insert(sym("go"), go);
+
+        // Roads lead from a to b to c, and from d to a, but nothing leads to d.
+        let mut problem = PredicateProblem {
+            objects: objects(&["a", "b", "c", "d"]),
+            ..Default::default()
+        };
+        for (from, to) in [("a", "b"), ("b", "c"), ("d", "a")] {
+            problem
+                .initial_state
+                .bound_predicates
+                .push((road.clone(), vec![sym(from), sym(to)]));
+        }
+        problem
+            .initial_state
+            .bound_predicates
+            .push((PredicateSignature::new(sym("at"), 1), vec![sym("a")]));
+
+        assert_eq!(domain.static_predicates(), HashSet::from([road]));
+
+        let state = domain.initial_story_state(&problem);
+        let compiled = domain.compile_problem(&problem, &state, &mut FactTable::default());
+        let steps: Vec<_> = compiled
+            .steps
+            .iter()
+            .map(|step| step.step.to_string())
+            .collect();
+        assert_eq!(steps, ["go(a, b)", "go(b, c)"]);
+        assert_eq!(
+            compiled.grounding,
+            GroundingStats {
+                naive: 16,
+                static_pruned: 13,
+                unreachable: 1,
+                kept: 2,
+            }
+        );
+    }
+}
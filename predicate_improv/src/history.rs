@@ -0,0 +1,479 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    constraints::Constraint,
+    story::{
+        Action, Axiom, CompoundTask, LogicExpr, PredicateDomain, PredicateProblem,
+        PredicateSignature, Symbol, TaskCall, TypeName, TypedSymbol,
+    },
+    tension::Tension,
+};
+
+/// An item before and after an edit. `before` is `None` for additions and `after` is `None` for
+/// deletions.
+#[derive(Debug, Clone)]
+pub struct Change<T> {
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+impl<T> Change<T> {
+    pub fn added(item: T) -> Self {
+        Self {
+            before: None,
+            after: Some(item),
+        }
+    }
+
+    pub fn removed(item: T) -> Self {
+        Self {
+            before: Some(item),
+            after: None,
+        }
+    }
+
+    fn inverted(self) -> Self {
+        Self {
+            before: self.after,
+            after: self.before,
+        }
+    }
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Whether the edit leaves the item as it was, e.g. when an edit modal is cancelled.
+    pub fn is_noop(&self) -> bool {
+        self.before == self.after
+    }
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    /// Extends the change with the next one if that one picks up where this one left off.
+    fn merge(&mut self, next: &Change<T>) -> bool {
+        if next.before != self.after {
+            return false;
+        }
+        self.after = next.after.clone();
+        true
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Change<(K, V)> {
+    fn apply_to_map(&self, map: &mut HashMap<K, V>) {
+        if let Some((key, _)) = &self.before {
+            map.remove(key);
+        }
+        if let Some((key, value)) = &self.after {
+            map.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    /// Replaces `before` in place, so undoing an edit keeps the order of the list.
+    fn apply_to_vec(&self, vec: &mut Vec<T>) {
+        let position = self
+            .before
+            .as_ref()
+            .and_then(|before| vec.iter().position(|item| item == before));
+
+        match (position, &self.after) {
+            (Some(i), Some(after)) => vec[i] = after.clone(),
+            (Some(i), None) => {
+                vec.remove(i);
+            }
+            (None, Some(after)) => vec.push(after.clone()),
+            (None, None) => {}
+        }
+    }
+}
+
+/// A reversible edit of the domain or problem.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Predicate(Change<(PredicateSignature, Vec<TypedSymbol>)>),
+    Fluent(Change<(PredicateSignature, Vec<TypedSymbol>)>),
+    Axiom(Box<Change<(PredicateSignature, Axiom)>>),
+    Action(Box<Change<(Symbol, Action)>>),
+    Task(Box<Change<(Symbol, CompoundTask)>>),
+    Type(Change<(TypeName, Vec<TypeName>)>),
+    Constant(Change<(Symbol, TypeName)>),
+    /// An edit of an object of the problem at the given index.
+    Object(usize, Change<TypedSymbol>),
+    BoundPredicate(usize, Change<(PredicateSignature, Vec<Symbol>)>),
+    FluentValue(usize, Change<(PredicateSignature, Vec<Symbol>, i64)>),
+    Goal(usize, Change<LogicExpr>),
+    /// The maximum and minimum story length, which are changed together so the minimum never
+    /// exceeds the maximum.
+    StoryLength(usize, Change<(u32, u32)>),
+    CharacterGoals(usize, Change<Vec<(Symbol, LogicExpr)>>),
+    Beliefs(usize, Change<Vec<(Symbol, LogicExpr)>>),
+    Constraints(usize, Change<Vec<Constraint>>),
+    Outline(usize, Change<Vec<TaskCall>>),
+    Tension(usize, Change<Tension>),
+    /// Adding, deleting or renaming the problem at the given index.
+    Problem(Box<Change<(usize, PredicateProblem)>>),
+    /// Edits touching many items at once, such as renames.
+    Snapshot(Box<Change<(PredicateDomain, Vec<PredicateProblem>)>>),
+}
+
+impl Edit {
+    fn apply(&self, domain: &mut PredicateDomain, problems: &mut Vec<PredicateProblem>) {
+        match self {
+            Edit::Predicate(change) => change.apply_to_map(&mut domain.predicates),
+            Edit::Fluent(change) => change.apply_to_map(&mut domain.fluents),
+            Edit::Axiom(change) => change.apply_to_map(&mut domain.axioms),
+            Edit::Action(change) => change.apply_to_map(&mut domain.actions),
+            Edit::Task(change) => change.apply_to_map(&mut domain.tasks),
+            Edit::Type(change) => change.apply_to_map(&mut domain.types),
+            Edit::Constant(change) => change.apply_to_map(&mut domain.constants),
+            Edit::Object(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    change.apply_to_vec(&mut problem.objects);
+                }
+            }
+            Edit::BoundPredicate(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    change.apply_to_vec(&mut problem.initial_state.bound_predicates);
+                }
+            }
+            Edit::FluentValue(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    change.apply_to_vec(&mut problem.initial_state.fluent_values);
+                }
+            }
+            Edit::Goal(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.goal = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::CharacterGoals(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.character_goals = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::Beliefs(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.initial_state.beliefs = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::Constraints(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.constraints = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::Outline(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.outline = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::Tension(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    problem.tension = change.after.clone().unwrap_or_default();
+                }
+            }
+            Edit::Problem(change) => {
+                if let Some((i, _)) = &change.before
+                    && *i < problems.len()
+                {
+                    problems.remove(*i);
+                }
+                if let Some((i, problem)) = &change.after {
+                    problems.insert((*i).min(problems.len()), problem.clone());
+                }
+            }
+            Edit::StoryLength(i, change) => {
+                if let Some(problem) = problems.get_mut(*i) {
+                    (problem.max_story_length, problem.min_story_length) =
+                        change.after.unwrap_or_default();
+                }
+            }
+            Edit::Snapshot(change) => {
+                if let Some((after_domain, after_problems)) = &change.after {
+                    *domain = after_domain.clone();
+                    *problems = after_problems.clone();
+                }
+            }
+        }
+    }
+
+    /// Whether the edit leaves everything as it was. Snapshots are assumed to change something.
+    fn is_noop(&self) -> bool {
+        match self {
+            Edit::Predicate(change) => change.is_noop(),
+            Edit::Fluent(change) => change.is_noop(),
+            Edit::Axiom(change) => change.is_noop(),
+            Edit::Action(change) => change.is_noop(),
+            Edit::Task(change) => change.is_noop(),
+            Edit::Type(change) => change.is_noop(),
+            Edit::Constant(change) => change.is_noop(),
+            Edit::Object(_, change) => change.is_noop(),
+            Edit::BoundPredicate(_, change) => change.is_noop(),
+            Edit::FluentValue(_, change) => change.is_noop(),
+            Edit::Goal(_, change) => change.is_noop(),
+            Edit::StoryLength(_, change) => change.is_noop(),
+            Edit::CharacterGoals(_, change) | Edit::Beliefs(_, change) => change.is_noop(),
+            Edit::Constraints(_, change) => change.is_noop(),
+            Edit::Outline(_, change) => change.is_noop(),
+            Edit::Tension(_, change) => change.is_noop(),
+            Edit::Problem(change) => change.is_noop(),
+            Edit::Snapshot(_) => false,
+        }
+    }
+
+    fn inverted(self) -> Self {
+        match self {
+            Edit::Predicate(change) => Edit::Predicate(change.inverted()),
+            Edit::Fluent(change) => Edit::Fluent(change.inverted()),
+            Edit::Axiom(change) => Edit::Axiom(Box::new(change.inverted())),
+            Edit::Action(change) => Edit::Action(Box::new(change.inverted())),
+            Edit::Task(change) => Edit::Task(Box::new(change.inverted())),
+            Edit::Type(change) => Edit::Type(change.inverted()),
+            Edit::Constant(change) => Edit::Constant(change.inverted()),
+            Edit::Object(i, change) => Edit::Object(i, change.inverted()),
+            Edit::BoundPredicate(i, change) => Edit::BoundPredicate(i, change.inverted()),
+            Edit::FluentValue(i, change) => Edit::FluentValue(i, change.inverted()),
+            Edit::Goal(i, change) => Edit::Goal(i, change.inverted()),
+            Edit::StoryLength(i, change) => Edit::StoryLength(i, change.inverted()),
+            Edit::CharacterGoals(i, change) => Edit::CharacterGoals(i, change.inverted()),
+            Edit::Beliefs(i, change) => Edit::Beliefs(i, change.inverted()),
+            Edit::Constraints(i, change) => Edit::Constraints(i, change.inverted()),
+            Edit::Outline(i, change) => Edit::Outline(i, change.inverted()),
+            Edit::Tension(i, change) => Edit::Tension(i, change.inverted()),
+            Edit::Problem(change) => Edit::Problem(Box::new(change.inverted())),
+            Edit::Snapshot(change) => Edit::Snapshot(Box::new(change.inverted())),
+        }
+    }
+}
+
+/// Edits of the same item recorded less than this long after the previous one are undone together.
+const MERGE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Undo and redo stacks of edits made across all editor panels.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// When the last edit was recorded, as long as it may still be merged with the next one.
+    last_recorded: Option<Instant>,
+}
+
+impl History {
+    /// Records an edit that was already applied. Edits that change nothing, such as cancelled
+    /// edit modals, are ignored.
+    pub fn record(&mut self, edit: Edit) {
+        self.record_at(edit, Instant::now());
+    }
+
+    fn record_at(&mut self, edit: Edit, now: Instant) {
+        if edit.is_noop() {
+            return;
+        }
+        self.redo.clear();
+
+        // Typing into the goal editors, dragging the climax or editing the problem name changes the
+        // problem on every frame, so changes of the same item that follow each other closely are
+        // undone together.
+        let continuous = self
+            .last_recorded
+            .replace(now)
+            .is_some_and(|last| now.duration_since(last) < MERGE_WINDOW);
+        let merged = continuous
+            && match (&edit, self.undo.last_mut()) {
+                (Edit::Goal(i, change), Some(Edit::Goal(last_i, last))) if i == last_i => {
+                    last.merge(change)
+                }
+                (Edit::CharacterGoals(i, change), Some(Edit::CharacterGoals(last_i, last)))
+                | (Edit::Beliefs(i, change), Some(Edit::Beliefs(last_i, last)))
+                    if i == last_i =>
+                {
+                    last.merge(change)
+                }
+                (Edit::Constraints(i, change), Some(Edit::Constraints(last_i, last)))
+                    if i == last_i =>
+                {
+                    last.merge(change)
+                }
+                (Edit::Outline(i, change), Some(Edit::Outline(last_i, last))) if i == last_i => {
+                    last.merge(change)
+                }
+                (Edit::Tension(i, change), Some(Edit::Tension(last_i, last))) if i == last_i => {
+                    last.merge(change)
+                }
+                (Edit::Problem(change), Some(Edit::Problem(last)))
+                    if is_in_place(change) && is_in_place(last) =>
+                {
+                    last.merge(change)
+                }
+                _ => false,
+            };
+
+        if !merged {
+            self.undo.push(edit);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self, domain: &mut PredicateDomain, problems: &mut Vec<PredicateProblem>) {
+        self.last_recorded = None;
+        if let Some(edit) = self.undo.pop() {
+            edit.clone().inverted().apply(domain, problems);
+            self.redo.push(edit);
+        }
+    }
+
+    pub fn redo(&mut self, domain: &mut PredicateDomain, problems: &mut Vec<PredicateProblem>) {
+        self.last_recorded = None;
+        if let Some(edit) = self.redo.pop() {
+            edit.apply(domain, problems);
+            self.undo.push(edit);
+        }
+    }
+}
+
+/// Whether the change edits a problem without adding or deleting it.
+fn is_in_place(change: &Change<(usize, PredicateProblem)>) -> bool {
+    matches!(
+        (&change.before, &change.after),
+        (Some((before, _)), Some((after, _))) if before == after
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        test_util::{domain_with, sym, untyped as object},
+    };
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut domain = PredicateDomain::default();
+        let mut problems = vec![PredicateProblem::default()];
+        let mut history = History::default();
+
+        for name in ["alice", "bob"] {
+            problems[0].objects.push(object(name));
+            history.record(Edit::Object(0, Change::added(object(name))));
+        }
+        problems[0].objects.remove(0);
+        history.record(Edit::Object(0, Change::removed(object("alice"))));
+
+        history.undo(&mut domain, &mut problems);
+        history.undo(&mut domain, &mut problems);
+        assert_eq!(problems[0].objects, [object("alice")]);
+
+        history.redo(&mut domain, &mut problems);
+        assert_eq!(problems[0].objects, [object("alice"), object("bob")]);
+        assert!(history.can_redo());
+
+        // A new edit discards the redo stack.
+        history.record(Edit::Goal(
+            0,
+            Change {
+                before: Some(LogicExpr::True),
+                after: Some(LogicExpr::Not(Box::new(LogicExpr::True))),
+            },
+        ));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn restores_deleted_problems_in_place() {
+        let mut domain = PredicateDomain::default();
+        let mut problems: Vec<PredicateProblem> = ["castle", "forest", "sea"]
+            .into_iter()
+            .map(|name| PredicateProblem {
+                name: name.into(),
+                ..Default::default()
+            })
+            .collect();
+        let mut history = History::default();
+
+        let forest = problems.remove(1);
+        history.record(Edit::Problem(Box::new(Change::removed((1, forest)))));
+        history.undo(&mut domain, &mut problems);
+
+        let names: Vec<&str> = problems.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["castle", "forest", "sea"]);
+    }
+
+    #[test]
+    fn ignores_cancelled_edits() {
+        let mut history = History::default();
+        let constant = (sym("sword"), TypeName("item".into()));
+
+        history.record(Edit::Constant(Change {
+            before: Some(constant.clone()),
+            after: Some(constant),
+        }));
+
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undoes_overwriting_an_entry() {
+        let mut domain = PredicateDomain::default();
+        let mut problems = Vec::new();
+        let mut history = History::default();
+        let item = (sym("sword"), TypeName("item".into()));
+        let weapon = (sym("sword"), TypeName("weapon".into()));
+
+        domain.constants.insert(weapon.0.clone(), weapon.1.clone());
+        history.record(Edit::Constant(Change {
+            before: Some(item),
+            after: Some(weapon),
+        }));
+
+        history.undo(&mut domain, &mut problems);
+        assert_eq!(domain.constants[&sym("sword")], TypeName("item".into()));
+        history.redo(&mut domain, &mut problems);
+        assert_eq!(domain.constants[&sym("sword")], TypeName("weapon".into()));
+    }
+
+    #[test]
+    fn merges_edits_made_in_one_go() {
+        let mut domain = domain_with(&[("open", 0)], &[]);
+        let parse = |text| parse_logic_expr(&domain, text).unwrap();
+        let goal = |before, after| {
+            Edit::Goal(
+                0,
+                Change {
+                    before: Some(parse(before)),
+                    after: Some(parse(after)),
+                },
+            )
+        };
+        let mut history = History::default();
+
+        let start = Instant::now();
+        history.record_at(goal("true", "open()"), start);
+        history.record_at(
+            goal("open()", "not open()"),
+            start + Duration::from_millis(100),
+        );
+        history.record_at(goal("not open()", "open()"), start + Duration::from_secs(3));
+        let mut problems = vec![PredicateProblem {
+            goal: parse("open()"),
+            ..Default::default()
+        }];
+
+        history.undo(&mut domain, &mut problems);
+        assert_eq!(problems[0].goal.to_string(), "not open()");
+        history.undo(&mut domain, &mut problems);
+        assert_eq!(problems[0].goal, LogicExpr::True);
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,247 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    axioms::{free_variables, groundings},
+    story::{
+        Bindings, CompoundTask, Method, PredicateDomain, PredicateProblem, PredicateStory,
+        StoryState, StoryStep, Symbol, TaskCall, TypeName,
+    },
+};
+
+/// How deeply compound tasks may expand into each other, so recursive methods terminate.
+const MAX_DECOMPOSITION_DEPTH: usize = 32;
+
+/// How many tasks are tried before giving up on decomposing an outline.
+const DECOMPOSITION_BUDGET: usize = 100_000;
+
+/// The tasks left to perform, with the last one on top, each with how deeply it is nested.
+type Agenda = Vec<(TaskCall, usize)>;
+
+impl PredicateDomain {
+    /// Expands the outline of the problem into a story by performing its tasks in order.
+    /// Actions are taken as they are, compound tasks are replaced by the subtasks of one of their
+    /// applicable methods, picked at random and backtracked over when the rest of the outline
+    /// cannot be performed.
+    ///
+    /// Like improvised stories, the result has to fit within the maximum length, reach the goal
+    /// and meet the constraints of the problem.
+    pub fn decompose_outline(&self, problem: &PredicateProblem) -> Option<PredicateStory> {
+        let mut agenda: Agenda = problem
+            .outline
+            .iter()
+            .rev()
+            .map(|task| (task.clone(), 0))
+            .collect();
+        let mut steps = Vec::new();
+        let mut states = vec![self.initial_story_state(problem)];
+        let mut budget = DECOMPOSITION_BUDGET;
+
+        self.decompose(problem, &mut agenda, &mut steps, &mut states, &mut budget)
+            .then(|| PredicateStory::new(steps, states))
+            .filter(|story| self.unexplained_steps(problem, story).is_empty())
+    }
+
+    /// Performs the tasks of the agenda after the given steps. On failure, the agenda, steps and
+    /// states are left as they were.
+    fn decompose(
+        &self,
+        problem: &PredicateProblem,
+        agenda: &mut Agenda,
+        steps: &mut Vec<StoryStep>,
+        states: &mut Vec<StoryState>,
+        budget: &mut usize,
+    ) -> bool {
+        let Some((task, depth)) = agenda.pop() else {
+            return self.is_finished(problem, steps, states);
+        };
+        if *budget == 0 {
+            agenda.push((task, depth));
+            return false;
+        }
+        *budget -= 1;
+
+        let state = states.last().expect("stories start in a state");
+        let performed = if self.actions.contains_key(&task.name) {
+            let step = StoryStep {
+                action: task.name.clone(),
+                arguments: task.arguments.clone(),
+            };
+
+            match self.apply_step(state, &step) {
+                Some(next) if steps.len() < problem.max_story_length as usize => {
+                    steps.push(step);
+                    states.push(next);
+
+                    let performed = self.decompose(problem, agenda, steps, states, budget);
+                    if !performed {
+                        steps.pop();
+                        states.pop();
+                    }
+                    performed
+                }
+                _ => false,
+            }
+        } else if let Some(compound) = self.tasks.get(&task.name)
+            && depth < MAX_DECOMPOSITION_DEPTH
+        {
+            let mut methods: Vec<&Method> = compound.methods.iter().collect();
+            methods.shuffle(&mut rand::rng());
+
+            let subtask_lists: Vec<Vec<TaskCall>> = methods
+                .into_iter()
+                .flat_map(|method| self.method_instances(state, compound, method, &task.arguments))
+                .collect();
+            subtask_lists.into_iter().any(|subtasks| {
+                let len = agenda.len();
+                agenda.extend(subtasks.into_iter().rev().map(|task| (task, depth + 1)));
+
+                let performed = self.decompose(problem, agenda, steps, states, budget);
+                if !performed {
+                    agenda.truncate(len);
+                }
+                performed
+            })
+        } else {
+            false
+        };
+
+        if !performed {
+            agenda.push((task, depth));
+        }
+        performed
+    }
+
+    /// The subtasks of the method for every binding of its variables under which its
+    /// precondition holds, in random order.
+    fn method_instances(
+        &self,
+        state: &StoryState,
+        task: &CompoundTask,
+        method: &Method,
+        arguments: &[Symbol],
+    ) -> Vec<Vec<TaskCall>> {
+        if arguments.len() != task.parameters.len() {
+            return Vec::new();
+        }
+        let Some(arguments) = state.resolve_all(arguments, &Bindings::new()) else {
+            return Vec::new();
+        };
+
+        let mut free = free_variables(&method.precondition, &task.parameters);
+        for subtask in &method.subtasks {
+            for argument in &subtask.arguments {
+                if argument.0.starts_with('?')
+                    && !task.parameters.iter().any(|param| param.name == *argument)
+                    && !free.contains(argument)
+                {
+                    free.push(argument.clone());
+                }
+            }
+        }
+
+        let everything = state.atoms_of_type(self, &TypeName::default());
+        let mut instances: Vec<Vec<TaskCall>> = groundings(&vec![everything; free.len()])
+            .into_iter()
+            .filter_map(|atoms| {
+                let bindings: Bindings = task
+                    .parameters
+                    .iter()
+                    .map(|param| param.name.clone())
+                    .zip(arguments.iter().copied())
+                    .chain(free.iter().cloned().zip(atoms))
+                    .collect();
+                if !state.evaluate(&method.precondition, &bindings) {
+                    return None;
+                }
+
+                let bind = |symbol: &Symbol| match bindings.get(symbol) {
+                    Some(atom) => Symbol(state.get_atom_name(*atom).unwrap_or_default().into()),
+                    None => symbol.clone(),
+                };
+                let subtasks = method
+                    .subtasks
+                    .iter()
+                    .map(|subtask| TaskCall {
+                        name: subtask.name.clone(),
+                        arguments: subtask.arguments.iter().map(bind).collect(),
+                    })
+                    .collect();
+                Some(subtasks)
+            })
+            .collect();
+
+        instances.shuffle(&mut rand::rng());
+        instances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        story::PredicateSignature,
+        test_util::{action, domain_with, objects, sym, untyped},
+    };
+
+    fn call(name: &str, arguments: &[&str]) -> TaskCall {
+        TaskCall {
+            name: sym(name),
+            arguments: arguments.iter().map(|arg| sym(arg)).collect(),
+        }
+    }
+
+    #[test]
+    fn decomposes_an_outline_into_actions() {
+        let mut domain = domain_with(&[("armed", 1), ("captive", 1), ("sword", 1)], &[]);
+        let take = action(&domain, &["?h", "?s"], "sword(?s)", "armed(?h)");
+        let free = action(&domain, &["?h", "?v"], "armed(?h)", "not captive(?v)");
+        domain.actions.insert(sym("take"), take);
+        domain.actions.insert(sym("free"), free);
+
+        // Heroes arm themselves with whichever sword there is, unless they already are.
+        let methods = vec![
+            Method {
+                name: "armed".into(),
+                precondition: parse_logic_expr(&domain, "armed(?h)").unwrap(),
+                subtasks: vec![call("free", &["?h", "?v"])],
+            },
+            Method {
+                name: "unarmed".into(),
+                precondition: parse_logic_expr(&domain, "not armed(?h) and sword(?s)").unwrap(),
+                subtasks: vec![call("take", &["?h", "?s"]), call("free", &["?h", "?v"])],
+            },
+        ];
+        domain.tasks.insert(
+            sym("rescue"),
+            CompoundTask {
+                parameters: vec![untyped("?h"), untyped("?v")],
+                methods,
+            },
+        );
+
+        let mut problem = PredicateProblem {
+            objects: objects(&["hero", "princess", "excalibur"]),
+            ..Default::default()
+        };
+        problem.initial_state.bound_predicates = vec![
+            (
+                PredicateSignature::new(sym("sword"), 1),
+                vec![sym("excalibur")],
+            ),
+            (
+                PredicateSignature::new(sym("captive"), 1),
+                vec![sym("princess")],
+            ),
+        ];
+        problem.max_story_length = 5;
+        problem.goal = parse_logic_expr(&domain, "not captive(princess)").unwrap();
+        problem.outline = vec![call("rescue", &["hero", "princess"])];
+
+        let story = domain.decompose_outline(&problem).unwrap();
+        assert_eq!(story.text, "take(hero, excalibur)\nfree(hero, princess)\n");
+
+        problem.max_story_length = 1;
+        assert!(domain.decompose_outline(&problem).is_none());
+    }
+}
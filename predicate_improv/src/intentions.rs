@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+
+use crate::story::{
+    Bindings, Fact, PredicateDomain, PredicateProblem, PredicateStory, StoryState, StoryStep,
+    Symbol,
+};
+
+impl PredicateDomain {
+    /// The characters consenting to the step: the arguments bound to the agents of its action.
+    pub fn step_agents(&self, step: &StoryStep) -> Vec<Symbol> {
+        let Some(action) = self.actions.get(&step.action) else {
+            return Vec::new();
+        };
+
+        action
+            .parameters
+            .iter()
+            .zip(&step.arguments)
+            .filter(|(param, _)| action.agents.contains(&param.name))
+            .map(|(_, argument)| argument.clone())
+            .collect()
+    }
+
+    /// The facts the precondition of the step relies on in the state it is taken in.
+    pub fn relied_on_facts(&self, step: &StoryStep, state: &StoryState) -> Vec<Fact> {
+        let mut facts = Vec::new();
+        if let Some(action) = self.actions.get(&step.action)
+            && let Some(arguments) = state.resolve_all(&step.arguments, &Bindings::new())
+        {
+            state.supporting_facts(&action.precondition, &action.bind(&arguments), &mut facts);
+        }
+        facts
+    }
+
+    /// Lists the steps whose agents have no reason to take part, along with the agent.
+    ///
+    /// As in IPOCL, a step is explained for one of its agents if it belongs to a frame of
+    /// commitment of that character: a chain of the character's own steps, each providing a fact
+    /// the next one relies on, ending in a step that establishes a fact the character's goal
+    /// relies on when the goal first holds. Goals that hold from the start explain nothing.
+    pub fn unexplained_steps(
+        &self,
+        problem: &PredicateProblem,
+        story: &PredicateStory,
+    ) -> Vec<(usize, Symbol)> {
+        let no_bindings = Bindings::new();
+
+        let agents: Vec<Vec<Symbol>> = story
+            .steps
+            .iter()
+            .map(|step| self.step_agents(step))
+            .collect();
+        let produced: Vec<HashSet<Fact>> = story
+            .states
+            .windows(2)
+            .map(|states| states[1].changed_facts(&states[0]))
+            .collect();
+        let relied_on: Vec<Vec<Fact>> = story
+            .steps
+            .iter()
+            .zip(&story.states)
+            .map(|(step, state)| self.relied_on_facts(step, state))
+            .collect();
+
+        // The last step before `before` that established the fact.
+        let producer =
+            |fact: &Fact, before: usize| (0..before).rev().find(|i| produced[*i].contains(fact));
+
+        let mut explained = HashSet::new();
+        for (character, goal) in &problem.character_goals {
+            let Some(achieved) = story
+                .states
+                .iter()
+                .position(|state| state.evaluate(goal, &no_bindings))
+                .filter(|achieved| *achieved > 0)
+            else {
+                continue;
+            };
+
+            let mut facts = Vec::new();
+            story.states[achieved].supporting_facts(goal, &no_bindings, &mut facts);
+
+            let mut frame: Vec<usize> = facts
+                .iter()
+                .filter_map(|fact| producer(fact, achieved))
+                .collect();
+            while let Some(i) = frame.pop() {
+                if agents[i].contains(character) && explained.insert((i, character)) {
+                    frame.extend(relied_on[i].iter().filter_map(|fact| producer(fact, i)));
+                }
+            }
+        }
+
+        agents
+            .iter()
+            .enumerate()
+            .flat_map(|(i, agents)| agents.iter().map(move |agent| (i, agent)))
+            .filter(|step| !explained.contains(step))
+            .map(|(i, agent)| (i, agent.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        story::PredicateSignature,
+        test_util::{action, domain_with, problem_with, step, sym},
+    };
+
+    /// A knight who has to take a sword before slaying the dragon, and can also sing.
+    fn domain() -> PredicateDomain {
+        let mut domain = domain_with(&[("armed", 1), ("alive", 1), ("sang", 1)], &[]);
+        for (name, parameters, precondition, effect) in [
+            ("take_sword", &["?k"][..], "not armed(?k)", "armed(?k)"),
+            ("sing", &["?k"], "true", "sang(?k)"),
+            (
+                "slay",
+                &["?k", "?d"],
+                "armed(?k) and alive(?d)",
+                "not alive(?d)",
+            ),
+        ] {
+            let mut action = action(&domain, parameters, precondition, effect);
+            action.agents = vec![sym("?k")];
+            domain.actions.insert(sym(name), action);
+        }
+        domain
+    }
+
+    #[test]
+    fn explains_steps_leading_to_a_goal() {
+        let domain = domain();
+
+        let mut problem = problem_with(&domain, &["knight", "dragon"], "true");
+        problem.initial_state.bound_predicates.push((
+            PredicateSignature::new(sym("alive"), 1),
+            vec![sym("dragon")],
+        ));
+        problem.character_goals.push((
+            sym("knight"),
+            parse_logic_expr(&domain, "not alive(dragon)").unwrap(),
+        ));
+
+        let err = domain
+            .validate_story(
+                &problem,
+                vec![
+                    step("take_sword", &["knight"]),
+                    step("sing", &["knight"]),
+                    step("slay", &["knight", "dragon"]),
+                ],
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "step 2: nothing explains why `knight` takes part"
+        );
+        assert!(
+            domain
+                .validate_story(
+                    &problem,
+                    vec![
+                        step("take_sword", &["knight"]),
+                        step("slay", &["knight", "dragon"]),
+                    ],
+                )
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,598 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::story::{
+    BeliefUpdate, Comparison, FluentUpdate, LogicExpr, NumericExpr, PredicateDomain,
+    PredicateSignature, Symbol,
+};
+
+/// A parse error, pointing at the byte range of the offending text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "column {}: {}", self.span.start + 1, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    LParen,
+    RParen,
+    Comma,
+    Not,
+    And,
+    Or,
+    True,
+    Update(FluentUpdate),
+    Believes,
+    Learn(BeliefUpdate),
+    Compare(Comparison),
+    Plus,
+    Minus,
+    Star,
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("`{name}`"),
+            Token::Number(value) => format!("`{value}`"),
+            Token::LParen => "`(`".into(),
+            Token::RParen => "`)`".into(),
+            Token::Comma => "`,`".into(),
+            Token::Not => "`not`".into(),
+            Token::And => "`and`".into(),
+            Token::Or => "`or`".into(),
+            Token::True => "`true`".into(),
+            Token::Update(kind) => format!("`{}`", kind.keyword()),
+            Token::Believes => "`believes`".into(),
+            Token::Learn(kind) => format!("`{}`", kind.keyword()),
+            Token::Compare(comparison) => format!("`{}`", comparison.symbol()),
+            Token::Plus => "`+`".into(),
+            Token::Minus => "`-`".into(),
+            Token::Star => "`*`".into(),
+            Token::End => "end of input".into(),
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '?')
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '¬' => Token::Not,
+            '∧' => Token::And,
+            '∨' => Token::Or,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '=' => Token::Compare(Comparison::Equal),
+            '<' | '>' => {
+                let or_equal = chars.next_if(|(_, c)| *c == '=').is_some();
+                let comparison = match (c, or_equal) {
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessOrEqual,
+                    ('>', false) => Comparison::Greater,
+                    _ => Comparison::GreaterOrEqual,
+                };
+
+                tokens.push((
+                    Token::Compare(comparison),
+                    start..start + 1 + or_equal as usize,
+                ));
+                continue;
+            }
+            c if is_symbol_char(c) => {
+                // Names like `has-key` may contain dashes, but `gold(?x)-1` and `5-3` are
+                // subtractions, so a dash only belongs to a name when a letter follows it.
+                let number = c.is_ascii_digit();
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|&(i, c)| match c {
+                    '-' => !number && text[i + 1..].starts_with(char::is_alphabetic),
+                    c => is_symbol_char(c),
+                }) {
+                    end = i + c.len_utf8();
+                }
+
+                let word = &text[start..end];
+                let token = match word {
+                    "not" => Token::Not,
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "true" => Token::True,
+                    "assign" => Token::Update(FluentUpdate::Assign),
+                    "increase" => Token::Update(FluentUpdate::Increase),
+                    "decrease" => Token::Update(FluentUpdate::Decrease),
+                    "believes" => Token::Believes,
+                    "observe" => Token::Learn(BeliefUpdate::Observe),
+                    "inform" => Token::Learn(BeliefUpdate::Inform),
+                    _ => match word.parse() {
+                        Ok(value) => Token::Number(value),
+                        Err(_) => Token::Ident(word.into()),
+                    },
+                };
+
+                tokens.push((token, start..end));
+                continue;
+            }
+            c => {
+                return Err(ParseError {
+                    span: start..start + c.len_utf8(),
+                    message: format!("unexpected character `{c}`"),
+                });
+            }
+        };
+
+        tokens.push((token, start..start + c.len_utf8()));
+    }
+
+    tokens.push((Token::End, text.len()..text.len()));
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    domain: &'a PredicateDomain,
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn next(&mut self) -> (Token, Range<usize>) {
+        let token = self.tokens[self.pos].clone();
+        if token.0 != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<Range<usize>, ParseError> {
+        let (token, span) = self.next();
+        if token == expected {
+            Ok(span)
+        } else {
+            Err(ParseError {
+                span,
+                message: format!(
+                    "expected {}, found {}",
+                    expected.describe(),
+                    token.describe()
+                ),
+            })
+        }
+    }
+
+    fn or(&mut self) -> Result<LogicExpr, ParseError> {
+        let mut expr = self.and()?;
+        while *self.peek() == Token::Or {
+            self.next();
+            expr = LogicExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<LogicExpr, ParseError> {
+        let mut expr = self.unary()?;
+        while *self.peek() == Token::And {
+            self.next();
+            expr = LogicExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<LogicExpr, ParseError> {
+        if *self.peek() == Token::Not {
+            self.next();
+            return Ok(LogicExpr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<LogicExpr, ParseError> {
+        let start = self.pos;
+
+        match self.next() {
+            (Token::True, _) => Ok(LogicExpr::True),
+            (Token::LParen, _) => {
+                // The parentheses may group a condition or a number, as in `(gold(?a) + 1) > 2`,
+                // so parse both and keep whichever gets further.
+                let condition = self
+                    .or()
+                    .and_then(|expr| self.expect(Token::RParen).map(|_| expr));
+                if let Ok(expr) = &condition
+                    && !matches!(
+                        self.peek(),
+                        Token::Compare(_) | Token::Plus | Token::Minus | Token::Star
+                    )
+                {
+                    return Ok(expr.clone());
+                }
+
+                self.pos = start;
+                match (condition, self.comparison()) {
+                    (_, Ok(expr)) => Ok(expr),
+                    (Err(condition_err), Err(err))
+                        if condition_err.span.start >= err.span.start =>
+                    {
+                        Err(condition_err)
+                    }
+                    (_, Err(err)) => Err(err),
+                }
+            }
+            (Token::Update(kind), _) => self.update(kind),
+            (Token::Believes, _) => {
+                let (character, expr) = self.belief()?;
+                Ok(LogicExpr::Believes(character, Box::new(expr)))
+            }
+            (Token::Learn(kind), _) => {
+                let (character, expr) = self.belief()?;
+                Ok(LogicExpr::Learn(kind, character, Box::new(expr)))
+            }
+            (Token::Number(_) | Token::Minus, _) => {
+                self.pos = start;
+                self.comparison()
+            }
+            (Token::Ident(name), _) if self.is_fluent(&name) => {
+                self.pos = start;
+                self.comparison()
+            }
+            (Token::Ident(name), span) => self.predicate(name, span),
+            (token, span) => Err(ParseError {
+                span,
+                message: format!("expected a condition, found {}", token.describe()),
+            }),
+        }
+    }
+
+    /// Parses the argument list following a predicate or fluent name, which may be omitted if
+    /// there are no arguments.
+    fn arguments(&mut self) -> Result<Vec<Symbol>, ParseError> {
+        let mut arguments = Vec::new();
+
+        if *self.peek() == Token::LParen {
+            self.next();
+            if *self.peek() == Token::RParen {
+                self.next();
+            } else {
+                loop {
+                    match self.next() {
+                        (Token::Ident(arg), _) => arguments.push(Symbol(arg)),
+                        (token, span) => {
+                            return Err(ParseError {
+                                span,
+                                message: format!(
+                                    "expected an argument, found {}",
+                                    token.describe()
+                                ),
+                            });
+                        }
+                    }
+
+                    match self.next() {
+                        (Token::Comma, _) => {}
+                        (Token::RParen, _) => break,
+                        (token, span) => {
+                            return Err(ParseError {
+                                span,
+                                message: format!("expected `,` or `)`, found {}", token.describe()),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    fn predicate(
+        &mut self,
+        name: String,
+        name_span: Range<usize>,
+    ) -> Result<LogicExpr, ParseError> {
+        let arguments = self.arguments()?;
+
+        let signature = PredicateSignature::new(Symbol(name), arguments.len() as u32);
+        if !self.domain.predicates.contains_key(&signature) && !self.domain.is_derived(&signature) {
+            return Err(ParseError {
+                span: name_span,
+                message: format!(
+                    "unknown predicate `{}/{}`",
+                    signature.function.0, signature.arity
+                ),
+            });
+        }
+
+        Ok(LogicExpr::Predicate(signature, arguments))
+    }
+
+    fn is_fluent(&self, name: &str) -> bool {
+        self.domain
+            .fluents
+            .keys()
+            .any(|signature| signature.function.0 == name)
+    }
+
+    fn fluent(
+        &mut self,
+        name: String,
+        name_span: Range<usize>,
+    ) -> Result<(PredicateSignature, Vec<Symbol>), ParseError> {
+        let arguments = self.arguments()?;
+
+        let signature = PredicateSignature::new(Symbol(name), arguments.len() as u32);
+        if !self.domain.fluents.contains_key(&signature) {
+            return Err(ParseError {
+                span: name_span,
+                message: format!(
+                    "unknown fluent `{}/{}`",
+                    signature.function.0, signature.arity
+                ),
+            });
+        }
+
+        Ok((signature, arguments))
+    }
+
+    /// Parses `increase(fluent(args), value)` after the keyword.
+    fn update(&mut self, kind: FluentUpdate) -> Result<LogicExpr, ParseError> {
+        self.expect(Token::LParen)?;
+
+        let (signature, arguments) = match self.next() {
+            (Token::Ident(name), span) => self.fluent(name, span)?,
+            (token, span) => {
+                return Err(ParseError {
+                    span,
+                    message: format!("expected a fluent, found {}", token.describe()),
+                });
+            }
+        };
+
+        self.expect(Token::Comma)?;
+        let value = self.sum()?;
+        self.expect(Token::RParen)?;
+
+        Ok(LogicExpr::Update(kind, signature, arguments, value))
+    }
+
+    /// Parses `(character, condition)` after `believes`, `observe` or `inform`.
+    fn belief(&mut self) -> Result<(Symbol, LogicExpr), ParseError> {
+        self.expect(Token::LParen)?;
+
+        let character = match self.next() {
+            (Token::Ident(name), _) => Symbol(name),
+            (token, span) => {
+                return Err(ParseError {
+                    span,
+                    message: format!("expected a character, found {}", token.describe()),
+                });
+            }
+        };
+
+        self.expect(Token::Comma)?;
+        let expr = self.or()?;
+        self.expect(Token::RParen)?;
+
+        Ok((character, expr))
+    }
+
+    fn comparison(&mut self) -> Result<LogicExpr, ParseError> {
+        let lhs = self.sum()?;
+
+        let comparison = match self.next() {
+            (Token::Compare(comparison), _) => comparison,
+            (token, span) => {
+                return Err(ParseError {
+                    span,
+                    message: format!("expected a comparison, found {}", token.describe()),
+                });
+            }
+        };
+
+        Ok(LogicExpr::Compare(comparison, lhs, self.sum()?))
+    }
+
+    fn sum(&mut self) -> Result<NumericExpr, ParseError> {
+        let mut expr = self.product()?;
+        loop {
+            match self.peek() {
+                Token::Plus => {
+                    self.next();
+                    expr = NumericExpr::Add(Box::new(expr), Box::new(self.product()?));
+                }
+                Token::Minus => {
+                    self.next();
+                    expr = NumericExpr::Sub(Box::new(expr), Box::new(self.product()?));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<NumericExpr, ParseError> {
+        let mut expr = self.number()?;
+        while *self.peek() == Token::Star {
+            self.next();
+            expr = NumericExpr::Mul(Box::new(expr), Box::new(self.number()?));
+        }
+        Ok(expr)
+    }
+
+    fn number(&mut self) -> Result<NumericExpr, ParseError> {
+        match self.next() {
+            (Token::Number(value), _) => Ok(NumericExpr::Number(value)),
+            (Token::Minus, _) => match self.next() {
+                (Token::Number(value), _) => Ok(NumericExpr::Number(-value)),
+                (token, span) => Err(ParseError {
+                    span,
+                    message: format!("expected a number, found {}", token.describe()),
+                }),
+            },
+            (Token::LParen, _) => {
+                let expr = self.sum()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            (Token::Ident(name), span) => {
+                let (signature, arguments) = self.fluent(name, span)?;
+                Ok(NumericExpr::Fluent(signature, arguments))
+            }
+            (token, span) => Err(ParseError {
+                span,
+                message: format!("expected a number or fluent, found {}", token.describe()),
+            }),
+        }
+    }
+}
+
+/// Parses expressions such as `at(?x, ?here) and not (dead(?x) or asleep(?x))`. `not` binds
+/// tighter than `and`, which binds tighter than `or`; both binary operators are left-associative.
+/// Fluents are compared with `<`, `<=`, `=`, `>=` and `>`, as in `gold(?a) >= gold(?b) + 2`, and
+/// changed with `assign`, `increase` and `decrease`, as in `decrease(health(?c), 1)`. Beliefs are
+/// tested with `believes(?c, at(?x, ?l))` and changed with `observe` and `inform`. Every
+/// predicate and fluent must be declared or derived in the domain with a matching arity.
+pub fn parse_logic_expr(domain: &PredicateDomain, text: &str) -> Result<LogicExpr, ParseError> {
+    let mut parser = Parser {
+        domain,
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+
+    let expr = parser.or()?;
+    match parser.next() {
+        (Token::End, _) => Ok(expr),
+        (token, span) => Err(ParseError {
+            span,
+            message: format!(
+                "expected `and`, `or` or end of input, found {}",
+                token.describe()
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain() -> PredicateDomain {
+        let mut domain = PredicateDomain::default();
+        for (name, arity) in [("at", 2), ("dead", 1), ("asleep", 1), ("night", 0)] {
+            domain.predicates.insert(
+                PredicateSignature::new(Symbol(name.into()), arity),
+                Vec::new(),
+            );
+        }
+        for (name, arity) in [("gold", 1), ("trust", 2)] {
+            domain.fluents.insert(
+                PredicateSignature::new(Symbol(name.into()), arity),
+                Vec::new(),
+            );
+        }
+        domain
+    }
+
+    #[test]
+    fn round_trips_through_pretty_printer() {
+        let domain = domain();
+
+        for text in [
+            "true",
+            "at(?x, ?here) and not (dead(?x) or asleep(?x))",
+            "night() or dead(?x) and asleep(?x)",
+            "(night() or dead(?x)) and asleep(?x)",
+            "dead(?x) and (asleep(?x) and night())",
+            "not not night()",
+        ] {
+            let expr = parse_logic_expr(&domain, text).unwrap();
+            assert_eq!(expr.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn round_trips_numeric_expressions() {
+        let domain = domain();
+
+        for text in [
+            "gold(?x) >= 10",
+            "trust(?x, ?y) - 1 < gold(?x) * (gold(?y) + -2)",
+            "(gold(?x) + 1) * 2 = 4 and not dead(?x)",
+            "increase(gold(?x), trust(?x, ?y) * 5) and decrease(gold(?y), 5)",
+            "assign(trust(?y, ?x), 0)",
+        ] {
+            let expr = parse_logic_expr(&domain, text).unwrap();
+            assert_eq!(expr.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn round_trips_beliefs() {
+        let domain = domain();
+
+        for text in [
+            "believes(?x, dead(?y) and not asleep(?y)) and not believes(?y, night())",
+            "observe(?x, at(?y, ?here)) and inform(?y, not dead(?x))",
+        ] {
+            let expr = parse_logic_expr(&domain, text).unwrap();
+            assert_eq!(expr.to_string(), text);
+        }
+
+        let err = parse_logic_expr(&domain, "increase(dead(?x), 1)").unwrap_err();
+        assert_eq!(err.message, "unknown fluent `dead/1`");
+    }
+
+    #[test]
+    fn reports_error_spans() {
+        let domain = domain();
+
+        let err = parse_logic_expr(&domain, "dead(?x) and alive(?x)").unwrap_err();
+        assert_eq!(err.span, 13..18);
+
+        let err = parse_logic_expr(&domain, "at(?x ?y)").unwrap_err();
+        assert_eq!(err.span, 6..8);
+
+        let err = parse_logic_expr(&domain, "(dead(?x)").unwrap_err();
+        assert_eq!(err.span, 9..9);
+    }
+
+    #[test]
+    fn reads_dashes_between_operands_as_minus() {
+        let mut domain = domain();
+        domain.predicates.insert(
+            PredicateSignature::new(Symbol("has-key".into()), 1),
+            Vec::new(),
+        );
+
+        for (text, printed) in [
+            ("gold(?x)-1 > 5-3", "gold(?x) - 1 > 5 - 3"),
+            ("-2 < gold(?x)", "-2 < gold(?x)"),
+            (
+                "has-key(?x) and gold(?x)--2 = 0",
+                "has-key(?x) and gold(?x) - -2 = 0",
+            ),
+        ] {
+            let expr = parse_logic_expr(&domain, text).unwrap();
+            assert_eq!(expr.to_string(), printed);
+        }
+    }
+}
@@ -1,15 +1,63 @@
+use std::num::NonZero;
+
 use eframe::{egui, NativeOptions};
 
 use crate::app::PredicateImprovApp;
 
 mod app;
 mod action_panel;
+mod axioms;
+mod compact;
+mod compiled;
+mod constraints;
+mod diversity;
+mod generation;
+mod grounding;
+mod history;
+mod htn;
+mod intentions;
+mod logic_parser;
+mod metrics;
+mod partial_order;
 mod predicate_panel;
 mod problem_panel;
+mod rename;
+mod search;
+mod session;
 mod types_and_constants_panel;
 mod story;
+mod story_graph;
+mod story_graph_panel;
+mod story_inspector_panel;
+mod task_panel;
+mod tension;
+#[cfg(test)]
+mod test_util;
+mod validator;
+mod validator_panel;
+
+/// Reads the command line, which may only set the number of threads stories are generated on with
+/// `--threads N`.
+fn threads_arg(mut args: impl Iterator<Item = String>) -> Result<Option<usize>, String> {
+    let mut threads = None;
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--threads") {
+            Some("") => args.next().ok_or("`--threads` needs a number")?,
+            Some(value) if value.starts_with('=') => value[1..].into(),
+            _ => return Err(format!("unknown argument `{arg}`")),
+        };
+        let count: NonZero<usize> = value.parse().map_err(|_| format!("`{value}` is not a number of threads"))?;
+        threads = Some(count.get());
+    }
+    Ok(threads)
+}
 
 fn main() -> eframe::Result {
+    let threads = threads_arg(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\nusage: predicate_improv [--threads N]");
+        std::process::exit(2);
+    });
+
     let native_options = NativeOptions {
         viewport: egui::ViewportBuilder {
             title: Some("Predicate Improvizer".into()),
@@ -18,5 +66,11 @@ fn main() -> eframe::Result {
         },
         ..Default::default()
     };
-    eframe::run_native("Predicate Improvizer", native_options, Box::new(|cc| Ok(Box::new(PredicateImprovApp::new(cc)))))
+    eframe::run_native("Predicate Improvizer", native_options, Box::new(move |cc| {
+        let mut app = PredicateImprovApp::new(cc);
+        if let Some(threads) = threads {
+            app.threads = threads;
+        }
+        Ok(Box::new(app))
+    }))
 }
@@ -0,0 +1,284 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    partial_order::PlanNode,
+    story::{Bindings, Fact, PredicateDomain, PredicateProblem, PredicateStory},
+};
+
+/// Something measured about a story, to compare and rank stories by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoryMetric {
+    /// The number of steps.
+    Length,
+    /// The number of different characters acting as agents of some step.
+    Characters,
+    /// The fraction of the goal and the character goals that hold at the end.
+    GoalSatisfaction,
+    /// The number of facts a step changes back after an earlier step changed them, including
+    /// characters changing their minds.
+    Reversals,
+    /// The fraction of steps that contribute to the goal through a chain of causal links.
+    CausalCoherence,
+    /// How closely the tension of the story follows the arc of the problem.
+    TensionFit,
+}
+
+impl StoryMetric {
+    pub const ALL: [StoryMetric; 6] = [
+        StoryMetric::Length,
+        StoryMetric::Characters,
+        StoryMetric::GoalSatisfaction,
+        StoryMetric::Reversals,
+        StoryMetric::CausalCoherence,
+        StoryMetric::TensionFit,
+    ];
+
+    pub fn measure(
+        self,
+        domain: &PredicateDomain,
+        problem: &PredicateProblem,
+        story: &PredicateStory,
+    ) -> f32 {
+        match self {
+            StoryMetric::Length => story.steps.len() as f32,
+            StoryMetric::Characters => {
+                let characters: HashSet<_> = story
+                    .steps
+                    .iter()
+                    .flat_map(|step| domain.step_agents(step))
+                    .collect();
+                characters.len() as f32
+            }
+            StoryMetric::GoalSatisfaction => {
+                let goals: Vec<_> = problem
+                    .has_goal()
+                    .then_some(&problem.goal)
+                    .into_iter()
+                    .chain(problem.character_goals.iter().map(|(_, goal)| goal))
+                    .collect();
+                let Some(last) = story.states.last() else {
+                    return 0.;
+                };
+                if goals.is_empty() {
+                    return 1.;
+                }
+
+                let satisfied = goals
+                    .iter()
+                    .filter(|goal| last.evaluate(goal, &Bindings::new()))
+                    .count();
+                satisfied as f32 / goals.len() as f32
+            }
+            StoryMetric::Reversals => {
+                let mut changed = HashSet::new();
+                let mut reversals = 0;
+                for states in story.states.windows(2) {
+                    for fact in states[1].changed_facts(&states[0]) {
+                        if reversed(&fact).is_some_and(|reversed| changed.contains(&reversed)) {
+                            reversals += 1;
+                        }
+                        changed.insert(fact);
+                    }
+                }
+                reversals as f32
+            }
+            StoryMetric::CausalCoherence => {
+                if story.steps.is_empty() {
+                    return 1.;
+                }
+
+                let links = domain.causal_links(problem, story);
+                let mut contributing = HashSet::new();
+                let mut open = vec![PlanNode::Goal];
+                while let Some(consumer) = open.pop() {
+                    for link in links.iter().filter(|link| link.consumer == consumer) {
+                        if let PlanNode::Step(step) = link.producer
+                            && contributing.insert(step)
+                        {
+                            open.push(link.producer);
+                        }
+                    }
+                }
+                contributing.len() as f32 / story.steps.len() as f32
+            }
+            StoryMetric::TensionFit => problem.tension.fit(&problem.tension.curve(story)),
+        }
+    }
+
+    /// Formats a value of the metric, with fractions as percentages.
+    pub fn format(self, value: f32) -> String {
+        match self {
+            StoryMetric::GoalSatisfaction
+            | StoryMetric::CausalCoherence
+            | StoryMetric::TensionFit => {
+                format!("{:.0}%", value * 100.)
+            }
+            StoryMetric::Length | StoryMetric::Characters | StoryMetric::Reversals => {
+                format!("{value}")
+            }
+        }
+    }
+}
+
+impl Display for StoryMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoryMetric::Length => write!(f, "Length"),
+            StoryMetric::Characters => write!(f, "Characters"),
+            StoryMetric::GoalSatisfaction => write!(f, "Goals met"),
+            StoryMetric::Reversals => write!(f, "Reversals"),
+            StoryMetric::CausalCoherence => write!(f, "Coherence"),
+            StoryMetric::TensionFit => write!(f, "Tension fit"),
+        }
+    }
+}
+
+/// The fact that undoes the change to `fact`, if it can be undone.
+fn reversed(fact: &Fact) -> Option<Fact> {
+    match fact {
+        Fact::Predicate(signature, arguments) => {
+            Some(Fact::Absent(signature.clone(), arguments.clone()))
+        }
+        Fact::Absent(signature, arguments) => {
+            Some(Fact::Predicate(signature.clone(), arguments.clone()))
+        }
+        Fact::Belief(character, signature, arguments, value) => Some(Fact::Belief(
+            *character,
+            signature.clone(),
+            arguments.clone(),
+            !value,
+        )),
+        Fact::Fluent(..) => None,
+    }
+}
+
+/// How much each metric counts towards the score stories are ranked by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ranking {
+    /// The weight of each metric, in the order of `StoryMetric::ALL`.
+    pub weights: [f32; StoryMetric::ALL.len()],
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Self {
+            weights: StoryMetric::ALL.map(|metric| match metric {
+                StoryMetric::GoalSatisfaction
+                | StoryMetric::CausalCoherence
+                | StoryMetric::TensionFit => 1.,
+                StoryMetric::Length | StoryMetric::Characters | StoryMetric::Reversals => 0.,
+            }),
+        }
+    }
+}
+
+/// The value of every metric for a story, along with the weighted sum it is ranked by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoryScore {
+    pub values: [f32; StoryMetric::ALL.len()],
+    pub total: f32,
+}
+
+impl Display for StoryScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (metric, value) in StoryMetric::ALL.iter().zip(self.values) {
+            write!(f, "{metric} {}, ", metric.format(value))?;
+        }
+        write!(f, "score {:.2}", self.total)
+    }
+}
+
+impl PredicateDomain {
+    pub fn score_story(
+        &self,
+        problem: &PredicateProblem,
+        story: &PredicateStory,
+        ranking: &Ranking,
+    ) -> StoryScore {
+        let values = StoryMetric::ALL.map(|metric| metric.measure(self, problem, story));
+        let total = values
+            .iter()
+            .zip(&ranking.weights)
+            .map(|(value, weight)| value * weight)
+            .sum();
+        StoryScore { values, total }
+    }
+
+    /// Sorts the stories from the highest score to the lowest.
+    pub fn rank_stories(
+        &self,
+        problem: &PredicateProblem,
+        stories: &mut Vec<PredicateStory>,
+        ranking: &Ranking,
+    ) {
+        let mut scored: Vec<_> = std::mem::take(stories)
+            .into_iter()
+            .map(|story| (self.score_story(problem, &story, ranking).total, story))
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        stories.extend(scored.into_iter().map(|(_, story)| story));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        test_util::{domain_with, problem_with, step, story_of, sym},
+    };
+
+    #[test]
+    fn measures_and_ranks_stories() {
+        let mut domain = domain_with(
+            &[("has-key", 1), ("open", 1), ("sang", 1)],
+            &[
+                ("take-key", "true", "has-key(?c)"),
+                ("drop-key", "has-key(?c)", "not has-key(?c)"),
+                ("open-door", "has-key(?c)", "open(?c)"),
+                ("sing", "true", "sang(?c)"),
+            ],
+        );
+        for action in domain.actions.values_mut() {
+            action.agents = vec![sym("?c")];
+        }
+
+        let mut problem = problem_with(&domain, &["alice", "bob"], "open(alice)");
+        problem.character_goals.push((
+            sym("alice"),
+            parse_logic_expr(&domain, "sang(bob)").unwrap(),
+        ));
+
+        // Not every step serves a character goal, which the validator would reject.
+        let story = |steps: &[(&str, &str)]| {
+            let steps = steps
+                .iter()
+                .map(|(action, character)| step(action, &[character]))
+                .collect();
+            story_of(&domain, &problem, steps)
+        };
+        let focused = story(&[("take-key", "alice"), ("open-door", "alice")]);
+        let rambling = story(&[
+            ("take-key", "alice"),
+            ("sing", "bob"),
+            ("open-door", "alice"),
+            ("drop-key", "alice"),
+            ("take-key", "alice"),
+        ]);
+
+        let ranking = Ranking::default();
+        let score = domain.score_story(&problem, &rambling, &ranking);
+        assert_eq!(score.values, [5., 2., 1., 2., 0.4, 1.]);
+        let score = domain.score_story(&problem, &focused, &ranking);
+        assert_eq!(score.values, [2., 1., 0.5, 0., 1., 1.]);
+        assert_eq!(
+            score.to_string(),
+            "Length 2, Characters 1, Goals met 50%, Reversals 0, Coherence 100%, Tension fit 100%, \
+             score 2.50"
+        );
+
+        let mut stories = vec![rambling, focused];
+        domain.rank_stories(&problem, &mut stories, &ranking);
+        assert_eq!(stories[0].steps.len(), 2);
+    }
+}
@@ -0,0 +1,536 @@
+use std::ops::Range;
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    axioms::groundings,
+    generation::{Progress, stream_attempts},
+    story::{
+        Bindings, Fact, LogicExpr, PredicateDomain, PredicateProblem, PredicateStory, StoryState,
+        StoryStep, Symbol,
+    },
+};
+
+/// How many times planning starts over before giving up.
+const PLANNING_ATTEMPTS: usize = 20;
+
+/// How many partial plans are refined per attempt.
+const PLANNING_BUDGET: usize = 10_000;
+
+/// Node of a partial plan holding the initial state as effects.
+const START: usize = 0;
+/// Node of a partial plan holding the goal as preconditions. Steps are the nodes after it.
+const GOAL: usize = 1;
+
+/// Where a causal link starts or ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlanNode {
+    /// The initial state of the problem.
+    Start,
+    /// The step at the index into `PartialOrderPlan::steps`.
+    Step(usize),
+    /// The goal of the problem.
+    Goal,
+}
+
+/// A fact established by one node and relied on by a later one, e.g. the key Alice took which
+/// lets her open the door.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CausalLink {
+    pub producer: PlanNode,
+    pub consumer: PlanNode,
+    /// Either `Fact::Predicate` or `Fact::Absent`.
+    pub fact: Fact,
+}
+
+impl CausalLink {
+    /// Formats the fact as `at(alice, castle)` or `not at(alice, castle)`.
+    pub fn fact_label(&self, state: &StoryState) -> String {
+        match &self.fact {
+            Fact::Predicate(signature, arguments) => state.fact_label(signature, arguments),
+            Fact::Absent(signature, arguments) => {
+                format!("not {}", state.fact_label(signature, arguments))
+            }
+            Fact::Fluent(signature, arguments) => state.fact_label(signature, arguments),
+            Fact::Belief(character, signature, arguments, value) => {
+                state.belief_label(*character, signature, arguments, *value)
+            }
+        }
+    }
+}
+
+/// Steps that only need to happen in the given order where one relies on another, as found by
+/// partial-order causal-link planning.
+#[derive(Debug, Clone, Default)]
+pub struct PartialOrderPlan {
+    pub steps: Vec<StoryStep>,
+    /// Pairs of indices into `steps` where the first step has to happen before the second,
+    /// including the order implied by causal links between steps.
+    pub orderings: Vec<(usize, usize)>,
+    pub causal_links: Vec<CausalLink>,
+}
+
+impl PartialOrderPlan {
+    /// Picks one order of the steps at random that keeps every ordering of the plan.
+    pub fn linearize(&self) -> Vec<usize> {
+        let mut remaining: Vec<usize> = (0..self.steps.len()).collect();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        while !remaining.is_empty() {
+            let ready: Vec<usize> = remaining
+                .iter()
+                .copied()
+                .filter(|step| {
+                    !self
+                        .orderings
+                        .iter()
+                        .any(|(before, after)| after == step && remaining.contains(before))
+                })
+                .collect();
+            // Orderings never form a cycle, so some step is always ready.
+            let next = ready[rand::random_range(0..ready.len())];
+
+            remaining.retain(|step| *step != next);
+            order.push(next);
+        }
+
+        order
+    }
+
+    /// The same plan with its steps in the given order, which has to be a permutation of the
+    /// step indices.
+    pub fn reordered(self, order: &[usize]) -> Self {
+        let mut index = vec![0; order.len()];
+        for (new, old) in order.iter().enumerate() {
+            index[*old] = new;
+        }
+        let node = |node: PlanNode| match node {
+            PlanNode::Step(old) => PlanNode::Step(index[old]),
+            node => node,
+        };
+
+        Self {
+            steps: order.iter().map(|old| self.steps[*old].clone()).collect(),
+            orderings: self
+                .orderings
+                .iter()
+                .map(|(before, after)| (index[*before], index[*after]))
+                .collect(),
+            causal_links: self
+                .causal_links
+                .into_iter()
+                .map(|link| CausalLink {
+                    producer: node(link.producer),
+                    consumer: node(link.consumer),
+                    fact: link.fact,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A grounding of an action whose precondition and effect are conjunctions of literals.
+struct Operator {
+    step: StoryStep,
+    preconditions: Vec<Fact>,
+    effects: Vec<Fact>,
+}
+
+/// A plan under construction. Node `START` is the initial state, `GOAL` the goal, and node
+/// `i + 2` the step `steps[i]`.
+#[derive(Debug, Clone, Default)]
+struct PartialPlan {
+    /// Indices into the operators.
+    steps: Vec<usize>,
+    orderings: Vec<(usize, usize)>,
+    links: Vec<(usize, usize, Fact)>,
+    /// Preconditions of nodes that no causal link provides yet.
+    open: Vec<(usize, Fact)>,
+}
+
+impl PartialPlan {
+    fn nodes(&self) -> Range<usize> {
+        START..self.steps.len() + 2
+    }
+
+    /// Whether `first` has to happen before `second`.
+    fn before(&self, first: usize, second: usize) -> bool {
+        if first == second || first == GOAL || second == START {
+            return false;
+        }
+        if first == START || second == GOAL {
+            return true;
+        }
+
+        let mut stack = vec![first];
+        let mut visited = vec![false; self.steps.len() + 2];
+        while let Some(node) = stack.pop() {
+            for (before, after) in &self.orderings {
+                if *before == node && !visited[*after] {
+                    if *after == second {
+                        return true;
+                    }
+                    visited[*after] = true;
+                    stack.push(*after);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// Searches for partial plans by repeatedly providing an open precondition with a causal link
+/// and resolving the threats this creates, backtracking over both choices.
+struct Planner<'a> {
+    operators: Vec<Operator>,
+    initial: &'a StoryState,
+    max_steps: usize,
+    budget: usize,
+}
+
+impl Planner<'_> {
+    fn provides(&self, plan: &PartialPlan, node: usize, fact: &Fact) -> bool {
+        match node {
+            START => match fact {
+                Fact::Predicate(signature, arguments) => self.initial.holds(signature, arguments),
+                Fact::Absent(signature, arguments) => !self.initial.holds(signature, arguments),
+                _ => false,
+            },
+            GOAL => false,
+            step => self.operators[plan.steps[step - 2]].effects.contains(fact),
+        }
+    }
+
+    /// A link and a step that could undo its fact between its producer and consumer.
+    fn threat(&self, plan: &PartialPlan) -> Option<(usize, usize, usize)> {
+        plan.links.iter().find_map(|(producer, consumer, fact)| {
+            let negated = negated(fact);
+            (GOAL + 1..plan.steps.len() + 2)
+                .find(|step| {
+                    step != producer
+                        && step != consumer
+                        && self.operators[plan.steps[step - 2]]
+                            .effects
+                            .contains(&negated)
+                        && !plan.before(*step, *producer)
+                        && !plan.before(*consumer, *step)
+                })
+                .map(|step| (*producer, *consumer, step))
+        })
+    }
+
+    fn refine(&mut self, mut plan: PartialPlan) -> Option<PartialPlan> {
+        if self.budget == 0 {
+            return None;
+        }
+        self.budget -= 1;
+
+        // Threats are resolved by moving the step after the consumer or before the producer.
+        if let Some((producer, consumer, step)) = self.threat(&plan) {
+            let mut resolutions = Vec::new();
+            if consumer != GOAL && !plan.before(step, consumer) {
+                resolutions.push((consumer, step));
+            }
+            if producer != START && !plan.before(producer, step) {
+                resolutions.push((step, producer));
+            }
+
+            return resolutions.into_iter().find_map(|ordering| {
+                let mut plan = plan.clone();
+                plan.orderings.push(ordering);
+                self.refine(plan)
+            });
+        }
+
+        let Some((consumer, fact)) = plan.open.pop() else {
+            return Some(plan);
+        };
+
+        let mut existing: Vec<usize> = plan
+            .nodes()
+            .filter(|node| {
+                *node != consumer
+                    && self.provides(&plan, *node, &fact)
+                    && !plan.before(consumer, *node)
+            })
+            .collect();
+        existing.shuffle(&mut rand::rng());
+
+        let mut added: Vec<usize> = if plan.steps.len() < self.max_steps {
+            (0..self.operators.len())
+                .filter(|op| self.operators[*op].effects.contains(&fact))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        added.shuffle(&mut rand::rng());
+
+        // Reusing steps keeps plans short, so try that first.
+        for producer in existing {
+            let mut plan = plan.clone();
+            plan.links.push((producer, consumer, fact.clone()));
+            plan.orderings.push((producer, consumer));
+
+            if let Some(plan) = self.refine(plan) {
+                return Some(plan);
+            }
+        }
+
+        for op in added {
+            let mut plan = plan.clone();
+            let producer = plan.steps.len() + 2;
+            plan.steps.push(op);
+            plan.links.push((producer, consumer, fact.clone()));
+            plan.orderings.push((producer, consumer));
+            plan.open.extend(
+                self.operators[op]
+                    .preconditions
+                    .iter()
+                    .map(|precondition| (producer, precondition.clone())),
+            );
+
+            if let Some(plan) = self.refine(plan) {
+                return Some(plan);
+            }
+        }
+
+        None
+    }
+
+    fn into_plan(self, plan: PartialPlan) -> PartialOrderPlan {
+        let node = |node: usize| match node {
+            START => PlanNode::Start,
+            GOAL => PlanNode::Goal,
+            step => PlanNode::Step(step - 2),
+        };
+
+        PartialOrderPlan {
+            steps: plan
+                .steps
+                .iter()
+                .map(|op| self.operators[*op].step.clone())
+                .collect(),
+            orderings: plan
+                .orderings
+                .iter()
+                .filter(|(before, after)| *before > GOAL && *after > GOAL)
+                .map(|(before, after)| (before - 2, after - 2))
+                .collect(),
+            causal_links: plan
+                .links
+                .into_iter()
+                .map(|(producer, consumer, fact)| CausalLink {
+                    producer: node(producer),
+                    consumer: node(consumer),
+                    fact,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn negated(fact: &Fact) -> Fact {
+    match fact {
+        Fact::Predicate(signature, arguments) => Fact::Absent(signature.clone(), arguments.clone()),
+        Fact::Absent(signature, arguments) => Fact::Predicate(signature.clone(), arguments.clone()),
+        fact => fact.clone(),
+    }
+}
+
+impl PredicateDomain {
+    /// Generates a story from a partial-order plan, ordering its steps at random. The story is
+    /// attached to the plan, reordered to match the story, so its causal links can be shown.
+    ///
+    /// Only actions whose precondition and effect are conjunctions of literals take part, and
+    /// the goal has to be one as well. The minimum length, constraints and intentions are checked
+    /// once the steps are ordered. Planning is tried up to `PLANNING_ATTEMPTS` times on `threads`
+    /// threads, passing each new story to `on_story` until it returns false.
+    pub fn generate_partial_order_stories(
+        &self,
+        problem: &PredicateProblem,
+        threads: usize,
+        progress: &Progress,
+        on_story: &mut impl FnMut(PredicateStory) -> bool,
+    ) {
+        stream_attempts(PLANNING_ATTEMPTS, threads, progress, on_story, || {
+            self.plan_story(problem)
+        });
+    }
+
+    /// Plans the problem once and puts the plan in a random order, if that order makes a valid
+    /// story.
+    pub fn plan_story(&self, problem: &PredicateProblem) -> Option<PredicateStory> {
+        let plan = self.partial_order_plan(problem)?;
+        let order = plan.linearize();
+        let plan = plan.reordered(&order);
+
+        let mut story = self.validate_story(problem, plan.steps.clone()).ok()?;
+        story.plan = Some(plan);
+        Some(story)
+    }
+
+    /// Finds a plan reaching the goal of the problem with partial-order causal-link planning.
+    pub fn partial_order_plan(&self, problem: &PredicateProblem) -> Option<PartialOrderPlan> {
+        let initial = self.initial_story_state(problem);
+
+        let mut goal = Vec::new();
+        if !self.literals(&problem.goal, &initial, &Bindings::new(), &mut goal) {
+            return None;
+        }
+
+        let mut planner = Planner {
+            operators: self.operators(&initial, problem),
+            initial: &initial,
+            max_steps: problem.max_story_length as usize,
+            budget: PLANNING_BUDGET,
+        };
+        let plan = PartialPlan {
+            open: goal.into_iter().map(|fact| (GOAL, fact)).collect(),
+            ..Default::default()
+        };
+
+        let plan = planner.refine(plan)?;
+        Some(planner.into_plan(plan))
+    }
+
+    /// Every grounding of the actions the planner can reason about.
+    fn operators(&self, state: &StoryState, problem: &PredicateProblem) -> Vec<Operator> {
+        let mut operators = Vec::new();
+
+        for (name, action) in &self.actions {
+            if problem.excludes(name) {
+                continue;
+            }
+
+            let candidates: Vec<_> = action
+                .parameters
+                .iter()
+                .map(|param| state.atoms_of_type(self, &param.r#type))
+                .collect();
+
+            for arguments in groundings(&candidates) {
+                let bindings = action.bind(&arguments);
+                let mut preconditions = Vec::new();
+                let mut effects = Vec::new();
+
+                if self.literals(&action.precondition, state, &bindings, &mut preconditions)
+                    && self.literals(&action.effect, state, &bindings, &mut effects)
+                {
+                    let arguments = arguments
+                        .iter()
+                        .map(|atom| Symbol(state.get_atom_name(*atom).unwrap_or_default().into()))
+                        .collect();
+
+                    operators.push(Operator {
+                        step: StoryStep {
+                            action: name.clone(),
+                            arguments,
+                        },
+                        preconditions,
+                        effects,
+                    });
+                }
+            }
+        }
+
+        operators
+    }
+
+    /// Collects the ground literals of a conjunction. Returns false if the expression is anything
+    /// else, or refers to derived predicates or unknown symbols.
+    fn literals(
+        &self,
+        expr: &LogicExpr,
+        state: &StoryState,
+        bindings: &Bindings,
+        out: &mut Vec<Fact>,
+    ) -> bool {
+        let mut literal = |expr: &LogicExpr, holds: bool| match expr {
+            LogicExpr::Predicate(signature, symbols) if !self.is_derived(signature) => {
+                let Some(arguments) = state.resolve_all(symbols, bindings) else {
+                    return false;
+                };
+                out.push(match holds {
+                    true => Fact::Predicate(signature.clone(), arguments),
+                    false => Fact::Absent(signature.clone(), arguments),
+                });
+                true
+            }
+            _ => false,
+        };
+
+        match expr {
+            LogicExpr::True => true,
+            LogicExpr::And(lhs, rhs) => {
+                self.literals(lhs, state, bindings, out) && self.literals(rhs, state, bindings, out)
+            }
+            LogicExpr::Not(inner) => literal(inner, false),
+            expr => literal(expr, true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        story::PredicateSignature,
+        test_util::{domain_with, problem_with, sym},
+    };
+
+    #[test]
+    fn links_steps_to_the_facts_they_rely_on() {
+        let domain = domain_with(
+            &[("has-key", 1), ("open", 1), ("sang", 1)],
+            &[
+                ("take-key", "true", "has-key(?c)"),
+                ("open-door", "has-key(?c)", "open(?c)"),
+                ("sing", "true", "sang(?c)"),
+            ],
+        );
+        let mut problem = problem_with(&domain, &["alice"], "open(alice) and sang(alice)");
+        problem.max_story_length = 3;
+
+        let mut story = None;
+        domain.generate_partial_order_stories(&problem, 1, &Progress::default(), &mut |found| {
+            story = Some(found);
+            false
+        });
+        let story = story.unwrap();
+        let plan = story.plan.unwrap();
+        let position = |action: &str| {
+            plan.steps
+                .iter()
+                .position(|step| step.action == sym(action))
+                .unwrap()
+        };
+        let (take, open, sing) = (
+            position("take-key"),
+            position("open-door"),
+            position("sing"),
+        );
+
+        assert_eq!(plan.steps.len(), 3);
+        assert!(take < open);
+        assert!(
+            plan.causal_links.contains(&CausalLink {
+                producer: PlanNode::Step(take),
+                consumer: PlanNode::Step(open),
+                fact: Fact::Predicate(
+                    PredicateSignature::new(sym("has-key"), 1),
+                    story.states[0]
+                        .resolve_all(&[sym("alice")], &Bindings::new())
+                        .unwrap(),
+                ),
+            })
+        );
+        // Singing has nothing to do with the door, so it may happen at any point.
+        assert_eq!(plan.orderings, vec![(take, open)]);
+        assert!(
+            plan.causal_links.iter().any(
+                |link| link.producer == PlanNode::Step(sing) && link.consumer == PlanNode::Goal
+            )
+        );
+    }
+}
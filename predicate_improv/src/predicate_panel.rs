@@ -1,19 +1,65 @@
 use std::mem::take;
 
 use eframe::egui::{self, Color32, Frame, Margin, RichText, Vec2};
+use serde::{Deserialize, Serialize};
 
-use crate::app::{PredicateImprovApp, typed_object_input};
+use crate::{
+    action_panel::{LogicExprEditor, logic_expr},
+    app::{PredicateImprovApp, RenameDraft, name_taken, typed_object_input},
+    history::{Change, Edit, History},
+    rename::RenameTarget,
+    story::{Axiom, LogicExpr, PredicateSignature, Symbol, TypeName, TypedSymbol},
+};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct PredicateDraft {
     pub function: String,
     pub variables: Vec<(String, String)>,
+    /// Whether the draft declares a numeric fluent rather than a predicate.
+    #[serde(default)]
+    pub numeric: bool,
+    /// The definition of a derived predicate, or `None` for predicates set by actions.
+    #[serde(default)]
+    pub derived: Option<LogicExpr>,
+    #[serde(skip)]
+    pub body_editor: LogicExprEditor,
+}
+
+impl PredicateDraft {
+    pub fn into_predicate(self) -> (PredicateSignature, Vec<TypedSymbol>) {
+        let signature = PredicateSignature::new(Symbol(self.function), self.variables.len() as u32);
+        let variables = self
+            .variables
+            .into_iter()
+            .map(|(var, ty)| TypedSymbol {
+                name: Symbol(var),
+                r#type: TypeName(ty),
+            })
+            .collect();
+
+        (signature, variables)
+    }
+
+    pub fn into_axiom(mut self) -> (PredicateSignature, Axiom) {
+        let body = self.derived.take().unwrap_or_default();
+        let (signature, variables) = self.into_predicate();
+
+        (signature, Axiom { variables, body })
+    }
+}
+
+/// The kind of item shown by `declaration_item`.
+enum Declaration<'a> {
+    Predicate,
+    Fluent,
+    /// A derived predicate with its definition.
+    Derived(&'a mut LogicExpr),
 }
 
 #[derive(Debug, Default)]
 pub struct PredicatePanel {
     pub predicate_draft: PredicateDraft,
-    backup_predicate_draft: Option<PredicateDraft>,
+    pub backup_predicate_draft: Option<PredicateDraft>,
     pub add_predicate: bool,
     show_predicate_modal: bool,
 }
@@ -26,9 +72,31 @@ impl PredicatePanel {
 
         ui.add_space(16.);
 
-        if ui.button("New predicate").clicked() {
-            app.predicate_panel.show_predicate_modal = true;
-        }
+        ui.horizontal(|ui| {
+            let draft = &mut app.predicate_panel.predicate_draft;
+
+            if ui.button("New predicate").clicked() {
+                app.predicate_panel.show_predicate_modal = true;
+                draft.numeric = false;
+                draft.derived = None;
+            }
+
+            if ui.button("New fluent").clicked() {
+                app.predicate_panel.show_predicate_modal = true;
+                draft.numeric = true;
+                draft.derived = None;
+            }
+
+            if ui
+                .button("New derived predicate")
+                .on_hover_text("A predicate that holds whenever its definition does")
+                .clicked()
+            {
+                app.predicate_panel.show_predicate_modal = true;
+                draft.numeric = false;
+                draft.derived = Some(draft.derived.take().unwrap_or_default());
+            }
+        });
 
         Frame::canvas(ui.style())
             .inner_margin(Margin::same(8))
@@ -39,68 +107,39 @@ impl PredicatePanel {
                         ui.spacing_mut().item_spacing.y = 6.;
 
                         app.domain.predicates.retain(|predicate, variables| {
-                            Frame::new()
-                                .fill(Color32::from_rgb(36, 36, 36))
-                                .corner_radius(4.0)
-                                .inner_margin(Margin::same(4))
-                                .show(ui, |ui| {
-                                    ui.horizontal(|ui| {
-                                        ui.label(RichText::new(&*predicate.function).italics());
-
-                                        ui.label("(");
-                                        for var in &mut *variables {
-                                            Frame::new()
-                                                .corner_radius(2.0)
-                                                .stroke(ui.style().visuals.window_stroke())
-                                                .show(ui, |ui| {
-                                                    ui.label(format!(
-                                                        " {}: {} ",
-                                                        &var.name.0, &var.r#type.0
-                                                    ));
-                                                });
-                                        }
-                                        ui.label(")");
-
-                                        ui.allocate_space(
-                                            [(ui.available_width() - 40.).max(0.), 0.].into(),
-                                        );
-
-                                        ui.menu_button("…", |ui| {
-                                            let mut retain = if ui.button("Edit").clicked() {
-                                                app.predicate_panel.show_predicate_modal = true;
-
-                                                app.predicate_panel.predicate_draft.function =
-                                                    predicate.function.0.clone();
-                                                app.predicate_panel.predicate_draft.variables =
-                                                    variables
-                                                        .iter_mut()
-                                                        .map(|v| {
-                                                            (
-                                                                take(&mut v.name.0),
-                                                                take(&mut v.r#type.0),
-                                                            )
-                                                        })
-                                                        .collect();
-
-                                                app.predicate_panel.backup_predicate_draft = Some(
-                                                    app.predicate_panel.predicate_draft.clone(),
-                                                );
-
-                                                false
-                                            } else {
-                                                true
-                                            };
-
-                                            retain = retain && !ui.button("Delete").clicked();
-
-                                            retain
-                                        })
-                                        .inner
-                                        .unwrap_or(true)
-                                    })
-                                    .inner
-                                })
-                                .inner
+                            declaration_item(
+                                ui,
+                                &mut app.predicate_panel,
+                                &mut app.rename_draft,
+                                &mut app.history,
+                                predicate,
+                                variables,
+                                Declaration::Predicate,
+                            )
+                        });
+
+                        app.domain.fluents.retain(|fluent, variables| {
+                            declaration_item(
+                                ui,
+                                &mut app.predicate_panel,
+                                &mut app.rename_draft,
+                                &mut app.history,
+                                fluent,
+                                variables,
+                                Declaration::Fluent,
+                            )
+                        });
+
+                        app.domain.axioms.retain(|predicate, axiom| {
+                            declaration_item(
+                                ui,
+                                &mut app.predicate_panel,
+                                &mut app.rename_draft,
+                                &mut app.history,
+                                predicate,
+                                &mut axiom.variables,
+                                Declaration::Derived(&mut axiom.body),
+                            )
                         });
                     });
             });
@@ -112,9 +151,117 @@ impl PredicatePanel {
     }
 }
 
+/// Shows a predicate, numeric fluent or derived predicate. Returns whether to keep it.
+fn declaration_item(
+    ui: &mut egui::Ui,
+    panel: &mut PredicatePanel,
+    rename_draft: &mut Option<RenameDraft>,
+    history: &mut History,
+    signature: &PredicateSignature,
+    variables: &mut Vec<TypedSymbol>,
+    mut kind: Declaration,
+) -> bool {
+    let numeric = matches!(kind, Declaration::Fluent);
+
+    Frame::new()
+        .fill(Color32::from_rgb(36, 36, 36))
+        .corner_radius(4.0)
+        .inner_margin(Margin::same(4))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&*signature.function).italics());
+
+                ui.label("(");
+                for var in &mut *variables {
+                    Frame::new()
+                        .corner_radius(2.0)
+                        .stroke(ui.style().visuals.window_stroke())
+                        .show(ui, |ui| {
+                            ui.label(format!(" {}: {} ", &var.name.0, &var.r#type.0));
+                        });
+                }
+                ui.label(")");
+
+                match &kind {
+                    Declaration::Predicate => {}
+                    Declaration::Fluent => {
+                        ui.label(RichText::new("number").weak());
+                    }
+                    Declaration::Derived(body) => {
+                        ui.label(RichText::new(format!(":= {body}")).weak());
+                    }
+                }
+
+                ui.allocate_space([(ui.available_width() - 40.).max(0.), 0.].into());
+
+                ui.menu_button("…", |ui| {
+                    let mut retain = if ui.button("Edit").clicked() {
+                        panel.show_predicate_modal = true;
+
+                        panel.predicate_draft.function = signature.function.0.clone();
+                        panel.predicate_draft.variables = variables
+                            .iter_mut()
+                            .map(|v| (take(&mut v.name.0), take(&mut v.r#type.0)))
+                            .collect();
+                        panel.predicate_draft.numeric = numeric;
+                        panel.predicate_draft.derived = match &mut kind {
+                            Declaration::Derived(body) => Some(take(*body)),
+                            _ => None,
+                        };
+
+                        panel.backup_predicate_draft = Some(panel.predicate_draft.clone());
+
+                        false
+                    } else {
+                        true
+                    };
+
+                    if ui.button("Rename…").clicked() {
+                        let target = if numeric {
+                            RenameTarget::Fluent(signature.clone())
+                        } else {
+                            RenameTarget::Predicate(signature.clone())
+                        };
+                        *rename_draft = Some(RenameDraft::new(target));
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        let variables = variables.clone();
+                        history.record(match &kind {
+                            Declaration::Predicate => {
+                                Edit::Predicate(Change::removed((signature.clone(), variables)))
+                            }
+                            Declaration::Fluent => {
+                                Edit::Fluent(Change::removed((signature.clone(), variables)))
+                            }
+                            Declaration::Derived(body) => {
+                                let axiom = Axiom {
+                                    variables,
+                                    body: (**body).clone(),
+                                };
+                                Edit::Axiom(Box::new(Change::removed((signature.clone(), axiom))))
+                            }
+                        });
+                        retain = false;
+                    }
+
+                    retain
+                })
+                .inner
+                .unwrap_or(true)
+            })
+            .inner
+        })
+        .inner
+}
+
 fn predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
-        ui.label("function:");
+        if app.predicate_panel.predicate_draft.numeric {
+            ui.label("fluent:");
+        } else {
+            ui.label("function:");
+        }
         ui.text_edit_singleline(&mut app.predicate_panel.predicate_draft.function);
     });
 
@@ -141,9 +288,55 @@ fn predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         }
     });
 
+    if let Some(body) = app.predicate_panel.predicate_draft.derived.clone() {
+        let variables = app.predicate_panel.predicate_draft.variables.clone();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("definition:");
+            let body = logic_expr(app, body, &variables, ui);
+            app.predicate_panel.predicate_draft.derived = Some(body);
+        });
+
+        let draft = &mut app.predicate_panel.predicate_draft;
+        if let Some(body) = &mut draft.derived {
+            draft.body_editor.show(ui, &app.domain, body);
+        }
+    }
+
+    let draft = &app.predicate_panel.predicate_draft;
+    let signature =
+        PredicateSignature::new(Symbol(draft.function.clone()), draft.variables.len() as u32);
+    // Derived predicates share their names with the predicates set by actions.
+    let declared = if draft.numeric {
+        app.domain.fluents.contains_key(&signature)
+    } else {
+        app.domain.predicates.contains_key(&signature) || app.domain.axioms.contains_key(&signature)
+    };
+    let taken = name_taken(
+        ui,
+        declared,
+        format!("{}/{}", draft.function, signature.arity),
+    );
+
+    // The predicate being edited is out of the domain until the modal is submitted, so check the
+    // axioms with the draft put back.
+    let mut cycle = None;
+    if app.predicate_panel.predicate_draft.derived.is_some() {
+        let mut domain = app.domain.clone();
+        let (signature, axiom) = app.predicate_panel.predicate_draft.clone().into_axiom();
+        domain.axioms.insert(signature, axiom);
+        cycle = domain.axiom_strata().err();
+    }
+    if let Some(err) = &cycle {
+        ui.label(RichText::new(err.to_string()).color(Color32::LIGHT_RED));
+    }
+
     ui.horizontal(|ui| {
         if ui
-            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .add_enabled(
+                !taken && cycle.is_none(),
+                egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)),
+            )
             .clicked()
         {
             app.predicate_panel.show_predicate_modal = false;
@@ -156,7 +349,7 @@ fn predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         {
             app.predicate_panel.show_predicate_modal = false;
 
-            if let Some(draft) = take(&mut app.predicate_panel.backup_predicate_draft) {
+            if let Some(draft) = app.predicate_panel.backup_predicate_draft.clone() {
                 app.predicate_panel.predicate_draft = draft;
                 app.predicate_panel.add_predicate = true;
             }
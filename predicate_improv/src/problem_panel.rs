@@ -1,30 +1,77 @@
 use std::mem::take;
 
 use eframe::egui::{self, Color32, Frame, Margin, RichText, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    app::{PredicateImprovApp, type_button, untyped_object_input},
-    story::{LogicExpr, Symbol, TypeName, TypedSymbol},
+    action_panel::{LogicExprEditor, logic_expr},
+    app::{
+        PredicateImprovApp, RenameDraft, symbol_candidates, symbol_input, type_button,
+        untyped_object_input,
+    },
+    constraints::Constraint,
+    history::{Change, Edit, History},
+    rename::RenameTarget,
+    story::{LogicExpr, PredicateProblem, PredicateSignature, Symbol, TypeName, TypedSymbol},
+    task_panel::TaskCallsEditor,
+    tension::{TensionArc, TensionTerm},
 };
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct BoundPredicateDraft {
     pub predicate_name: String,
     pub bound_objects: Vec<String>,
 }
 
+impl BoundPredicateDraft {
+    pub fn into_bound_predicate(self) -> (PredicateSignature, Vec<Symbol>) {
+        let signature =
+            PredicateSignature::new(Symbol(self.predicate_name), self.bound_objects.len() as u32);
+        let bindings = self.bound_objects.into_iter().map(Symbol).collect();
+
+        (signature, bindings)
+    }
+}
+
+/// The initial value of a numeric fluent, e.g. `gold(alice) = 10`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FluentValueDraft {
+    pub fluent_name: String,
+    pub bound_objects: Vec<String>,
+    pub value: i64,
+}
+
+impl FluentValueDraft {
+    pub fn into_fluent_value(self) -> (PredicateSignature, Vec<Symbol>, i64) {
+        let signature =
+            PredicateSignature::new(Symbol(self.fluent_name), self.bound_objects.len() as u32);
+        let bindings = self.bound_objects.into_iter().map(Symbol).collect();
+
+        (signature, bindings, self.value)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ProblemPanel {
     pub bound_predicate_draft: BoundPredicateDraft,
-    backup_bound_predicate_draft: Option<BoundPredicateDraft>,
+    pub backup_bound_predicate_draft: Option<BoundPredicateDraft>,
+    pub fluent_value_draft: FluentValueDraft,
+    pub backup_fluent_value_draft: Option<FluentValueDraft>,
     pub object_draft: (String, String),
+    goal_editor: LogicExprEditor,
+    character_goal_editors: Vec<LogicExprEditor>,
+    belief_editors: Vec<LogicExprEditor>,
+    outline_editor: TaskCallsEditor,
     pub add_bound_predicate: bool,
     show_bound_predicate_modal: bool,
+    pub add_fluent_value: bool,
+    show_fluent_value_modal: bool,
 }
 
 impl ProblemPanel {
     pub fn show(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
         ui.spacing_mut().item_spacing = [6., 12.].into();
+        let selected = app.selected_problem;
 
         egui::TopBottomPanel::bottom("initial_state_panel")
             .frame(
@@ -39,17 +86,41 @@ impl ProblemPanel {
 
                 ui.label("Initial State");
 
-                let next_signature = app.domain.predicates.keys().next();
-                if ui
-                    .add_enabled(
-                        next_signature.is_some(),
-                        egui::Button::new("Bind predicate"),
-                    )
-                    .clicked()
-                {
-                    app.problem_panel.show_bound_predicate_modal = true;
+                let unknown = app.domain.unknown_initial_symbols(app.problem());
+                if !unknown.is_empty() {
+                    let names: Vec<_> = unknown.iter().map(|name| name.as_str()).collect();
+                    ui.label(
+                        RichText::new(format!(
+                            "Facts about unknown objects are ignored: {}",
+                            names.join(", ")
+                        ))
+                        .color(Color32::LIGHT_RED),
+                    );
                 }
 
+                ui.horizontal(|ui| {
+                    let next_signature = app.domain.predicates.keys().next();
+                    if ui
+                        .add_enabled(
+                            next_signature.is_some(),
+                            egui::Button::new("Bind predicate"),
+                        )
+                        .clicked()
+                    {
+                        app.problem_panel.show_bound_predicate_modal = true;
+                    }
+
+                    if ui
+                        .add_enabled(
+                            !app.domain.fluents.is_empty(),
+                            egui::Button::new("Set fluent"),
+                        )
+                        .clicked()
+                    {
+                        app.problem_panel.show_fluent_value_modal = true;
+                    }
+                });
+
                 Frame::canvas(ui.style())
                     .inner_margin(Margin::same(8))
                     .show(ui, |ui| {
@@ -58,8 +129,10 @@ impl ProblemPanel {
                             .show(ui, |ui| {
                                 ui.spacing_mut().item_spacing.y = 6.;
 
-                                app.problem.initial_state.bound_predicates.retain(
-                                    |signature, bindings| {
+                                app.problems[selected]
+                                    .initial_state
+                                    .bound_predicates
+                                    .retain_mut(|(signature, bindings)| {
                                         Frame::new()
                                             .fill(Color32::from_rgb(36, 36, 36))
                                             .corner_radius(4.0)
@@ -119,8 +192,18 @@ impl ProblemPanel {
                                                                 true
                                                             };
 
-                                                        retain = retain
-                                                            && !ui.button("Delete").clicked();
+                                                        if ui.button("Delete").clicked() {
+                                                            app.history.record(
+                                                                Edit::BoundPredicate(
+                                                                    selected,
+                                                                    Change::removed((
+                                                                        signature.clone(),
+                                                                        bindings.clone(),
+                                                                    )),
+                                                                ),
+                                                            );
+                                                            retain = false;
+                                                        }
 
                                                         retain
                                                     })
@@ -130,16 +213,61 @@ impl ProblemPanel {
                                                 .inner
                                             })
                                             .inner
-                                    },
-                                );
+                                    });
+
+                                app.problems[selected]
+                                    .initial_state
+                                    .fluent_values
+                                    .retain_mut(|(signature, bindings, value)| {
+                                        fluent_value_item(
+                                            ui,
+                                            &mut app.problem_panel,
+                                            &mut app.history,
+                                            selected,
+                                            signature,
+                                            bindings,
+                                            *value,
+                                        )
+                                    });
                             });
                     });
             });
 
         ui.label(RichText::new("Problem").text_style(egui::TextStyle::Name("Heading2".into())));
 
+        problem_tabs(ui, app);
+
         ui.add_space(16.);
 
+        let selected = app.selected_problem;
+        let goal = app.problem().goal.clone();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Goal:");
+            let expr = logic_expr(app, goal.clone(), &[], ui);
+            app.problem_mut().goal = expr;
+        });
+
+        app.problem_panel
+            .goal_editor
+            .show(ui, &app.domain, &mut app.problems[selected].goal);
+
+        if app.problem().goal != goal {
+            app.history.record(Edit::Goal(
+                selected,
+                Change {
+                    before: Some(goal),
+                    after: Some(app.problem().goal.clone()),
+                },
+            ));
+        }
+
+        character_exprs(ui, app, CHARACTER_GOALS);
+        character_exprs(ui, app, BELIEFS);
+        constraints(ui, app);
+        tension(ui, app);
+        outline(ui, app);
+
         ui.label("Objects");
         ui.horizontal(|ui| {
             egui::TextEdit::singleline(&mut app.problem_panel.object_draft.0)
@@ -152,10 +280,14 @@ impl ProblemPanel {
                 && !app.problem_panel.object_draft.0.is_empty()
                 && !app.problem_panel.object_draft.1.is_empty()
             {
-                app.problem.objects.push(TypedSymbol {
+                let object = TypedSymbol {
                     name: Symbol(take(&mut app.problem_panel.object_draft.0)),
                     r#type: TypeName(take(&mut app.problem_panel.object_draft.1)),
-                });
+                };
+
+                app.problems[selected].objects.push(object.clone());
+                app.history
+                    .record(Edit::Object(selected, Change::added(object)));
             }
         });
 
@@ -167,7 +299,7 @@ impl ProblemPanel {
                     .show(ui, |ui| {
                         ui.spacing_mut().item_spacing.y = 6.;
 
-                        app.problem.objects.retain(|obj| {
+                        app.problems[selected].objects.retain(|obj| {
                             Frame::new()
                                 .fill(Color32::from_rgb(36, 36, 36))
                                 .corner_radius(4.0)
@@ -180,8 +312,27 @@ impl ProblemPanel {
                                             [(ui.available_width() - 40.).max(0.), 0.].into(),
                                         );
 
-                                        !ui.button(egui_material_icons::icons::ICON_REMOVE)
-                                            .clicked()
+                                        ui.menu_button("…", |ui| {
+                                            if ui.button("Rename…").clicked() {
+                                                app.rename_draft =
+                                                    Some(RenameDraft::new(RenameTarget::Object(
+                                                        selected,
+                                                        obj.name.clone(),
+                                                    )));
+                                            }
+
+                                            if ui.button("Delete").clicked() {
+                                                app.history.record(Edit::Object(
+                                                    selected,
+                                                    Change::removed(obj.clone()),
+                                                ));
+                                                false
+                                            } else {
+                                                true
+                                            }
+                                        })
+                                        .inner
+                                        .unwrap_or(true)
                                     })
                                     .inner
                                 })
@@ -194,9 +345,106 @@ impl ProblemPanel {
             egui::Modal::new("bound_predicate_modal".into())
                 .show(ui.ctx(), |ui| bound_predicate_modal(app, ui));
         }
+
+        if app.problem_panel.show_fluent_value_modal {
+            egui::Modal::new("fluent_value_modal".into())
+                .show(ui.ctx(), |ui| fluent_value_modal(app, ui));
+        }
     }
 }
 
+/// Tabs switching between the problems of the domain, followed by a menu to add, duplicate or
+/// delete problems and a field to rename the selected one.
+fn problem_tabs(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    ui.horizontal_wrapped(|ui| {
+        for (i, problem) in app.problems.iter().enumerate() {
+            let name = if problem.name.is_empty() {
+                "<unnamed>"
+            } else {
+                &problem.name
+            };
+
+            if ui
+                .selectable_label(i == app.selected_problem, name)
+                .clicked()
+            {
+                app.selected_problem = i;
+            }
+        }
+
+        ui.menu_button("…", |ui| {
+            if ui.button("New problem").clicked() {
+                let name = format!("Problem {}", app.problems.len() + 1);
+                let name = unique_problem_name(&app.problems, &name);
+                insert_problem(
+                    app,
+                    PredicateProblem {
+                        name,
+                        ..Default::default()
+                    },
+                );
+            }
+
+            if ui.button("Duplicate").clicked() {
+                let mut problem = app.problem().clone();
+                problem.name =
+                    unique_problem_name(&app.problems, &format!("{} copy", problem.name));
+                insert_problem(app, problem);
+            }
+
+            if ui
+                .add_enabled(app.problems.len() > 1, egui::Button::new("Delete"))
+                .clicked()
+            {
+                let i = app.selected_problem;
+                let problem = app.problems.remove(i);
+                app.selected_problem = i.min(app.problems.len() - 1);
+                app.history
+                    .record(Edit::Problem(Box::new(Change::removed((i, problem)))));
+            }
+        });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Name:");
+
+        let i = app.selected_problem;
+        let name = app.problem().name.clone();
+        if ui
+            .text_edit_singleline(&mut app.problem_mut().name)
+            .changed()
+        {
+            let before = PredicateProblem {
+                name,
+                ..app.problem().clone()
+            };
+            app.history.record(Edit::Problem(Box::new(Change {
+                before: Some((i, before)),
+                after: Some((i, app.problem().clone())),
+            })));
+        }
+    });
+}
+
+/// Adds the problem after the selected one and selects it.
+fn insert_problem(app: &mut PredicateImprovApp, problem: PredicateProblem) {
+    let i = app.selected_problem + 1;
+    app.problems.insert(i, problem.clone());
+    app.selected_problem = i;
+    app.history
+        .record(Edit::Problem(Box::new(Change::added((i, problem)))));
+}
+
+/// The name if no problem has it yet, otherwise the name followed by the first free number.
+fn unique_problem_name(problems: &[PredicateProblem], name: &str) -> String {
+    let is_free = |name: &String| problems.iter().all(|problem| problem.name != *name);
+
+    std::iter::once(name.to_string())
+        .chain((2..).map(|n| format!("{name} {n}")))
+        .find(is_free)
+        .unwrap()
+}
+
 fn bound_predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
         ui.label(format!(
@@ -220,14 +468,29 @@ fn bound_predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
     });
 
     {
+        let draft = &mut app.problem_panel.bound_predicate_draft;
+        let signature = PredicateSignature::new(
+            Symbol(draft.predicate_name.clone()),
+            draft.bound_objects.len() as u32,
+        );
+        let variable_types = app.domain.predicates.get(&signature);
+
         let mut i = 0;
-        app.problem_panel
-            .bound_predicate_draft
-            .bound_objects
-            .retain_mut(|obj| {
-                i += 1;
-                untyped_object_input(ui, obj, format!("var {i}"))
-            });
+        draft.bound_objects.retain_mut(|obj| {
+            let expected = variable_types
+                .and_then(|types| types.get(i))
+                .map(|var| var.r#type.clone())
+                .unwrap_or_default();
+            let candidates = symbol_candidates(
+                &app.domain,
+                &app.problems[app.selected_problem],
+                &[],
+                &expected,
+            );
+
+            i += 1;
+            untyped_object_input(ui, obj, &candidates, format!("var {i}"))
+        });
     }
 
     ui.horizontal(|ui| {
@@ -245,10 +508,450 @@ fn bound_predicate_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         {
             app.problem_panel.show_bound_predicate_modal = false;
 
-            if let Some(draft) = take(&mut app.problem_panel.backup_bound_predicate_draft) {
+            if let Some(draft) = app.problem_panel.backup_bound_predicate_draft.clone() {
                 app.problem_panel.bound_predicate_draft = draft;
                 app.problem_panel.add_bound_predicate = true;
             }
         }
     });
 }
+
+/// Shows the initial value of a fluent. Returns whether to keep it.
+fn fluent_value_item(
+    ui: &mut egui::Ui,
+    panel: &mut ProblemPanel,
+    history: &mut History,
+    problem: usize,
+    signature: &PredicateSignature,
+    bindings: &mut [Symbol],
+    value: i64,
+) -> bool {
+    Frame::new()
+        .fill(Color32::from_rgb(36, 36, 36))
+        .corner_radius(4.0)
+        .inner_margin(Margin::same(4))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(&signature.function.0);
+
+                ui.label("(");
+                for var in &*bindings {
+                    Frame::new()
+                        .corner_radius(2.0)
+                        .stroke(ui.style().visuals.window_stroke())
+                        .show(ui, |ui| {
+                            ui.label(format!(" {} ", &var.0));
+                        });
+                }
+                ui.label(format!(") = {value}"));
+
+                ui.allocate_space([(ui.available_width() - 40.).max(0.), 0.].into());
+
+                ui.menu_button("…", |ui| {
+                    let mut retain = if ui.button("Edit").clicked() {
+                        panel.show_fluent_value_modal = true;
+
+                        panel.fluent_value_draft = FluentValueDraft {
+                            fluent_name: signature.function.0.clone(),
+                            bound_objects: bindings.iter_mut().map(|v| take(&mut v.0)).collect(),
+                            value,
+                        };
+                        panel.backup_fluent_value_draft = Some(panel.fluent_value_draft.clone());
+
+                        false
+                    } else {
+                        true
+                    };
+
+                    if ui.button("Delete").clicked() {
+                        history.record(Edit::FluentValue(
+                            problem,
+                            Change::removed((signature.clone(), bindings.to_vec(), value)),
+                        ));
+                        retain = false;
+                    }
+
+                    retain
+                })
+                .inner
+                .unwrap_or(true)
+            })
+            .inner
+        })
+        .inner
+}
+
+fn fluent_value_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "fluent: {}",
+            app.problem_panel.fluent_value_draft.fluent_name
+        ));
+
+        ui.menu_button("Change", |ui| {
+            for fs in app.domain.fluents.keys() {
+                if ui
+                    .button(format!("{}/{}", fs.function.0, fs.arity))
+                    .clicked()
+                {
+                    app.problem_panel.fluent_value_draft.fluent_name = fs.function.0.clone();
+
+                    app.problem_panel.fluent_value_draft.bound_objects =
+                        vec![String::new(); fs.arity as usize];
+                }
+            }
+        });
+    });
+
+    {
+        let draft = &mut app.problem_panel.fluent_value_draft;
+        let signature = PredicateSignature::new(
+            Symbol(draft.fluent_name.clone()),
+            draft.bound_objects.len() as u32,
+        );
+        let variable_types = app.domain.fluents.get(&signature);
+
+        let mut i = 0;
+        draft.bound_objects.retain_mut(|obj| {
+            let expected = variable_types
+                .and_then(|types| types.get(i))
+                .map(|var| var.r#type.clone())
+                .unwrap_or_default();
+            let candidates = symbol_candidates(
+                &app.domain,
+                &app.problems[app.selected_problem],
+                &[],
+                &expected,
+            );
+
+            i += 1;
+            untyped_object_input(ui, obj, &candidates, format!("var {i}"))
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("value:");
+            ui.add(egui::DragValue::new(&mut draft.value));
+        });
+    }
+
+    ui.horizontal(|ui| {
+        if ui
+            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .clicked()
+        {
+            app.problem_panel.show_fluent_value_modal = false;
+            app.problem_panel.add_fluent_value = true;
+        }
+
+        if ui
+            .add(egui::Button::new("Cancel").min_size(Vec2::new(ui.available_width(), 0.)))
+            .clicked()
+        {
+            app.problem_panel.show_fluent_value_modal = false;
+
+            if let Some(draft) = app.problem_panel.backup_fluent_value_draft.clone() {
+                app.problem_panel.fluent_value_draft = draft;
+                app.problem_panel.add_fluent_value = true;
+            }
+        }
+    });
+}
+
+type CharacterExprList = Vec<(Symbol, LogicExpr)>;
+
+/// A list of expressions about characters, such as their goals, each edited with a tree and a
+/// text editor.
+struct CharacterExprs {
+    label: &'static str,
+    hover_text: &'static str,
+    /// Joins the character and the expression, as in "alice wants ...".
+    verb: &'static str,
+    exprs: fn(&mut PredicateProblem) -> &mut CharacterExprList,
+    editors: fn(&mut ProblemPanel) -> &mut Vec<LogicExprEditor>,
+    edit: fn(usize, Change<CharacterExprList>) -> Edit,
+}
+
+const CHARACTER_GOALS: CharacterExprs = CharacterExprs {
+    label: "Character goals",
+    hover_text: "Characters only act when it brings them closer to one of their goals",
+    verb: "wants",
+    exprs: |problem| &mut problem.character_goals,
+    editors: |panel| &mut panel.character_goal_editors,
+    edit: Edit::CharacterGoals,
+};
+
+const BELIEFS: CharacterExprs = CharacterExprs {
+    label: "Initial beliefs",
+    hover_text: "What characters believe when the story starts, right or wrong. Characters know \
+        nothing else until they observe it or are told.",
+    verb: "believes",
+    exprs: |problem| &mut problem.initial_state.beliefs,
+    editors: |panel| &mut panel.belief_editors,
+    edit: Edit::Beliefs,
+};
+
+fn character_exprs(ui: &mut egui::Ui, app: &mut PredicateImprovApp, list: CharacterExprs) {
+    let selected = app.selected_problem;
+    let before = (list.exprs)(app.problem_mut()).clone();
+
+    ui.horizontal(|ui| {
+        ui.label(list.label).on_hover_text(list.hover_text);
+
+        if ui.button(egui_material_icons::icons::ICON_ADD).clicked() {
+            (list.exprs)(app.problem_mut()).push((Symbol::default(), LogicExpr::True));
+        }
+    });
+
+    let candidates = symbol_candidates(&app.domain, app.problem(), &[], &TypeName::default());
+    (list.editors)(&mut app.problem_panel).resize_with(before.len(), Default::default);
+
+    let mut i = 0;
+    while i < (list.exprs)(&mut app.problems[selected]).len() {
+        let (mut character, expr) = (list.exprs)(&mut app.problems[selected])[i].clone();
+
+        let retain = ui
+            .horizontal_wrapped(|ui| {
+                symbol_input(ui, &mut character.0, &candidates, 100.);
+                ui.label(list.verb);
+                let expr = logic_expr(app, expr, &[], ui);
+                (list.exprs)(&mut app.problems[selected])[i] = (character, expr);
+
+                !ui.button(egui_material_icons::icons::ICON_REMOVE).clicked()
+            })
+            .inner;
+
+        if !retain {
+            (list.exprs)(&mut app.problems[selected]).remove(i);
+            (list.editors)(&mut app.problem_panel).remove(i);
+            continue;
+        }
+
+        (list.editors)(&mut app.problem_panel)[i].show(
+            ui,
+            &app.domain,
+            &mut (list.exprs)(&mut app.problems[selected])[i].1,
+        );
+        i += 1;
+    }
+
+    let after = (list.exprs)(app.problem_mut()).clone();
+    if after != before {
+        app.history.record((list.edit)(
+            selected,
+            Change {
+                before: Some(before),
+                after: Some(after),
+            },
+        ));
+    }
+}
+
+/// The conditions on the whole course of the story, each shown as a menu to delete it followed
+/// by editors for its conditions or action.
+fn constraints(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    let selected = app.selected_problem;
+    let before = app.problem().constraints.clone();
+
+    ui.horizontal(|ui| {
+        ui.label("Constraints")
+            .on_hover_text("Conditions on the whole story, e.g. something must happen early on");
+
+        ui.menu_button(egui_material_icons::icons::ICON_ADD, |ui| {
+            for constraint in Constraint::templates() {
+                if ui.button(constraint.keyword()).clicked() {
+                    app.problem_mut().constraints.push(constraint);
+                }
+            }
+        });
+    });
+
+    let mut i = 0;
+    while i < app.problems[selected].constraints.len() {
+        let constraint = app.problems[selected].constraints[i].clone();
+
+        let retain = ui
+            .horizontal_wrapped(|ui| {
+                let retain = ui
+                    .menu_button(constraint.keyword(), |ui| !ui.button("Delete").clicked())
+                    .inner
+                    .unwrap_or(true);
+
+                ui.label("(");
+                let constraint = match constraint {
+                    Constraint::Always(expr) => Constraint::Always(logic_expr(app, expr, &[], ui)),
+                    Constraint::Sometime(expr) => {
+                        Constraint::Sometime(logic_expr(app, expr, &[], ui))
+                    }
+                    Constraint::AtMostOnce(expr) => {
+                        Constraint::AtMostOnce(logic_expr(app, expr, &[], ui))
+                    }
+                    Constraint::SometimeBefore(later, earlier) => {
+                        let later = logic_expr(app, later, &[], ui);
+                        ui.label(",");
+                        Constraint::SometimeBefore(later, logic_expr(app, earlier, &[], ui))
+                    }
+                    Constraint::Within(mut percent, expr) => {
+                        ui.add(
+                            egui::DragValue::new(&mut percent)
+                                .range(0..=100)
+                                .suffix("%"),
+                        );
+                        ui.label(",");
+                        Constraint::Within(percent, logic_expr(app, expr, &[], ui))
+                    }
+                    Constraint::Includes(action) => {
+                        Constraint::Includes(action_button(app, ui, action))
+                    }
+                    Constraint::Excludes(action) => {
+                        Constraint::Excludes(action_button(app, ui, action))
+                    }
+                };
+                ui.label(")");
+
+                app.problems[selected].constraints[i] = constraint;
+                retain
+            })
+            .inner;
+
+        if retain {
+            i += 1;
+        } else {
+            app.problems[selected].constraints.remove(i);
+        }
+    }
+
+    if app.problem().constraints != before {
+        app.history.record(Edit::Constraints(
+            selected,
+            Change {
+                before: Some(before),
+                after: Some(app.problem().constraints.clone()),
+            },
+        ));
+    }
+}
+
+/// The weighted conditions that make states tense, and the arc stories are ranked against.
+fn tension(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    let selected = app.selected_problem;
+    let before = app.problem().tension.clone();
+
+    ui.horizontal(|ui| {
+        ui.label("Tension").on_hover_text(
+            "Each condition adds its weight to the tension of the states it holds in, e.g. 3 \
+            while the dragon is in the village",
+        );
+
+        if ui.button(egui_material_icons::icons::ICON_ADD).clicked() {
+            app.problem_mut().tension.terms.push(TensionTerm {
+                weight: 1,
+                condition: LogicExpr::True,
+            });
+        }
+    });
+
+    let mut i = 0;
+    while i < app.problems[selected].tension.terms.len() {
+        let TensionTerm {
+            mut weight,
+            condition,
+        } = app.problems[selected].tension.terms[i].clone();
+
+        let retain = ui
+            .horizontal_wrapped(|ui| {
+                ui.add(egui::DragValue::new(&mut weight));
+                ui.label("while");
+                let condition = logic_expr(app, condition, &[], ui);
+                app.problems[selected].tension.terms[i] = TensionTerm { weight, condition };
+
+                !ui.button(egui_material_icons::icons::ICON_REMOVE).clicked()
+            })
+            .inner;
+
+        if retain {
+            i += 1;
+        } else {
+            app.problems[selected].tension.terms.remove(i);
+        }
+    }
+
+    if !app.problem().tension.is_empty() {
+        let tension = &mut app.problems[selected].tension;
+        ui.horizontal(|ui| {
+            ui.label("Target arc");
+            egui::ComboBox::from_id_salt("tension_arc")
+                .selected_text(tension.arc.to_string())
+                .show_ui(ui, |ui| {
+                    for arc in TensionArc::ALL {
+                        ui.selectable_value(&mut tension.arc, arc, arc.to_string());
+                    }
+                });
+
+            if tension.arc == TensionArc::Climax {
+                ui.add(
+                    egui::DragValue::new(&mut tension.climax)
+                        .range(0..=100)
+                        .suffix("%"),
+                );
+                ui.label("of the way through");
+            }
+        });
+    }
+
+    if app.problem().tension != before {
+        app.history.record(Edit::Tension(
+            selected,
+            Change {
+                before: Some(before),
+                after: Some(app.problem().tension.clone()),
+            },
+        ));
+    }
+}
+
+/// A menu to pick one of the actions of the domain.
+/// The tasks the story is made of, which generation then decomposes into actions.
+fn outline(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+    let selected = app.selected_problem;
+    let before = app.problem().outline.clone();
+
+    ui.label("Outline").on_hover_text(
+        "Tasks and actions the story performs in order, e.g. rescue(knight, princess)",
+    );
+    app.problem_panel.outline_editor.show(
+        ui,
+        &mut app.problems[selected].outline,
+        "rescue(knight, princess)",
+    );
+
+    if app.problem().outline != before {
+        app.history.record(Edit::Outline(
+            selected,
+            Change {
+                before: Some(before),
+                after: Some(app.problem().outline.clone()),
+            },
+        ));
+    }
+}
+
+fn action_button(app: &PredicateImprovApp, ui: &mut egui::Ui, mut action: Symbol) -> Symbol {
+    let label = if action.is_empty() {
+        "<action>"
+    } else {
+        action.as_str()
+    };
+
+    ui.menu_button(label.to_owned(), |ui| {
+        let mut names: Vec<&Symbol> = app.domain.actions.keys().collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for name in names {
+            if ui.button(name.as_str()).clicked() {
+                action = name.clone();
+            }
+        }
+    });
+
+    action
+}
@@ -0,0 +1,542 @@
+use std::fmt::Display;
+
+use crate::{
+    constraints::Constraint,
+    story::{
+        Action, CompoundTask, LogicExpr, PredicateDomain, PredicateProblem, PredicateSignature,
+        Symbol, TypeName,
+    },
+};
+
+/// Something with a name that can be referenced from elsewhere in the domain or problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameTarget {
+    Predicate(PredicateSignature),
+    Fluent(PredicateSignature),
+    Type(TypeName),
+    Constant(Symbol),
+    /// An object of the problem at the given index.
+    Object(usize, Symbol),
+}
+
+impl RenameTarget {
+    pub fn name(&self) -> &str {
+        match self {
+            RenameTarget::Predicate(signature) | RenameTarget::Fluent(signature) => {
+                &signature.function.0
+            }
+            RenameTarget::Type(name) => &name.0,
+            RenameTarget::Constant(name) | RenameTarget::Object(_, name) => &name.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    EmptyName,
+    NotFound,
+    AlreadyExists(String),
+    /// Constants and objects cannot take the name of a parameter, which would capture every
+    /// reference to them in that action, task or axiom.
+    ParameterName(String),
+}
+
+impl Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameError::EmptyName => write!(f, "the name cannot be empty"),
+            RenameError::NotFound => write!(f, "there is nothing to rename"),
+            RenameError::AlreadyExists(name) => write!(f, "`{name}` already exists"),
+            RenameError::ParameterName(name) => write!(f, "`{name}` is the name of a parameter"),
+        }
+    }
+}
+
+/// Renames the target and updates every reference to it across the domain and its problems.
+/// Objects only belong to one problem, so only that problem is updated when renaming an object.
+/// Nothing is changed if the rename fails.
+pub fn rename(
+    domain: &mut PredicateDomain,
+    problems: &mut [PredicateProblem],
+    target: &RenameTarget,
+    new_name: &str,
+) -> Result<(), RenameError> {
+    if new_name.is_empty() {
+        return Err(RenameError::EmptyName);
+    }
+    if target.name() == new_name {
+        return Ok(());
+    }
+
+    match target {
+        RenameTarget::Predicate(signature) => {
+            let renamed = PredicateSignature::new(Symbol(new_name.into()), signature.arity);
+            if domain.predicates.contains_key(&renamed) || domain.axioms.contains_key(&renamed) {
+                return Err(RenameError::AlreadyExists(format!(
+                    "{new_name}/{}",
+                    signature.arity
+                )));
+            }
+            if let Some(variables) = domain.predicates.remove(signature) {
+                domain.predicates.insert(renamed.clone(), variables);
+            } else {
+                let axiom = domain
+                    .axioms
+                    .remove(signature)
+                    .ok_or(RenameError::NotFound)?;
+                domain.axioms.insert(renamed.clone(), axiom);
+            }
+
+            let rename_signature = |expr: &mut LogicExpr| {
+                expr.for_each_predicate_mut(&mut |predicate, _| {
+                    if predicate == signature {
+                        *predicate = renamed.clone();
+                    }
+                });
+            };
+
+            for action in domain.actions.values_mut() {
+                rename_signature(&mut action.precondition);
+                rename_signature(&mut action.effect);
+            }
+            for axiom in domain.axioms.values_mut() {
+                rename_signature(&mut axiom.body);
+            }
+            for method in domain.tasks.values_mut().flat_map(|task| &mut task.methods) {
+                rename_signature(&mut method.precondition);
+            }
+
+            for problem in problems {
+                rename_signature(&mut problem.goal);
+                for expr in problem
+                    .constraints
+                    .iter_mut()
+                    .flat_map(Constraint::exprs_mut)
+                {
+                    rename_signature(expr);
+                }
+                for (_, expr) in problem
+                    .character_goals
+                    .iter_mut()
+                    .chain(&mut problem.initial_state.beliefs)
+                {
+                    rename_signature(expr);
+                }
+                for term in &mut problem.tension.terms {
+                    rename_signature(&mut term.condition);
+                }
+
+                for (predicate, _) in &mut problem.initial_state.bound_predicates {
+                    if predicate == signature {
+                        *predicate = renamed.clone();
+                    }
+                }
+            }
+        }
+        RenameTarget::Fluent(signature) => {
+            let renamed = PredicateSignature::new(Symbol(new_name.into()), signature.arity);
+            if domain.fluents.contains_key(&renamed) {
+                return Err(RenameError::AlreadyExists(format!(
+                    "{new_name}/{}",
+                    signature.arity
+                )));
+            }
+            let variables = domain
+                .fluents
+                .remove(signature)
+                .ok_or(RenameError::NotFound)?;
+            domain.fluents.insert(renamed.clone(), variables);
+
+            let rename_signature = |expr: &mut LogicExpr| {
+                expr.for_each_fluent_mut(&mut |fluent, _| {
+                    if fluent == signature {
+                        *fluent = renamed.clone();
+                    }
+                });
+            };
+
+            for action in domain.actions.values_mut() {
+                rename_signature(&mut action.precondition);
+                rename_signature(&mut action.effect);
+            }
+            for axiom in domain.axioms.values_mut() {
+                rename_signature(&mut axiom.body);
+            }
+            for method in domain.tasks.values_mut().flat_map(|task| &mut task.methods) {
+                rename_signature(&mut method.precondition);
+            }
+
+            for problem in problems {
+                rename_signature(&mut problem.goal);
+                for expr in problem
+                    .constraints
+                    .iter_mut()
+                    .flat_map(Constraint::exprs_mut)
+                {
+                    rename_signature(expr);
+                }
+                for term in &mut problem.tension.terms {
+                    rename_signature(&mut term.condition);
+                }
+                for (_, goal) in &mut problem.character_goals {
+                    rename_signature(goal);
+                }
+
+                for (fluent, _, _) in &mut problem.initial_state.fluent_values {
+                    if fluent == signature {
+                        *fluent = renamed.clone();
+                    }
+                }
+            }
+        }
+        RenameTarget::Type(name) => {
+            let renamed = TypeName(new_name.into());
+            if domain.types.contains_key(&renamed) {
+                return Err(RenameError::AlreadyExists(new_name.into()));
+            }
+            let supertypes = domain.types.remove(name).ok_or(RenameError::NotFound)?;
+            domain.types.insert(renamed.clone(), supertypes);
+
+            let rename_type = |ty: &mut TypeName| {
+                if ty == name {
+                    *ty = renamed.clone();
+                }
+            };
+
+            domain
+                .types
+                .values_mut()
+                .flatten()
+                .chain(domain.constants.values_mut())
+                .chain(
+                    domain
+                        .predicates
+                        .values_mut()
+                        .flatten()
+                        .map(|var| &mut var.r#type),
+                )
+                .chain(
+                    domain
+                        .fluents
+                        .values_mut()
+                        .flatten()
+                        .map(|var| &mut var.r#type),
+                )
+                .chain(
+                    domain
+                        .axioms
+                        .values_mut()
+                        .flat_map(|axiom| &mut axiom.variables)
+                        .map(|var| &mut var.r#type),
+                )
+                .chain(
+                    domain
+                        .actions
+                        .values_mut()
+                        .flat_map(|action| &mut action.parameters)
+                        .map(|param| &mut param.r#type),
+                )
+                .chain(
+                    domain
+                        .tasks
+                        .values_mut()
+                        .flat_map(|task| &mut task.parameters)
+                        .map(|param| &mut param.r#type),
+                )
+                .chain(
+                    problems
+                        .iter_mut()
+                        .flat_map(|problem| &mut problem.objects)
+                        .map(|obj| &mut obj.r#type),
+                )
+                .for_each(rename_type);
+        }
+        RenameTarget::Constant(name) => {
+            let renamed = Symbol(new_name.into());
+            if is_parameter(domain, &renamed) {
+                return Err(RenameError::ParameterName(new_name.into()));
+            }
+            if domain.constants.contains_key(&renamed)
+                || problems
+                    .iter()
+                    .any(|problem| problem.objects.iter().any(|obj| obj.name == renamed))
+            {
+                return Err(RenameError::AlreadyExists(new_name.into()));
+            }
+
+            let r#type = domain.constants.remove(name).ok_or(RenameError::NotFound)?;
+            domain.constants.insert(renamed.clone(), r#type);
+
+            for action in domain.actions.values_mut() {
+                rename_action_argument(action, name, &renamed);
+            }
+            for axiom in domain.axioms.values_mut() {
+                if !axiom.variables.iter().any(|var| var.name == *name) {
+                    rename_argument(&mut axiom.body, name, &renamed);
+                }
+            }
+            for task in domain.tasks.values_mut() {
+                rename_task_argument(task, name, &renamed);
+            }
+            for problem in problems {
+                rename_problem_argument(problem, name, &renamed);
+            }
+        }
+        RenameTarget::Object(i, name) => {
+            let renamed = Symbol(new_name.into());
+            let problem = problems.get_mut(*i).ok_or(RenameError::NotFound)?;
+            if is_parameter(domain, &renamed) {
+                return Err(RenameError::ParameterName(new_name.into()));
+            }
+            if domain.constants.contains_key(&renamed)
+                || problem.objects.iter().any(|obj| obj.name == renamed)
+            {
+                return Err(RenameError::AlreadyExists(new_name.into()));
+            }
+
+            let object = problem
+                .objects
+                .iter_mut()
+                .find(|obj| obj.name == *name)
+                .ok_or(RenameError::NotFound)?;
+            object.name = renamed.clone();
+
+            rename_problem_argument(problem, name, &renamed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the name is a parameter of some action or task, or a variable of some axiom.
+fn is_parameter(domain: &PredicateDomain, name: &Symbol) -> bool {
+    domain
+        .actions
+        .values()
+        .flat_map(|action| &action.parameters)
+        .chain(domain.tasks.values().flat_map(|task| &task.parameters))
+        .chain(domain.axioms.values().flat_map(|axiom| &axiom.variables))
+        .any(|param| param.name == *name)
+}
+
+fn rename_argument(expr: &mut LogicExpr, name: &Symbol, renamed: &Symbol) {
+    let mut rename_arguments = |_: &mut PredicateSignature, arguments: &mut Vec<Symbol>| {
+        arguments
+            .iter_mut()
+            .filter(|arg| *arg == name)
+            .for_each(|arg| *arg = renamed.clone());
+    };
+
+    expr.for_each_predicate_mut(&mut rename_arguments);
+    expr.for_each_fluent_mut(&mut rename_arguments);
+    expr.for_each_character_mut(&mut |character| {
+        if character == name {
+            *character = renamed.clone();
+        }
+    });
+}
+
+fn rename_problem_argument(problem: &mut PredicateProblem, name: &Symbol, renamed: &Symbol) {
+    rename_argument(&mut problem.goal, name, renamed);
+    for expr in problem
+        .constraints
+        .iter_mut()
+        .flat_map(Constraint::exprs_mut)
+    {
+        rename_argument(expr, name, renamed);
+    }
+    for term in &mut problem.tension.terms {
+        rename_argument(&mut term.condition, name, renamed);
+    }
+    for (character, expr) in problem
+        .character_goals
+        .iter_mut()
+        .chain(&mut problem.initial_state.beliefs)
+    {
+        if character == name {
+            *character = renamed.clone();
+        }
+        rename_argument(expr, name, renamed);
+    }
+
+    let initial_state = &mut problem.initial_state;
+    initial_state
+        .bound_predicates
+        .iter_mut()
+        .flat_map(|(_, bindings)| bindings)
+        .chain(
+            initial_state
+                .fluent_values
+                .iter_mut()
+                .flat_map(|(_, bindings, _)| bindings),
+        )
+        .chain(
+            problem
+                .outline
+                .iter_mut()
+                .flat_map(|call| &mut call.arguments),
+        )
+        .filter(|binding| *binding == name)
+        .for_each(|binding| *binding = renamed.clone());
+}
+
+/// Renames a constant used in an action, unless a parameter of the action shadows it.
+fn rename_action_argument(action: &mut Action, name: &Symbol, renamed: &Symbol) {
+    if action.parameters.iter().any(|param| param.name == *name) {
+        return;
+    }
+
+    rename_argument(&mut action.precondition, name, renamed);
+    rename_argument(&mut action.effect, name, renamed);
+}
+
+/// Renames a constant used in the methods of a task, unless a parameter of the task shadows it.
+fn rename_task_argument(task: &mut CompoundTask, name: &Symbol, renamed: &Symbol) {
+    if task.parameters.iter().any(|param| param.name == *name) {
+        return;
+    }
+
+    for method in &mut task.methods {
+        rename_argument(&mut method.precondition, name, renamed);
+        method
+            .subtasks
+            .iter_mut()
+            .flat_map(|call| &mut call.arguments)
+            .filter(|argument| *argument == name)
+            .for_each(|argument| *argument = renamed.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        story::Axiom,
+        test_util::{objects, sym},
+    };
+
+    #[test]
+    fn renames_every_reference() {
+        let at = PredicateSignature::new(sym("at"), 2);
+        let mut domain = PredicateDomain::default();
+        domain.predicates.insert(at.clone(), Vec::new());
+        domain.constants.insert(sym("home"), TypeName::default());
+        let gold = PredicateSignature::new(sym("gold"), 1);
+        domain.fluents.insert(gold.clone(), Vec::new());
+        domain.actions.insert(
+            sym("go"),
+            Action {
+                parameters: objects(&["home"]),
+                agents: Vec::new(),
+                precondition: LogicExpr::Predicate(at.clone(), vec![sym("home"), sym("castle")]),
+                effect: LogicExpr::True,
+            },
+        );
+
+        let mut problem = PredicateProblem {
+            objects: objects(&["alice"]),
+            ..Default::default()
+        };
+        problem
+            .initial_state
+            .bound_predicates
+            .push((at.clone(), vec![sym("alice"), sym("home")]));
+        problem
+            .initial_state
+            .fluent_values
+            .push((gold.clone(), vec![sym("alice")], 3));
+        problem.goal = LogicExpr::Predicate(at.clone(), vec![sym("alice"), sym("castle")]);
+        problem
+            .character_goals
+            .push((sym("alice"), problem.goal.clone()));
+        let mut problems = vec![problem.clone(), problem];
+
+        let target = RenameTarget::Predicate(at.clone());
+        rename(&mut domain, &mut problems, &target, "in").unwrap();
+        let target = RenameTarget::Constant(sym("home"));
+        rename(&mut domain, &mut problems, &target, "house").unwrap();
+        let target = RenameTarget::Object(0, sym("alice"));
+        rename(&mut domain, &mut problems, &target, "bob").unwrap();
+
+        let renamed = PredicateSignature::new(sym("in"), 2);
+        assert!(domain.predicates.contains_key(&renamed));
+        assert_eq!(problems[0].objects[0].name, sym("bob"));
+        assert_eq!(
+            problems[0].initial_state.bound_predicates,
+            [(renamed.clone(), vec![sym("bob"), sym("house")])]
+        );
+        assert_eq!(
+            problems[0].initial_state.fluent_values,
+            [(gold, vec![sym("bob")], 3)]
+        );
+        assert_eq!(problems[0].goal.to_string(), "in(bob, castle)");
+        assert_eq!(problems[0].character_goals[0].0, sym("bob"));
+        assert_eq!(
+            problems[0].character_goals[0].1.to_string(),
+            "in(bob, castle)"
+        );
+        // Objects belong to a single problem.
+        assert_eq!(problems[1].goal.to_string(), "in(alice, castle)");
+        // The action parameter shadows the constant, so it must keep its name.
+        assert_eq!(
+            domain.actions[&sym("go")].precondition.to_string(),
+            "in(home, castle)"
+        );
+    }
+
+    #[test]
+    fn refuses_to_merge_names() {
+        let mut domain = PredicateDomain::default();
+        domain.types.insert(TypeName("person".into()), Vec::new());
+        domain.types.insert(TypeName("place".into()), Vec::new());
+
+        let target = RenameTarget::Type(TypeName("person".into()));
+        let err = rename(&mut domain, &mut [], &target, "place");
+
+        assert_eq!(err, Err(RenameError::AlreadyExists("place".into())));
+        assert!(domain.types.contains_key(&TypeName("person".into())));
+    }
+
+    #[test]
+    fn refuses_parameter_names() {
+        let at = PredicateSignature::new(sym("at"), 2);
+        let mut domain = PredicateDomain::default();
+        domain.predicates.insert(at.clone(), Vec::new());
+        domain.constants.insert(sym("home"), TypeName::default());
+        domain.actions.insert(
+            sym("go"),
+            Action {
+                parameters: objects(&["?c"]),
+                agents: Vec::new(),
+                precondition: LogicExpr::Predicate(at.clone(), vec![sym("?c"), sym("home")]),
+                effect: LogicExpr::True,
+            },
+        );
+        let mut problems = vec![PredicateProblem {
+            objects: objects(&["hero"]),
+            ..Default::default()
+        }];
+
+        let target = RenameTarget::Constant(sym("home"));
+        let err = rename(&mut domain, &mut problems, &target, "?c");
+        assert_eq!(err, Err(RenameError::ParameterName("?c".into())));
+        let target = RenameTarget::Object(0, sym("hero"));
+        let err = rename(&mut domain, &mut problems, &target, "?c");
+        assert_eq!(err, Err(RenameError::ParameterName("?c".into())));
+
+        assert_eq!(
+            domain.actions[&sym("go")].precondition.to_string(),
+            "at(?c, home)"
+        );
+        assert_eq!(problems[0].objects[0].name, sym("hero"));
+
+        let near = PredicateSignature::new(sym("near"), 1);
+        let axiom = Axiom {
+            variables: objects(&["?x"]),
+            body: LogicExpr::Predicate(at, vec![sym("?x"), sym("home")]),
+        };
+        domain.axioms.insert(near, axiom);
+        let err = rename(&mut domain, &mut problems, &target, "?x");
+        assert_eq!(err, Err(RenameError::ParameterName("?x".into())));
+    }
+}
@@ -0,0 +1,494 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    compact::{CompactState, FactTable, Interner},
+    compiled::CompiledProblem,
+    generation::{Progress, parallel_map},
+    grounding::GroundingStats,
+    story::{
+        Bindings, LogicExpr, PredicateDomain, PredicateProblem, PredicateStory, StoryState,
+        StoryStep,
+    },
+};
+
+/// How the next state to expand is picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchAlgorithm {
+    /// By the length of the story so far plus the estimate, which finds the shortest stories
+    /// when the estimate never overestimates.
+    #[default]
+    AStar,
+    /// By the estimate alone, which is faster but may find longer stories.
+    GreedyBestFirst,
+}
+
+impl SearchAlgorithm {
+    pub const ALL: [SearchAlgorithm; 2] =
+        [SearchAlgorithm::AStar, SearchAlgorithm::GreedyBestFirst];
+}
+
+impl Display for SearchAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchAlgorithm::AStar => write!(f, "A*"),
+            SearchAlgorithm::GreedyBestFirst => write!(f, "Greedy best-first"),
+        }
+    }
+}
+
+/// Estimates how many steps are left until the goal holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Heuristic {
+    /// The number of literals of the goal that don't hold yet.
+    GoalCount,
+    /// The sum of the costs of reaching each goal literal when effects never delete anything.
+    Additive,
+    /// The length of a plan reaching the goal when effects never delete anything, found in the
+    /// relaxed planning graph as in the FF planner.
+    #[default]
+    FastForward,
+}
+
+impl Heuristic {
+    pub const ALL: [Heuristic; 3] = [
+        Heuristic::GoalCount,
+        Heuristic::Additive,
+        Heuristic::FastForward,
+    ];
+}
+
+impl Display for Heuristic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Heuristic::GoalCount => write!(f, "goal count"),
+            Heuristic::Additive => write!(f, "h_add"),
+            Heuristic::FastForward => write!(f, "h_FF"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSettings {
+    pub algorithm: SearchAlgorithm,
+    pub heuristic: Heuristic,
+    /// How many states may be expanded before giving up.
+    pub max_expansions: usize,
+    /// How long the search may take before giving up.
+    pub time_limit: Duration,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: SearchAlgorithm::default(),
+            heuristic: Heuristic::default(),
+            max_expansions: 100_000,
+            time_limit: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How many nodes of equal priority each thread expands at once, at most.
+const BATCH_PER_THREAD: usize = 16;
+
+/// What a search did, to compare heuristics and tune budgets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// States whose successors were generated.
+    pub expanded: usize,
+    /// States reached by a step, including those seen before.
+    pub generated: usize,
+    pub elapsed: Duration,
+    /// How many ground actions the search chose from.
+    pub grounding: GroundingStats,
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} expanded, {} generated in {:.1} ms",
+            self.expanded,
+            self.generated,
+            self.elapsed.as_secs_f64() * 1000.
+        )
+    }
+}
+
+/// A grounding of an action with only the literals of its precondition and effect, which is all
+/// the heuristics look at.
+struct RelaxedOperator {
+    preconditions: Vec<usize>,
+    effects: Vec<usize>,
+}
+
+/// The problem with deletes ignored, over the literals that appear in some precondition, effect
+/// or the goal.
+struct RelaxedProblem {
+    /// Each literal as the index of its fact in the `FactTable` and whether it should hold.
+    facts: Vec<(usize, bool)>,
+    operators: Vec<RelaxedOperator>,
+    goal: Vec<usize>,
+}
+
+impl RelaxedProblem {
+    fn new(
+        domain: &PredicateDomain,
+        problem: &PredicateProblem,
+        compiled: &CompiledProblem,
+        state: &StoryState,
+        table: &mut FactTable,
+    ) -> Self {
+        let mut literals_seen = Interner::default();
+        let mut facts = Vec::new();
+        let mut literals = |expr: &LogicExpr, bindings: &Bindings| {
+            let mut literals = Vec::new();
+            expr.for_each_literal(&mut |signature, symbols, holds| {
+                if domain.is_derived(signature) {
+                    return;
+                }
+                let Some(arguments) = state.resolve_all(symbols, bindings) else {
+                    return;
+                };
+
+                let literal = (table.fact_index(signature, &arguments), holds);
+                let index = literals_seen.intern(&literal);
+                if index == facts.len() {
+                    facts.push(literal);
+                }
+                literals.push(index);
+            });
+            literals
+        };
+
+        let goal = literals(&problem.goal, &Bindings::new());
+        let mut operators = Vec::new();
+
+        for step in &compiled.steps {
+            let action = &domain.actions[&step.step.action];
+            let preconditions = literals(&action.precondition, &step.bindings);
+            let effects = literals(&action.effect, &step.bindings);
+            operators.push(RelaxedOperator {
+                preconditions,
+                effects,
+            });
+        }
+
+        RelaxedProblem {
+            facts,
+            operators,
+            goal,
+        }
+    }
+
+    fn holds(&self, fact: usize, state: &CompactState) -> bool {
+        let (index, holds) = self.facts[fact];
+        state.facts.contains(index) == holds
+    }
+
+    /// Estimates the number of steps left, or `None` if the goal can't be reached even when
+    /// ignoring deletes.
+    fn estimate(&self, heuristic: Heuristic, state: &CompactState) -> Option<usize> {
+        if heuristic == Heuristic::GoalCount {
+            return Some(
+                self.goal
+                    .iter()
+                    .filter(|fact| !self.holds(**fact, state))
+                    .count(),
+            );
+        }
+
+        // The additive cost of each fact along with the operator that reaches it most cheaply.
+        let mut costs: Vec<Option<(usize, Option<usize>)>> = (0..self.facts.len())
+            .map(|fact| self.holds(fact, state).then_some((0, None)))
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (i, operator) in self.operators.iter().enumerate() {
+                let Some(cost) = operator
+                    .preconditions
+                    .iter()
+                    .map(|fact| costs[*fact].map(|(cost, _)| cost))
+                    .sum::<Option<usize>>()
+                else {
+                    continue;
+                };
+
+                for effect in &operator.effects {
+                    if costs[*effect].is_none_or(|(old, _)| cost + 1 < old) {
+                        costs[*effect] = Some((cost + 1, Some(i)));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        match heuristic {
+            Heuristic::GoalCount | Heuristic::Additive => self
+                .goal
+                .iter()
+                .map(|fact| costs[*fact].map(|(cost, _)| cost))
+                .sum(),
+            Heuristic::FastForward => {
+                let mut relaxed_plan = vec![false; self.operators.len()];
+                let mut reached = vec![false; self.facts.len()];
+                let mut open = self.goal.clone();
+
+                while let Some(fact) = open.pop() {
+                    if std::mem::replace(&mut reached[fact], true) {
+                        continue;
+                    }
+                    match costs[fact]? {
+                        (_, Some(operator)) if !relaxed_plan[operator] => {
+                            relaxed_plan[operator] = true;
+                            open.extend(&self.operators[operator].preconditions);
+                        }
+                        _ => {}
+                    }
+                }
+
+                Some(relaxed_plan.iter().filter(|used| **used).count())
+            }
+        }
+    }
+}
+
+/// A state reached by the search, with the step leading there from its parent.
+struct SearchNode {
+    state: StoryState,
+    compact: CompactState,
+    parent: Option<(usize, StoryStep)>,
+    depth: usize,
+}
+
+impl PredicateDomain {
+    /// Searches for stories reaching the goal of the problem, guided by a heuristic. States seen
+    /// before are skipped unless they are reached by a shorter story.
+    ///
+    /// Trajectory constraints and intentions are checked once the goal holds, so they may rule
+    /// out stories without the search trying the others through the same states.
+    ///
+    /// Each story found is passed to `on_story`, and the search keeps looking for others as long
+    /// as it returns true. It stops early once `progress` is cancelled. Nodes of equal priority
+    /// are expanded on `threads` threads at once.
+    pub fn search_stories(
+        &self,
+        problem: &PredicateProblem,
+        settings: &SearchSettings,
+        threads: usize,
+        progress: &Progress,
+        on_story: &mut impl FnMut(PredicateStory) -> bool,
+    ) -> SearchStats {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+
+        let initial = self.initial_story_state(problem);
+        let mut table = FactTable::default();
+        let compiled = self.compile_problem(problem, &initial, &mut table);
+        let relaxed = RelaxedProblem::new(self, problem, &compiled, &initial, &mut table);
+        stats.grounding = compiled.grounding;
+
+        let priority = |depth: usize, estimate: usize| match settings.algorithm {
+            SearchAlgorithm::AStar => (depth + estimate, estimate),
+            SearchAlgorithm::GreedyBestFirst => (estimate, depth),
+        };
+
+        let mut nodes = Vec::new();
+        let mut best_depths = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        let compact = table.compact(&initial);
+        if let Some(estimate) = relaxed.estimate(settings.heuristic, &compact) {
+            best_depths.insert(compact.clone(), 0);
+            nodes.push(SearchNode {
+                state: initial,
+                compact,
+                parent: None,
+                depth: 0,
+            });
+            queue.push(Reverse((priority(0, estimate), 0)));
+        }
+
+        'search: while let Some(Reverse((first, _))) = queue.peek() {
+            // Nodes with the same priority as the best one can be expanded together without
+            // changing which stories are found first.
+            let first = first.0;
+            let mut batch = Vec::new();
+            while batch.len() < threads.max(1) * BATCH_PER_THREAD
+                && let Some(Reverse(((priority, _), _))) = queue.peek()
+                && *priority == first
+            {
+                if stats.expanded >= settings.max_expansions
+                    || start.elapsed() >= settings.time_limit
+                    || progress.is_cancelled()
+                {
+                    break 'search;
+                }
+                let Some(Reverse((_, index))) = queue.pop() else {
+                    break;
+                };
+
+                let node = &nodes[index];
+                if compiled.goal.evaluate(&node.state, &node.compact)
+                    && let Some(found) = self.finished_story(problem, &nodes, index)
+                    && !on_story(found)
+                {
+                    break 'search;
+                }
+                if nodes[index].depth >= problem.max_story_length as usize {
+                    continue;
+                }
+                stats.expanded += 1;
+                progress.advance(1);
+                batch.push(index);
+            }
+
+            let successors: Vec<_> = parallel_map(threads, &batch, |index| {
+                let node = &nodes[*index];
+                compiled
+                    .applicable_steps(&node.state, &node.compact)
+                    .into_iter()
+                    .map(|step| {
+                        (
+                            *index,
+                            step,
+                            self.apply_compiled_step(&node.state, step, &table),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+            stats.generated += successors.len();
+
+            // Only interning facts needs the table mutably, so that part stays on this thread.
+            let mut fresh = Vec::new();
+            for (parent, step, state) in successors {
+                let depth = nodes[parent].depth + 1;
+                let compact = table.compact(&state);
+                if best_depths.get(&compact).is_some_and(|best| *best <= depth) {
+                    continue;
+                }
+                best_depths.insert(compact.clone(), depth);
+                fresh.push((parent, step, state, compact, depth));
+            }
+
+            let estimates = parallel_map(threads, &fresh, |(.., compact, _)| {
+                relaxed.estimate(settings.heuristic, compact)
+            });
+            for ((parent, step, state, compact, depth), estimate) in
+                fresh.into_iter().zip(estimates)
+            {
+                // States from which the goal can't be reached are dead ends.
+                let Some(estimate) = estimate else {
+                    continue;
+                };
+                nodes.push(SearchNode {
+                    state,
+                    compact,
+                    parent: Some((parent, step.step.clone())),
+                    depth,
+                });
+                queue.push(Reverse((priority(depth, estimate), nodes.len() - 1)));
+            }
+        }
+
+        stats.elapsed = start.elapsed();
+        stats
+    }
+
+    /// The story leading to the node, if it is long enough, meets the constraints and explains
+    /// every step.
+    fn finished_story(
+        &self,
+        problem: &PredicateProblem,
+        nodes: &[SearchNode],
+        mut index: usize,
+    ) -> Option<PredicateStory> {
+        let mut steps = Vec::new();
+        let mut states = vec![nodes[index].state.clone()];
+        while let Some((parent, step)) = &nodes[index].parent {
+            steps.push(step.clone());
+            states.push(nodes[*parent].state.clone());
+            index = *parent;
+        }
+        steps.reverse();
+        states.reverse();
+
+        self.is_finished(problem, &steps, &states)
+            .then(|| PredicateStory::new(steps, states))
+            .filter(|story| self.unexplained_steps(problem, story).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        story::PredicateSignature,
+        test_util::{action, domain_with, objects, sym},
+    };
+
+    #[test]
+    fn finds_the_shortest_story_with_every_heuristic() {
+        let mut domain = domain_with(&[("road", 2), ("at", 1)], &[]);
+        let walk = action(
+            &domain,
+            &["?from", "?to"],
+            "at(?from) and road(?from, ?to)",
+            "not at(?from) and at(?to)",
+        );
+        domain.actions.insert(sym("walk"), walk);
+
+        // A line of towns with a shortcut from the second to the last.
+        let towns = ["a", "b", "c", "d", "e"];
+        let mut problem = PredicateProblem {
+            objects: objects(&towns),
+            ..Default::default()
+        };
+        let road = PredicateSignature::new(sym("road"), 2);
+        for pair in towns.windows(2).chain([["b", "e"].as_slice()]) {
+            problem
+                .initial_state
+                .bound_predicates
+                .push((road.clone(), vec![sym(pair[0]), sym(pair[1])]));
+        }
+        problem
+            .initial_state
+            .bound_predicates
+            .push((PredicateSignature::new(sym("at"), 1), vec![sym("a")]));
+        problem.goal = parse_logic_expr(&domain, "at(e)").unwrap();
+        problem.max_story_length = 10;
+
+        for (heuristic, threads) in Heuristic::ALL.into_iter().flat_map(|h| [(h, 1), (h, 4)]) {
+            let settings = SearchSettings {
+                heuristic,
+                ..Default::default()
+            };
+            let mut story = None;
+            let progress = Progress::default();
+            let stats =
+                domain.search_stories(&problem, &settings, threads, &progress, &mut |found| {
+                    story = Some(found);
+                    false
+                });
+
+            assert_eq!(
+                story.unwrap().text,
+                "walk(a, b)\nwalk(b, e)\n",
+                "with {heuristic} on {threads} threads"
+            );
+            assert!(stats.expanded > 0 && stats.generated >= stats.expanded);
+        }
+    }
+}
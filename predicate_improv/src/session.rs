@@ -0,0 +1,219 @@
+use std::mem::take;
+
+use eframe::egui::{self, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action_panel::ActionDraft,
+    app::PredicateImprovApp,
+    predicate_panel::PredicateDraft,
+    problem_panel::{BoundPredicateDraft, FluentValueDraft},
+    story::{PredicateDomain, PredicateProblem},
+    task_panel::TaskDraft,
+    types_and_constants_panel::{ConstantDraft, TypeDraft},
+};
+
+/// The key under which the session is kept in eframe's storage.
+pub const SESSION_KEY: &str = "session";
+
+/// Everything needed to pick up the work where it was left after the app is closed or crashes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub domain: PredicateDomain,
+    pub problems: Vec<PredicateProblem>,
+    pub selected_problem: usize,
+    pub predicate_draft: PredicateDraft,
+    pub action_draft: ActionDraft,
+    #[serde(default)]
+    pub task_draft: TaskDraft,
+    pub type_draft: TypeDraft,
+    pub constant_draft: ConstantDraft,
+    pub bound_predicate_draft: BoundPredicateDraft,
+    #[serde(default)]
+    pub fluent_value_draft: FluentValueDraft,
+    pub object_draft: (String, String),
+    pub story_draft: String,
+}
+
+impl Session {
+    pub fn capture(app: &PredicateImprovApp) -> Self {
+        let mut domain = app.domain.clone();
+        let mut problems = app.problems.clone();
+
+        // Items being edited in a modal are removed until the modal is submitted, so save the
+        // originals along with the drafts.
+        if let Some(draft) = app.predicate_panel.backup_predicate_draft.clone()
+            && draft.derived.is_some()
+        {
+            let (signature, axiom) = draft.into_axiom();
+            domain.axioms.entry(signature).or_insert(axiom);
+        } else if let Some(draft) = app.predicate_panel.backup_predicate_draft.clone() {
+            let declarations = if draft.numeric {
+                &mut domain.fluents
+            } else {
+                &mut domain.predicates
+            };
+            let (signature, variables) = draft.into_predicate();
+            declarations.entry(signature).or_insert(variables);
+        }
+        if let Some(draft) = app.action_panel.backup_action_draft.clone() {
+            let (name, action) = draft.into_action();
+            domain.actions.entry(name).or_insert(action);
+        }
+        if let Some(draft) = app.task_panel.backup_task_draft.clone() {
+            let (name, task) = draft.into_task();
+            domain.tasks.entry(name).or_insert(task);
+        }
+        if let Some(draft) = app.types_and_constants_panel.backup_type_draft.clone() {
+            let (name, supertypes) = draft.into_type();
+            domain.types.entry(name).or_insert(supertypes);
+        }
+        if let Some(draft) = app.types_and_constants_panel.backup_constant_draft.clone() {
+            let (name, r#type) = draft.into_constant();
+            domain.constants.entry(name).or_insert(r#type);
+        }
+        if let Some(draft) = app.problem_panel.backup_bound_predicate_draft.clone() {
+            problems[app.selected_problem]
+                .initial_state
+                .bound_predicates
+                .push(draft.into_bound_predicate());
+        }
+        if let Some(draft) = app.problem_panel.backup_fluent_value_draft.clone() {
+            problems[app.selected_problem]
+                .initial_state
+                .fluent_values
+                .push(draft.into_fluent_value());
+        }
+
+        Self {
+            domain,
+            problems,
+            selected_problem: app.selected_problem,
+            predicate_draft: app.predicate_panel.predicate_draft.clone(),
+            action_draft: app.action_panel.action_draft.clone(),
+            task_draft: app.task_panel.task_draft.clone(),
+            type_draft: app.types_and_constants_panel.type_draft.clone(),
+            constant_draft: app.types_and_constants_panel.constant_draft.clone(),
+            bound_predicate_draft: app.problem_panel.bound_predicate_draft.clone(),
+            fluent_value_draft: app.problem_panel.fluent_value_draft.clone(),
+            object_draft: app.problem_panel.object_draft.clone(),
+            story_draft: app.validator_panel.story_draft.clone(),
+        }
+    }
+
+    pub fn restore(self, app: &mut PredicateImprovApp) {
+        app.domain = self.domain;
+        if !self.problems.is_empty() {
+            app.selected_problem = self.selected_problem.min(self.problems.len() - 1);
+            app.problems = self.problems;
+        }
+        app.predicate_panel.predicate_draft = self.predicate_draft;
+        app.action_panel.action_draft = self.action_draft;
+        app.task_panel.task_draft = self.task_draft;
+        app.types_and_constants_panel.type_draft = self.type_draft;
+        app.types_and_constants_panel.constant_draft = self.constant_draft;
+        app.problem_panel.bound_predicate_draft = self.bound_predicate_draft;
+        app.problem_panel.fluent_value_draft = self.fluent_value_draft;
+        app.problem_panel.object_draft = self.object_draft;
+        app.validator_panel.story_draft = self.story_draft;
+    }
+
+    /// Whether the session holds nothing worth offering to restore.
+    pub fn is_empty(&self) -> bool {
+        let domain = &self.domain;
+
+        domain.predicates.is_empty()
+            && domain.actions.is_empty()
+            && domain.constants.is_empty()
+            && domain.types.is_empty()
+            && domain.fluents.is_empty()
+            && domain.axioms.is_empty()
+            && domain.tasks.is_empty()
+            && self.problems.len() <= 1
+            && self.problems.iter().all(|problem| {
+                problem.objects.is_empty()
+                    && problem.initial_state.bound_predicates.is_empty()
+                    && problem.initial_state.fluent_values.is_empty()
+                    && !problem.has_goal()
+                    && problem.character_goals.is_empty()
+                    && problem.initial_state.beliefs.is_empty()
+                    && problem.constraints.is_empty()
+                    && problem.outline.is_empty()
+                    && problem.tension.is_empty()
+            })
+            && self.story_draft.is_empty()
+    }
+}
+
+/// Asks whether the session recovered on launch should replace the empty workspace.
+pub fn recovery_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
+    ui.label("Your work from the last session was saved. Do you want to restore it?");
+
+    ui.horizontal(|ui| {
+        if ui
+            .add(egui::Button::new("Restore").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .clicked()
+            && let Some(session) = take(&mut app.recovered_session)
+        {
+            session.restore(app);
+        }
+
+        if ui
+            .add(egui::Button::new("Discard").min_size(Vec2::new(ui.available_width(), 0.)))
+            .clicked()
+        {
+            app.recovered_session = None;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::story::{LogicExpr, PredicateSignature, Symbol, TypeName, TypedSymbol};
+
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.into(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_storage() {
+        let at = PredicateSignature::new(Symbol("at".into()), 1);
+        let mut session = Session::default();
+        session.domain.predicates.insert(
+            at.clone(),
+            vec![TypedSymbol {
+                name: Symbol("?x".into()),
+                r#type: TypeName("person".into()),
+            }],
+        );
+        session.problems.push(PredicateProblem::default());
+        session.problems[0].goal = LogicExpr::Not(Box::new(LogicExpr::Predicate(
+            at.clone(),
+            vec![Symbol("alice".into())],
+        )));
+        session.story_draft = "go(alice)".into();
+
+        let mut storage = MemoryStorage::default();
+        eframe::set_value(&mut storage, SESSION_KEY, &session);
+        let restored: Session = eframe::get_value(&storage, SESSION_KEY).unwrap();
+
+        assert_eq!(restored.domain.predicates, session.domain.predicates);
+        assert_eq!(restored.problems, session.problems);
+        assert_eq!(restored.story_draft, session.story_draft);
+        assert!(!restored.is_empty());
+    }
+}
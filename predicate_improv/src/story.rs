@@ -1,23 +1,44 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    sync::Arc,
+};
 
 use derive_deref::{Deref, DerefMut};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deref, DerefMut)]
+use crate::{
+    axioms::Derivation,
+    constraints::Constraint,
+    generation::{Progress, stream_attempts},
+    partial_order::PartialOrderPlan,
+    tension::Tension,
+};
+
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deref,
+    DerefMut,
+    Serialize,
+    Deserialize,
+)]
 pub struct Symbol(pub String);
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deref, DerefMut)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deref, DerefMut, Serialize, Deserialize)]
 pub struct TypeName(pub String);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Atom(usize);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct TypeSignature {
-    pub name: Symbol,
-    pub supertype: Vec<Symbol>,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TypedSymbol {
     pub name: Symbol,
     pub r#type: TypeName,
@@ -29,13 +50,7 @@ impl Hash for TypedSymbol {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum VariableOrConstant {
-    Variable(TypedSymbol),
-    Constant(Symbol),
-}
-
-#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PredicateSignature {
     /// The function symbol distinguishes different predicates, thus it should be unique.
     pub function: Symbol,
@@ -52,7 +67,7 @@ impl PredicateSignature {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PredicateDomain {
     /// Maps the predicate signature to the variable names.
     pub predicates: HashMap<PredicateSignature, Vec<TypedSymbol>>,
@@ -62,52 +77,951 @@ pub struct PredicateDomain {
     pub constants: HashMap<Symbol, TypeName>,
     /// Maps type names to supertypes
     pub types: HashMap<TypeName, Vec<TypeName>>,
+    /// Maps numeric fluent signatures to the variable names, e.g. `health(?c)` or
+    /// `trust(?a, ?b)`.
+    #[serde(default)]
+    pub fluents: HashMap<PredicateSignature, Vec<TypedSymbol>>,
+    /// Maps derived predicate signatures to their definitions, e.g.
+    /// `can-see(?a, ?b) := at(?a, ?l) and at(?b, ?l) and lit(?l)`.
+    #[serde(default)]
+    pub axioms: HashMap<PredicateSignature, Axiom>,
+    /// Maps compound task names to the ways of performing them, e.g. `rescue(?hero, ?victim)`.
+    #[serde(default)]
+    pub tasks: HashMap<Symbol, CompoundTask>,
 }
 
 impl PredicateDomain {
-    pub fn generate_story(&self) -> Option<PredicateStory> {
-        None
+    /// Improvises a story by randomly picking applicable actions, starting from the initial state
+    /// of the problem, until the goal and constraints hold after at least `min_story_length`
+    /// steps, `max_story_length` is reached or no action can be applied. Stories that end without
+    /// reaching the goal, that break a constraint, or in which a character acts without a reason,
+    /// are discarded and improvised again, up to `GENERATION_ATTEMPTS` times on `threads`
+    /// threads. Each new story is passed to `on_story`, until it returns false.
+    ///
+    /// Problems with an outline are decomposed into actions with `decompose_outline` instead.
+    pub fn generate_stories(
+        &self,
+        problem: &PredicateProblem,
+        threads: usize,
+        progress: &Progress,
+        on_story: &mut impl FnMut(PredicateStory) -> bool,
+    ) {
+        if !problem.outline.is_empty() {
+            progress.advance(1);
+            if let Some(story) = self.decompose_outline(problem) {
+                on_story(story);
+            }
+            return;
+        }
+
+        stream_attempts(GENERATION_ATTEMPTS, threads, progress, on_story, || {
+            self.improvise_story(problem)
+        });
+    }
+
+    fn improvise_story(&self, problem: &PredicateProblem) -> Option<PredicateStory> {
+        let mut state = self.initial_story_state(problem);
+        let mut steps = Vec::new();
+        let mut states = vec![state.clone()];
+
+        for _ in 0..problem.max_story_length {
+            if (problem.has_goal() || !problem.constraints.is_empty())
+                && self.is_finished(problem, &steps, &states)
+            {
+                break;
+            }
+
+            let applicable = self.candidate_steps(problem, &state);
+            if applicable.is_empty() {
+                break;
+            }
+
+            let step = applicable[rand::random_range(0..applicable.len())].clone();
+            state = self.apply_step(&state, &step)?;
+
+            steps.push(step);
+            states.push(state.clone());
+        }
+
+        self.is_finished(problem, &steps, &states)
+            .then(|| PredicateStory::new(steps, states))
+            .filter(|story| self.unexplained_steps(problem, story).is_empty())
     }
+
+    /// The applicable steps that stories of the problem may take in the state: those of actions
+    /// the constraints don't exclude, and whose agents all have goals, since characters without
+    /// goals can never have a reason to act.
+    pub fn candidate_steps(
+        &self,
+        problem: &PredicateProblem,
+        state: &StoryState,
+    ) -> Vec<StoryStep> {
+        self.applicable_steps(state)
+            .into_iter()
+            .filter(|step| {
+                !problem.excludes(&step.action)
+                    && self
+                        .step_agents(step)
+                        .iter()
+                        .all(|agent| problem.has_goals(agent))
+            })
+            .collect()
+    }
+
+    /// Whether the story is long enough, reaches the goal of the problem and meets its
+    /// constraints. `states` holds the state before the first step followed by the state after
+    /// each step.
+    pub fn is_finished(
+        &self,
+        problem: &PredicateProblem,
+        steps: &[StoryStep],
+        states: &[StoryState],
+    ) -> bool {
+        steps.len() >= problem.min_story_length as usize
+            && states
+                .last()
+                .is_some_and(|state| state.evaluate(&problem.goal, &Bindings::new()))
+            && problem.violated_constraint(steps, states).is_none()
+    }
+
+    /// Whether `ty` is `expected` or (transitively) one of its subtypes. The empty type name is
+    /// the implicit root type `object`.
+    pub fn is_subtype(&self, ty: &TypeName, expected: &TypeName) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![ty];
+
+        while let Some(ty) = stack.pop() {
+            if expected.is_empty() || ty == expected {
+                return true;
+            }
+
+            if visited.insert(ty) {
+                stack.extend(self.types.get(ty).into_iter().flatten());
+            }
+        }
+
+        false
+    }
+
+    /// The names in the initial facts and fluent values of the problem that are neither constants
+    /// nor objects, e.g. a misspelled object. `initial_story_state` skips the facts using them.
+    pub fn unknown_initial_symbols(&self, problem: &PredicateProblem) -> Vec<Symbol> {
+        let known = |name: &Symbol| {
+            self.constants.contains_key(name)
+                || problem.objects.iter().any(|object| &object.name == name)
+        };
+
+        let initial_state = &problem.initial_state;
+        let mut unknown = Vec::new();
+        for name in initial_state
+            .bound_predicates
+            .iter()
+            .flat_map(|(_, bindings)| bindings)
+            .chain(
+                initial_state
+                    .fluent_values
+                    .iter()
+                    .flat_map(|(_, bindings, _)| bindings),
+            )
+        {
+            if !known(name) && !unknown.contains(name) {
+                unknown.push(name.clone());
+            }
+        }
+        unknown
+    }
+
+    /// Builds the story state described by the initial state of the problem. Constants and
+    /// objects become atoms, in that order. Facts and fluent values naming anything else are
+    /// skipped, see `unknown_initial_symbols`.
+    pub fn initial_story_state(&self, problem: &PredicateProblem) -> StoryState {
+        let mut state = StoryState::default();
+
+        for (name, r#type) in &self.constants {
+            state.get_or_insert_atom(name, r#type);
+        }
+
+        for object in &problem.objects {
+            state.get_or_insert_atom(&object.name, &object.r#type);
+        }
+
+        let no_bindings = Bindings::new();
+        for (signature, bindings) in &problem.initial_state.bound_predicates {
+            if let Some(arguments) = state.resolve_all(bindings, &no_bindings) {
+                state.set_fact(signature, arguments, true);
+            }
+        }
+
+        for (signature, bindings, value) in &problem.initial_state.fluent_values {
+            if let Some(arguments) = state.resolve_all(bindings, &no_bindings) {
+                state.set_fluent(signature, arguments, *value);
+            }
+        }
+
+        for (character, beliefs) in &problem.initial_state.beliefs {
+            let told = LogicExpr::Learn(
+                BeliefUpdate::Inform,
+                character.clone(),
+                Box::new(beliefs.clone()),
+            );
+            state.apply_effect(&told, &Bindings::new());
+        }
+
+        if !self.axioms.is_empty() {
+            state.derivation = Some(Arc::new(self.derivation(&state)));
+        }
+        self.derive(&mut state);
+        state
+    }
+
+    /// Lists every grounding of every action whose precondition holds in the given state.
+    pub fn applicable_steps(&self, state: &StoryState) -> Vec<StoryStep> {
+        let mut steps = Vec::new();
+
+        for (name, action) in &self.actions {
+            let candidates: Vec<Vec<Atom>> = action
+                .parameters
+                .iter()
+                .map(|param| state.atoms_of_type(self, &param.r#type))
+                .collect();
+
+            let mut arguments = Vec::with_capacity(candidates.len());
+            self.ground_action(state, name, action, &candidates, &mut arguments, &mut steps);
+        }
+
+        steps
+    }
+
+    fn ground_action(
+        &self,
+        state: &StoryState,
+        name: &Symbol,
+        action: &Action,
+        candidates: &[Vec<Atom>],
+        arguments: &mut Vec<Atom>,
+        out: &mut Vec<StoryStep>,
+    ) {
+        let Some(atoms) = candidates.get(arguments.len()) else {
+            let bindings = action.bind(arguments);
+            if state.evaluate(&action.precondition, &bindings) {
+                out.push(StoryStep {
+                    action: name.clone(),
+                    arguments: arguments
+                        .iter()
+                        .map(|atom| Symbol(state.get_atom_name(*atom).unwrap_or_default().into()))
+                        .collect(),
+                });
+            }
+            return;
+        };
+
+        for atom in atoms {
+            arguments.push(*atom);
+            self.ground_action(state, name, action, candidates, arguments, out);
+            arguments.pop();
+        }
+    }
+
+    /// Applies the step to a copy of the state. Returns `None` if the step cannot be applied; use
+    /// `check_step` to find out why.
+    pub fn apply_step(&self, state: &StoryState, step: &StoryStep) -> Option<StoryState> {
+        self.check_step(state, step).ok()
+    }
+}
+
+/// How many random stories are improvised while looking for one that reaches the goal.
+pub const GENERATION_ATTEMPTS: usize = 1000;
+
+/// Maps action parameters to the atoms they are bound to.
+pub type Bindings = HashMap<Symbol, Atom>;
+
+/// The atoms of a story. Steps never add atoms, so all states of a story share one table.
+#[derive(Debug, Default, Clone)]
+pub struct AtomTable {
+    symbols: Vec<TypedSymbol>,
+    /// Looks atoms up by name without comparing strings one by one.
+    indices: HashMap<Symbol, Atom>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct StoryState {
     /// All atoms that exist in the story state
-    pub atoms: Vec<TypedSymbol>,
+    pub atoms: Arc<AtomTable>,
     /// All predicates bound here are true, all predicates omitted are false.
-    pub bound_predicates: HashMap<PredicateSignature, Vec<Atom>>,
+    pub bound_predicates: HashMap<PredicateSignature, HashSet<Vec<Atom>>>,
+    /// The values of numeric fluents. Fluents omitted here are 0.
+    pub fluents: HashMap<PredicateSignature, HashMap<Vec<Atom>, i64>>,
+    /// Whether each character believes facts to be true or false, which may differ from the
+    /// facts themselves. Facts omitted here are unknown to the character.
+    pub beliefs: HashMap<Atom, HashMap<(PredicateSignature, Vec<Atom>), bool>>,
+    /// The axioms of the domain the story started in, prepared by `initial_story_state`.
+    pub derivation: Option<Arc<Derivation>>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InitialState {
     /// All predicates bound here are true, all predicates omitted are false.
-    pub bound_predicates: HashMap<PredicateSignature, Vec<Symbol>>,
+    pub bound_predicates: Vec<(PredicateSignature, Vec<Symbol>)>,
+    /// Initial values of numeric fluents. Fluents omitted here start at 0.
+    #[serde(default)]
+    pub fluent_values: Vec<(PredicateSignature, Vec<Symbol>, i64)>,
+    /// What characters are told before the story starts, as literals joined with `and`.
+    #[serde(default)]
+    pub beliefs: Vec<(Symbol, LogicExpr)>,
 }
 
 impl StoryState {
     pub fn get_atom(&self, name: &Symbol) -> Option<Atom> {
-        let idx = self.atoms.iter().position(|obj| obj.name == name.clone())?;
-        Some(Atom(idx))
+        self.atoms.indices.get(name).copied()
     }
 
     pub fn get_or_insert_atom(&mut self, name: &Symbol, r#type: &TypeName) -> Atom {
-        if let Some(idx) = self.atoms.iter().position(|obj| obj.name == name.clone()) {
-            Atom(idx)
-        } else {
-            self.atoms.push(TypedSymbol {
-                name: name.clone(),
-                r#type: r#type.clone(),
+        if let Some(atom) = self.get_atom(name) {
+            return atom;
+        }
+
+        let atoms = Arc::make_mut(&mut self.atoms);
+        let atom = Atom(atoms.symbols.len());
+        atoms.symbols.push(TypedSymbol {
+            name: name.clone(),
+            r#type: r#type.clone(),
+        });
+        atoms.indices.insert(name.clone(), atom);
+        atom
+    }
+
+    pub fn get_atom_name(&self, atom: Atom) -> Option<&str> {
+        self.atoms.symbols.get(atom.0).map(|x| x.name.as_str())
+    }
+
+    pub fn get_atom_type(&self, atom: Atom) -> Option<&TypeName> {
+        self.atoms.symbols.get(atom.0).map(|x| &x.r#type)
+    }
+
+    /// All atoms whose type is `r#type` or one of its subtypes.
+    pub fn atoms_of_type(&self, domain: &PredicateDomain, r#type: &TypeName) -> Vec<Atom> {
+        let symbols = &self.atoms.symbols;
+        (0..symbols.len())
+            .filter(|idx| domain.is_subtype(&symbols[*idx].r#type, r#type))
+            .map(Atom)
+            .collect()
+    }
+
+    pub fn holds(&self, signature: &PredicateSignature, arguments: &[Atom]) -> bool {
+        self.bound_predicates
+            .get(signature)
+            .is_some_and(|bindings| bindings.contains(arguments))
+    }
+
+    pub fn set_fact(&mut self, signature: &PredicateSignature, arguments: Vec<Atom>, value: bool) {
+        if value {
+            self.bound_predicates
+                .entry(signature.clone())
+                .or_default()
+                .insert(arguments);
+        } else if let Some(bindings) = self.bound_predicates.get_mut(signature) {
+            bindings.remove(&arguments);
+            if bindings.is_empty() {
+                self.bound_predicates.remove(signature);
+            }
+        }
+    }
+
+    pub fn fluent_value(&self, signature: &PredicateSignature, arguments: &[Atom]) -> i64 {
+        self.fluents
+            .get(signature)
+            .and_then(|values| values.get(arguments))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_fluent(&mut self, signature: &PredicateSignature, arguments: Vec<Atom>, value: i64) {
+        self.fluents
+            .entry(signature.clone())
+            .or_default()
+            .insert(arguments, value);
+    }
+
+    /// Iterates over every fluent that was given a value.
+    pub fn fluent_values(&self) -> impl Iterator<Item = (&PredicateSignature, &Vec<Atom>, i64)> {
+        self.fluents.iter().flat_map(|(signature, values)| {
+            values
+                .iter()
+                .map(move |(args, value)| (signature, args, *value))
+        })
+    }
+
+    /// Whether the character believes the fact to be true or false, or `None` if they do not
+    /// know.
+    pub fn belief(
+        &self,
+        character: Atom,
+        signature: &PredicateSignature,
+        arguments: &[Atom],
+    ) -> Option<bool> {
+        self.beliefs
+            .get(&character)?
+            .get(&(signature.clone(), arguments.to_vec()))
+            .copied()
+    }
+
+    pub fn set_belief(
+        &mut self,
+        character: Atom,
+        signature: &PredicateSignature,
+        arguments: Vec<Atom>,
+        value: bool,
+    ) {
+        self.beliefs
+            .entry(character)
+            .or_default()
+            .insert((signature.clone(), arguments), value);
+    }
+
+    /// Iterates over every belief of every character.
+    pub fn beliefs(&self) -> impl Iterator<Item = (Atom, &PredicateSignature, &Vec<Atom>, bool)> {
+        self.beliefs.iter().flat_map(|(character, beliefs)| {
+            beliefs
+                .iter()
+                .map(|((signature, args), value)| (*character, signature, args, *value))
+        })
+    }
+
+    /// Formats a belief as `alice believes not at(bob, castle)`.
+    pub fn belief_label(
+        &self,
+        character: Atom,
+        signature: &PredicateSignature,
+        arguments: &[Atom],
+        value: bool,
+    ) -> String {
+        format!(
+            "{} believes {}{}",
+            self.get_atom_name(character).unwrap_or("?"),
+            if value { "" } else { "not " },
+            self.fact_label(signature, arguments)
+        )
+    }
+
+    /// Iterates over every true fact in the state.
+    pub fn facts(&self) -> impl Iterator<Item = (&PredicateSignature, &Vec<Atom>)> {
+        self.bound_predicates
+            .iter()
+            .flat_map(|(signature, bindings)| bindings.iter().map(move |args| (signature, args)))
+    }
+
+    /// Formats a fact as `function(arg1, arg2)`.
+    pub fn fact_label(&self, signature: &PredicateSignature, arguments: &[Atom]) -> String {
+        let arguments: Vec<&str> = arguments
+            .iter()
+            .map(|atom| self.get_atom_name(*atom).unwrap_or("?"))
+            .collect();
+
+        format!("{}({})", signature.function.0, arguments.join(", "))
+    }
+
+    /// Resolves a symbol from a logic expression to an atom, preferring action parameters over
+    /// constants and objects of the same name.
+    fn resolve(&self, symbol: &Symbol, bindings: &Bindings) -> Option<Atom> {
+        bindings
+            .get(symbol)
+            .copied()
+            .or_else(|| self.get_atom(symbol))
+    }
+
+    pub fn resolve_all(&self, symbols: &[Symbol], bindings: &Bindings) -> Option<Vec<Atom>> {
+        symbols
+            .iter()
+            .map(|symbol| self.resolve(symbol, bindings))
+            .collect()
+    }
+
+    /// Evaluates the expression in this state. Predicates and comparisons referring to unknown
+    /// symbols are false. Fluent and belief updates only have a meaning as effects and always
+    /// hold.
+    pub fn evaluate(&self, expr: &LogicExpr, bindings: &Bindings) -> bool {
+        match expr {
+            LogicExpr::True | LogicExpr::Update(..) | LogicExpr::Learn(..) => true,
+            LogicExpr::Believes(character, beliefs) => self
+                .resolve(character, bindings)
+                .is_some_and(|character| self.evaluate_belief(character, beliefs, bindings)),
+            LogicExpr::Compare(comparison, lhs, rhs) => self
+                .evaluate_numeric(lhs, bindings)
+                .zip(self.evaluate_numeric(rhs, bindings))
+                .is_some_and(|(lhs, rhs)| comparison.holds(lhs, rhs)),
+            LogicExpr::Predicate(signature, symbols) => self
+                .resolve_all(symbols, bindings)
+                .is_some_and(|arguments| self.holds(signature, &arguments)),
+            LogicExpr::Not(expr) => !self.evaluate(expr, bindings),
+            LogicExpr::And(lhs, rhs) => {
+                self.evaluate(lhs, bindings) && self.evaluate(rhs, bindings)
+            }
+            LogicExpr::Or(lhs, rhs) => self.evaluate(lhs, bindings) || self.evaluate(rhs, bindings),
+        }
+    }
+
+    /// Evaluates the expression against what the character believes: predicates hold if they are
+    /// believed to be true and negated predicates if they are believed to be false, so facts the
+    /// character does not know about satisfy neither.
+    fn evaluate_belief(&self, character: Atom, expr: &LogicExpr, bindings: &Bindings) -> bool {
+        self.believed_as(character, expr, bindings, true)
+    }
+
+    /// Whether the character believes the expression to be `value`. Negations are pushed down to
+    /// the predicates, since a fact the character does not know about is neither believed nor
+    /// disbelieved.
+    fn believed_as(
+        &self,
+        character: Atom,
+        expr: &LogicExpr,
+        bindings: &Bindings,
+        value: bool,
+    ) -> bool {
+        match expr {
+            LogicExpr::True => value,
+            LogicExpr::Predicate(signature, symbols) => self
+                .resolve_all(symbols, bindings)
+                .is_some_and(|args| self.belief(character, signature, &args) == Some(value)),
+            LogicExpr::Not(inner) => self.believed_as(character, inner, bindings, !value),
+            // Under negation, `and` turns into `or` and the other way around.
+            LogicExpr::And(lhs, rhs) if value => {
+                self.believed_as(character, lhs, bindings, value)
+                    && self.believed_as(character, rhs, bindings, value)
+            }
+            LogicExpr::Or(lhs, rhs) if !value => {
+                self.believed_as(character, lhs, bindings, value)
+                    && self.believed_as(character, rhs, bindings, value)
+            }
+            LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+                self.believed_as(character, lhs, bindings, value)
+                    || self.believed_as(character, rhs, bindings, value)
+            }
+            LogicExpr::Compare(..)
+            | LogicExpr::Update(..)
+            | LogicExpr::Believes(..)
+            | LogicExpr::Learn(..) => false,
+        }
+    }
+
+    /// Collects the facts the expression relies on in this state, following only the disjuncts
+    /// that hold. Negated predicates rely on the absence of a fact.
+    pub fn supporting_facts(&self, expr: &LogicExpr, bindings: &Bindings, out: &mut Vec<Fact>) {
+        match expr {
+            LogicExpr::True | LogicExpr::Update(..) | LogicExpr::Learn(..) => {}
+            LogicExpr::Believes(character, beliefs) => {
+                if let Some(character) = self.resolve(character, bindings)
+                    && self.evaluate_belief(character, beliefs, bindings)
+                {
+                    beliefs.for_each_literal(&mut |signature, symbols, value| {
+                        if let Some(arguments) = self.resolve_all(symbols, bindings)
+                            && self.belief(character, signature, &arguments) == Some(value)
+                        {
+                            out.push(Fact::Belief(character, signature.clone(), arguments, value));
+                        }
+                    });
+                }
+            }
+            LogicExpr::Predicate(signature, symbols) => {
+                if let Some(arguments) = self.resolve_all(symbols, bindings)
+                    && self.holds(signature, &arguments)
+                {
+                    out.push(Fact::Predicate(signature.clone(), arguments));
+                }
+            }
+            LogicExpr::Not(inner) => {
+                if let LogicExpr::Predicate(signature, symbols) = &**inner
+                    && let Some(arguments) = self.resolve_all(symbols, bindings)
+                    && !self.holds(signature, &arguments)
+                {
+                    out.push(Fact::Absent(signature.clone(), arguments));
+                }
+            }
+            LogicExpr::Compare(_, lhs, rhs) => {
+                self.supporting_fluents(lhs, bindings, out);
+                self.supporting_fluents(rhs, bindings, out);
+            }
+            LogicExpr::And(lhs, rhs) => {
+                self.supporting_facts(lhs, bindings, out);
+                self.supporting_facts(rhs, bindings, out);
+            }
+            LogicExpr::Or(lhs, rhs) => {
+                if self.evaluate(lhs, bindings) {
+                    self.supporting_facts(lhs, bindings, out);
+                } else {
+                    self.supporting_facts(rhs, bindings, out);
+                }
+            }
+        }
+    }
+
+    fn supporting_fluents(&self, expr: &NumericExpr, bindings: &Bindings, out: &mut Vec<Fact>) {
+        match expr {
+            NumericExpr::Number(_) => {}
+            NumericExpr::Fluent(signature, symbols) => {
+                if let Some(arguments) = self.resolve_all(symbols, bindings) {
+                    out.push(Fact::Fluent(signature.clone(), arguments));
+                }
+            }
+            NumericExpr::Add(lhs, rhs)
+            | NumericExpr::Sub(lhs, rhs)
+            | NumericExpr::Mul(lhs, rhs) => {
+                self.supporting_fluents(lhs, bindings, out);
+                self.supporting_fluents(rhs, bindings, out);
+            }
+        }
+    }
+
+    /// The facts that hold in this state but not in `previous`, including facts that were
+    /// removed and fluents whose value changed.
+    pub fn changed_facts(&self, previous: &StoryState) -> HashSet<Fact> {
+        let added = self
+            .facts()
+            .filter(|(signature, arguments)| !previous.holds(signature, arguments))
+            .map(|(signature, arguments)| Fact::Predicate(signature.clone(), arguments.clone()));
+        let removed = previous
+            .facts()
+            .filter(|(signature, arguments)| !self.holds(signature, arguments))
+            .map(|(signature, arguments)| Fact::Absent(signature.clone(), arguments.clone()));
+        let updated = self
+            .fluent_values()
+            .filter(|(signature, arguments, value)| {
+                previous.fluent_value(signature, arguments) != *value
+            })
+            .map(|(signature, arguments, _)| Fact::Fluent(signature.clone(), arguments.clone()));
+        let learned = self
+            .beliefs()
+            .filter(|(character, signature, arguments, value)| {
+                previous.belief(*character, signature, arguments) != Some(*value)
+            })
+            .map(|(character, signature, arguments, value)| {
+                Fact::Belief(character, signature.clone(), arguments.clone(), value)
             });
-            Atom(self.atoms.len() - 1)
+
+        added.chain(removed).chain(updated).chain(learned).collect()
+    }
+
+    /// Evaluates a numeric expression in this state. Returns `None` if it refers to unknown
+    /// symbols.
+    pub fn evaluate_numeric(&self, expr: &NumericExpr, bindings: &Bindings) -> Option<i64> {
+        match expr {
+            NumericExpr::Number(value) => Some(*value),
+            NumericExpr::Fluent(signature, symbols) => self
+                .resolve_all(symbols, bindings)
+                .map(|arguments| self.fluent_value(signature, &arguments)),
+            NumericExpr::Add(lhs, rhs) => Some(
+                self.evaluate_numeric(lhs, bindings)?
+                    .saturating_add(self.evaluate_numeric(rhs, bindings)?),
+            ),
+            NumericExpr::Sub(lhs, rhs) => Some(
+                self.evaluate_numeric(lhs, bindings)?
+                    .saturating_sub(self.evaluate_numeric(rhs, bindings)?),
+            ),
+            NumericExpr::Mul(lhs, rhs) => Some(
+                self.evaluate_numeric(lhs, bindings)?
+                    .saturating_mul(self.evaluate_numeric(rhs, bindings)?),
+            ),
+        }
+    }
+
+    /// Applies an effect: predicates become true, negated predicates become false and fluents are
+    /// updated. Deletions are applied before additions, so an effect may delete and re-add the
+    /// same fact. Fluent updates are computed from the values before the effect, but several
+    /// updates of the same fluent add up. Beliefs are updated last, so characters observe the
+    /// world as the effect leaves it. Disjunctions have no well-defined effect and are ignored.
+    pub fn apply_effect(&mut self, effect: &LogicExpr, bindings: &Bindings) {
+        self.apply_effect_deriving(effect, bindings, |_| {});
+    }
+
+    /// Applies an effect like `apply_effect`, calling `derive` on the changed world before
+    /// beliefs are updated, so characters observe derived facts as the effect leaves them.
+    pub fn apply_effect_deriving(
+        &mut self,
+        effect: &LogicExpr,
+        bindings: &Bindings,
+        derive: impl FnOnce(&mut StoryState),
+    ) {
+        let mut changes = EffectChanges::default();
+        self.collect_effect(effect, bindings, true, &mut changes);
+
+        for (signature, arguments) in changes.deletions {
+            self.set_fact(signature, arguments, false);
+        }
+        for (signature, arguments) in changes.additions {
+            self.set_fact(signature, arguments, true);
+        }
+        for (kind, signature, arguments, value) in changes.updates {
+            let current = self.fluent_value(signature, &arguments);
+            self.set_fluent(signature, arguments, kind.apply(current, value));
+        }
+        if changes.beliefs.iter().any(|(.., value)| value.is_none()) {
+            derive(self);
+        }
+        for (character, signature, arguments, value) in changes.beliefs {
+            let value = value.unwrap_or_else(|| self.holds(signature, &arguments));
+            self.set_belief(character, signature, arguments, value);
+        }
+    }
+
+    fn collect_effect<'a>(
+        &self,
+        effect: &'a LogicExpr,
+        bindings: &Bindings,
+        positive: bool,
+        changes: &mut EffectChanges<'a>,
+    ) {
+        match effect {
+            LogicExpr::True
+            | LogicExpr::Or(_, _)
+            | LogicExpr::Compare(..)
+            | LogicExpr::Believes(..) => {}
+            LogicExpr::Learn(kind, character, beliefs) => {
+                if let Some(character) = self.resolve(character, bindings)
+                    && positive
+                {
+                    beliefs.for_each_literal(&mut |signature, symbols, value| {
+                        if let Some(arguments) = self.resolve_all(symbols, bindings) {
+                            let value = match kind {
+                                BeliefUpdate::Observe => None,
+                                BeliefUpdate::Inform => Some(value),
+                            };
+                            changes
+                                .beliefs
+                                .push((character, signature, arguments, value));
+                        }
+                    });
+                }
+            }
+            LogicExpr::Predicate(signature, symbols) => {
+                if let Some(arguments) = self.resolve_all(symbols, bindings) {
+                    if positive {
+                        changes.additions.push((signature, arguments));
+                    } else {
+                        changes.deletions.push((signature, arguments));
+                    }
+                }
+            }
+            LogicExpr::Update(kind, signature, symbols, value) => {
+                if let Some(arguments) = self.resolve_all(symbols, bindings)
+                    && let Some(value) = self.evaluate_numeric(value, bindings)
+                    && positive
+                {
+                    changes.updates.push((*kind, signature, arguments, value));
+                }
+            }
+            LogicExpr::Not(expr) => self.collect_effect(expr, bindings, !positive, changes),
+            LogicExpr::And(lhs, rhs) => {
+                self.collect_effect(lhs, bindings, positive, changes);
+                self.collect_effect(rhs, bindings, positive, changes);
+            }
+        }
+    }
+}
+
+/// Something a condition can rely on in a story state.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Fact {
+    /// The predicate holds for the atoms.
+    Predicate(PredicateSignature, Vec<Atom>),
+    /// The predicate does not hold for the atoms.
+    Absent(PredicateSignature, Vec<Atom>),
+    /// The value of the fluent for the atoms.
+    Fluent(PredicateSignature, Vec<Atom>),
+    /// The character believes the predicate to be true or false for the atoms.
+    Belief(Atom, PredicateSignature, Vec<Atom>, bool),
+}
+
+/// The changes made by an effect, collected before any of them is applied.
+#[derive(Default)]
+struct EffectChanges<'a> {
+    additions: Vec<(&'a PredicateSignature, Vec<Atom>)>,
+    deletions: Vec<(&'a PredicateSignature, Vec<Atom>)>,
+    updates: Vec<(FluentUpdate, &'a PredicateSignature, Vec<Atom>, i64)>,
+    /// The new beliefs of characters, or `None` if they observe whether the fact holds.
+    beliefs: Vec<(Atom, &'a PredicateSignature, Vec<Atom>, Option<bool>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    pub const ALL: [Comparison; 5] = [
+        Comparison::Less,
+        Comparison::LessOrEqual,
+        Comparison::Equal,
+        Comparison::GreaterOrEqual,
+        Comparison::Greater,
+    ];
+
+    pub fn holds(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            Comparison::Less => lhs < rhs,
+            Comparison::LessOrEqual => lhs <= rhs,
+            Comparison::Equal => lhs == rhs,
+            Comparison::GreaterOrEqual => lhs >= rhs,
+            Comparison::Greater => lhs > rhs,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// How an effect changes the value of a fluent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FluentUpdate {
+    Assign,
+    Increase,
+    Decrease,
+}
+
+impl FluentUpdate {
+    pub const ALL: [FluentUpdate; 3] = [
+        FluentUpdate::Assign,
+        FluentUpdate::Increase,
+        FluentUpdate::Decrease,
+    ];
+
+    pub fn apply(self, current: i64, value: i64) -> i64 {
+        match self {
+            FluentUpdate::Assign => value,
+            FluentUpdate::Increase => current.saturating_add(value),
+            FluentUpdate::Decrease => current.saturating_sub(value),
         }
     }
 
-    pub fn get_atom_name(&mut self, atom: Atom) -> Option<&str> {
-        self.atoms.get(atom.0).map(|x| x.name.as_str())
+    pub fn keyword(self) -> &'static str {
+        match self {
+            FluentUpdate::Assign => "assign",
+            FluentUpdate::Increase => "increase",
+            FluentUpdate::Decrease => "decrease",
+        }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// How an effect changes what a character believes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BeliefUpdate {
+    /// The character finds out whether each predicate holds.
+    Observe,
+    /// The character is told the literals, whether or not they hold.
+    Inform,
+}
+
+impl BeliefUpdate {
+    pub const ALL: [BeliefUpdate; 2] = [BeliefUpdate::Observe, BeliefUpdate::Inform];
+
+    pub fn keyword(self) -> &'static str {
+        match self {
+            BeliefUpdate::Observe => "observe",
+            BeliefUpdate::Inform => "inform",
+        }
+    }
+}
+
+/// An integer expression over numeric fluents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NumericExpr {
+    Number(i64),
+    /// The value of the fluent for the given variables or constants.
+    Fluent(PredicateSignature, Vec<Symbol>),
+    /// x + y
+    Add(Box<NumericExpr>, Box<NumericExpr>),
+    /// x - y
+    Sub(Box<NumericExpr>, Box<NumericExpr>),
+    /// x * y
+    Mul(Box<NumericExpr>, Box<NumericExpr>),
+}
+
+impl Default for NumericExpr {
+    fn default() -> Self {
+        NumericExpr::Number(0)
+    }
+}
+
+impl NumericExpr {
+    /// Calls `f` with the signature and arguments of every fluent in the expression.
+    pub fn for_each_fluent_mut(
+        &mut self,
+        f: &mut impl FnMut(&mut PredicateSignature, &mut Vec<Symbol>),
+    ) {
+        match self {
+            NumericExpr::Number(_) => {}
+            NumericExpr::Fluent(signature, arguments) => f(signature, arguments),
+            NumericExpr::Add(lhs, rhs)
+            | NumericExpr::Sub(lhs, rhs)
+            | NumericExpr::Mul(lhs, rhs) => {
+                lhs.for_each_fluent_mut(f);
+                rhs.for_each_fluent_mut(f);
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            NumericExpr::Add(_, _) | NumericExpr::Sub(_, _) => 1,
+            NumericExpr::Mul(_, _) => 2,
+            NumericExpr::Number(_) | NumericExpr::Fluent(_, _) => 3,
+        }
+    }
+
+    fn fmt_with_precedence(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        min_precedence: u8,
+    ) -> std::fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "(")?;
+            self.fmt_with_precedence(f, 0)?;
+            return write!(f, ")");
+        }
+
+        let (lhs, operator, rhs) = match self {
+            NumericExpr::Number(value) => return write!(f, "{value}"),
+            NumericExpr::Fluent(signature, symbols) => {
+                return write_term(f, &signature.function, symbols);
+            }
+            NumericExpr::Add(lhs, rhs) => (lhs, "+", rhs),
+            NumericExpr::Sub(lhs, rhs) => (lhs, "-", rhs),
+            NumericExpr::Mul(lhs, rhs) => (lhs, "*", rhs),
+        };
+
+        let precedence = self.precedence();
+        lhs.fmt_with_precedence(f, precedence)?;
+        write!(f, " {operator} ")?;
+        rhs.fmt_with_precedence(f, precedence + 1)
+    }
+}
+
+impl Display for NumericExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_precedence(f, 0)
+    }
+}
+
+/// Writes `function(arg1, arg2)`.
+fn write_term(
+    f: &mut std::fmt::Formatter<'_>,
+    function: &Symbol,
+    symbols: &[Symbol],
+) -> std::fmt::Result {
+    let symbols: Vec<&str> = symbols.iter().map(|symbol| symbol.as_str()).collect();
+    write!(f, "{}({})", function.0, symbols.join(", "))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum LogicExpr {
     #[default]
     /// Always true
@@ -120,24 +1034,420 @@ pub enum LogicExpr {
     And(Box<LogicExpr>, Box<LogicExpr>),
     /// x ∨ y
     Or(Box<LogicExpr>, Box<LogicExpr>),
+    /// Compares two numeric expressions, e.g. `health(?c) > 0`.
+    Compare(Comparison, NumericExpr, NumericExpr),
+    /// Changes the value of the fluent for the given variables or constants, e.g.
+    /// `decrease(gold(?c), 5)`. Only meaningful in effects.
+    Update(FluentUpdate, PredicateSignature, Vec<Symbol>, NumericExpr),
+    /// The character believes the expression holds, e.g. `believes(?c, not at(?x, ?l))`.
+    /// Predicates must be believed true and negated predicates believed false.
+    Believes(Symbol, Box<LogicExpr>),
+    /// Changes what the character believes about the literals of the expression, e.g.
+    /// `inform(?c, at(?x, ?l))`. Only meaningful in effects.
+    Learn(BeliefUpdate, Symbol, Box<LogicExpr>),
 }
 
-#[derive(Debug, Default, Clone)]
+impl LogicExpr {
+    /// Calls `f` with the signature and arguments of every predicate in the expression.
+    pub fn for_each_predicate_mut(
+        &mut self,
+        f: &mut impl FnMut(&mut PredicateSignature, &mut Vec<Symbol>),
+    ) {
+        match self {
+            LogicExpr::True | LogicExpr::Compare(..) | LogicExpr::Update(..) => {}
+            LogicExpr::Predicate(signature, arguments) => f(signature, arguments),
+            LogicExpr::Not(expr) | LogicExpr::Believes(_, expr) | LogicExpr::Learn(_, _, expr) => {
+                expr.for_each_predicate_mut(f)
+            }
+            LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+                lhs.for_each_predicate_mut(f);
+                rhs.for_each_predicate_mut(f);
+            }
+        }
+    }
+
+    /// Calls `f` with the signature and arguments of every fluent in the expression, including
+    /// updated fluents.
+    pub fn for_each_fluent_mut(
+        &mut self,
+        f: &mut impl FnMut(&mut PredicateSignature, &mut Vec<Symbol>),
+    ) {
+        match self {
+            LogicExpr::True
+            | LogicExpr::Predicate(_, _)
+            | LogicExpr::Believes(..)
+            | LogicExpr::Learn(..) => {}
+            LogicExpr::Compare(_, lhs, rhs) => {
+                lhs.for_each_fluent_mut(f);
+                rhs.for_each_fluent_mut(f);
+            }
+            LogicExpr::Update(_, signature, arguments, value) => {
+                f(signature, arguments);
+                value.for_each_fluent_mut(f);
+            }
+            LogicExpr::Not(expr) => expr.for_each_fluent_mut(f),
+            LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+                lhs.for_each_fluent_mut(f);
+                rhs.for_each_fluent_mut(f);
+            }
+        }
+    }
+
+    /// Calls `f` with every character whose beliefs the expression tests or changes.
+    pub fn for_each_character_mut(&mut self, f: &mut impl FnMut(&mut Symbol)) {
+        match self {
+            LogicExpr::True
+            | LogicExpr::Predicate(_, _)
+            | LogicExpr::Compare(..)
+            | LogicExpr::Update(..) => {}
+            LogicExpr::Believes(character, expr) | LogicExpr::Learn(_, character, expr) => {
+                f(character);
+                expr.for_each_character_mut(f);
+            }
+            LogicExpr::Not(expr) => expr.for_each_character_mut(f),
+            LogicExpr::And(lhs, rhs) | LogicExpr::Or(lhs, rhs) => {
+                lhs.for_each_character_mut(f);
+                rhs.for_each_character_mut(f);
+            }
+        }
+    }
+
+    /// Calls `f` with every predicate of a conjunction of literals, along with whether it is
+    /// asserted or negated. Other parts of the expression are skipped.
+    pub fn for_each_literal<'a>(
+        &'a self,
+        f: &mut impl FnMut(&'a PredicateSignature, &'a [Symbol], bool),
+    ) {
+        match self {
+            LogicExpr::Predicate(signature, symbols) => f(signature, symbols, true),
+            LogicExpr::Not(inner) => {
+                if let LogicExpr::Predicate(signature, symbols) = &**inner {
+                    f(signature, symbols, false);
+                }
+            }
+            LogicExpr::And(lhs, rhs) => {
+                lhs.for_each_literal(f);
+                rhs.for_each_literal(f);
+            }
+            _ => {}
+        }
+    }
+
+    /// Binding strength of the outermost operator, used to decide where parentheses are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            LogicExpr::Or(_, _) => 1,
+            LogicExpr::And(_, _) => 2,
+            LogicExpr::Not(_) => 3,
+            LogicExpr::True
+            | LogicExpr::Predicate(_, _)
+            | LogicExpr::Compare(..)
+            | LogicExpr::Update(..)
+            | LogicExpr::Believes(..)
+            | LogicExpr::Learn(..) => 4,
+        }
+    }
+
+    fn fmt_with_precedence(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        min_precedence: u8,
+    ) -> std::fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "(")?;
+            self.fmt_with_precedence(f, 0)?;
+            return write!(f, ")");
+        }
+
+        match self {
+            LogicExpr::True => write!(f, "true"),
+            LogicExpr::Predicate(signature, symbols) => write_term(f, &signature.function, symbols),
+            LogicExpr::Compare(comparison, lhs, rhs) => {
+                write!(f, "{lhs} {} {rhs}", comparison.symbol())
+            }
+            LogicExpr::Update(kind, signature, symbols, value) => {
+                write!(f, "{}(", kind.keyword())?;
+                write_term(f, &signature.function, symbols)?;
+                write!(f, ", {value})")
+            }
+            LogicExpr::Believes(character, expr) => write!(f, "believes({}, {expr})", character.0),
+            LogicExpr::Learn(kind, character, expr) => {
+                write!(f, "{}({}, {expr})", kind.keyword(), character.0)
+            }
+            LogicExpr::Not(expr) => {
+                write!(f, "not ")?;
+                expr.fmt_with_precedence(f, 3)
+            }
+            LogicExpr::And(lhs, rhs) => {
+                lhs.fmt_with_precedence(f, 2)?;
+                write!(f, " and ")?;
+                rhs.fmt_with_precedence(f, 3)
+            }
+            LogicExpr::Or(lhs, rhs) => {
+                lhs.fmt_with_precedence(f, 1)?;
+                write!(f, " or ")?;
+                rhs.fmt_with_precedence(f, 2)
+            }
+        }
+    }
+}
+
+/// Prints the expression in the syntax accepted by `logic_parser::parse_logic_expr`, with as few
+/// parentheses as possible.
+impl Display for LogicExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_precedence(f, 0)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Action {
     pub parameters: Vec<TypedSymbol>,
+    /// The parameters that are characters consenting to the action. Each of them must have a
+    /// reason to take part, see `PredicateDomain::unexplained_steps`.
+    #[serde(default)]
+    pub agents: Vec<Symbol>,
     pub precondition: LogicExpr,
     pub effect: LogicExpr,
 }
 
-#[derive(Debug, Default, Clone)]
+impl Action {
+    /// Binds the parameters of the action to the given arguments, in order.
+    pub fn bind(&self, arguments: &[Atom]) -> Bindings {
+        self.parameters
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(arguments.iter().copied())
+            .collect()
+    }
+}
+
+/// The definition of a derived predicate. The predicate holds for the atoms bound to its
+/// variables whenever the body holds for some atoms bound to the other variables of the body.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Axiom {
+    pub variables: Vec<TypedSymbol>,
+    pub body: LogicExpr,
+}
+
+/// A task achieved by performing the subtasks of one of its methods, e.g.
+/// `rescue(?hero, ?victim)`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompoundTask {
+    pub parameters: Vec<TypedSymbol>,
+    pub methods: Vec<Method>,
+}
+
+/// One way of performing a compound task. Variables of the precondition and subtasks that are not
+/// parameters of the task are bound to whichever atoms satisfy the precondition.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Method {
+    pub name: String,
+    pub precondition: LogicExpr,
+    /// Actions and compound tasks, performed in order.
+    pub subtasks: Vec<TaskCall>,
+}
+
+/// A call of an action or compound task, e.g. `rescue(?hero, princess)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskCall {
+    pub name: Symbol,
+    pub arguments: Vec<Symbol>,
+}
+
+impl From<StoryStep> for TaskCall {
+    fn from(step: StoryStep) -> Self {
+        Self {
+            name: step.action,
+            arguments: step.arguments,
+        }
+    }
+}
+
+impl Display for TaskCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_term(f, &self.name, &self.arguments)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PredicateProblem {
+    /// Distinguishes the problems of a domain, e.g. the scenario of a class.
+    pub name: String,
     /// The maximum number of actions allowed in a story sequence.
     pub max_story_length: u32,
+    /// The minimum number of actions in a story sequence.
+    #[serde(default)]
+    pub min_story_length: u32,
     pub objects: Vec<TypedSymbol>,
     pub initial_state: InitialState,
+    /// Must hold at the end of the story. `LogicExpr::True` means the story has no goal and runs
+    /// for `max_story_length` steps.
+    pub goal: LogicExpr,
+    /// The goals characters pursue on their own. A character may have several.
+    #[serde(default)]
+    pub character_goals: Vec<(Symbol, LogicExpr)>,
+    /// Conditions on the whole course of the story, which must hold along with the goal.
+    #[serde(default)]
+    pub constraints: Vec<Constraint>,
+    /// The compound tasks and actions the story is made of, in order. Stories of problems with an
+    /// outline are found by decomposing it rather than by improvising.
+    #[serde(default)]
+    pub outline: Vec<TaskCall>,
+    /// How tense each state is and the arc generated stories should follow.
+    #[serde(default)]
+    pub tension: Tension,
+}
+
+impl PredicateProblem {
+    pub fn has_goal(&self) -> bool {
+        !matches!(self.goal, LogicExpr::True)
+    }
+
+    pub fn has_goals(&self, character: &Symbol) -> bool {
+        self.character_goals
+            .iter()
+            .any(|(name, _)| name == character)
+    }
+}
+
+/// A single grounded action in a story, e.g. `move(alice, forest, castle)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StoryStep {
+    pub action: Symbol,
+    pub arguments: Vec<Symbol>,
+}
+
+impl Display for StoryStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arguments: Vec<&str> = self.arguments.iter().map(|arg| arg.as_str()).collect();
+        write!(f, "{}({})", self.action.0, arguments.join(", "))
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct PredicateStory {
     pub text: String,
+    pub steps: Vec<StoryStep>,
+    /// The state before the first step followed by the state after each step.
+    pub states: Vec<StoryState>,
+    /// The partial-order plan the story was ordered from, if it was planned that way.
+    pub plan: Option<PartialOrderPlan>,
+}
+
+impl PredicateStory {
+    pub fn new(steps: Vec<StoryStep>, states: Vec<StoryState>) -> Self {
+        let text = steps.iter().map(|step| format!("{step}\n")).collect();
+
+        Self {
+            text,
+            steps,
+            states,
+            plan: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        test_util::{add_actions, domain_with, problem_with, step, sym},
+        validator::StoryError,
+    };
+
+    #[test]
+    fn improvises_stories_that_reach_the_goal() {
+        let domain = domain_with(
+            &[("has-key", 1), ("open", 1)],
+            &[
+                ("take-key", "not has-key(?c)", "has-key(?c)"),
+                ("open-door", "has-key(?c) and not open(?c)", "open(?c)"),
+            ],
+        );
+        let mut problem = problem_with(&domain, &["alice"], "open(alice)");
+        problem.max_story_length = 2;
+
+        let mut stories = Vec::new();
+        domain.generate_stories(&problem, 1, &Progress::default(), &mut |story| {
+            stories.push(story.text);
+            true
+        });
+        assert_eq!(stories, ["take-key(alice)\nopen-door(alice)\n"]);
+
+        // Without a goal, the walk runs until no action applies.
+        problem.goal = parse_logic_expr(&domain, "true").unwrap();
+        problem.max_story_length = 5;
+        let story = domain.improvise_story(&problem).unwrap();
+        assert_eq!(story.steps.len(), 2);
+        assert_eq!(story.states.len(), 3);
+    }
+
+    #[test]
+    fn skips_initial_facts_about_unknown_objects() {
+        let domain = domain_with(&[("at", 1)], &[]);
+        let mut problem = problem_with(&domain, &["alice"], "true");
+        let at = PredicateSignature::new(sym("at"), 1);
+        for name in ["alice", "alcie"] {
+            problem
+                .initial_state
+                .bound_predicates
+                .push((at.clone(), vec![sym(name)]));
+        }
+
+        assert_eq!(domain.unknown_initial_symbols(&problem), [sym("alcie")]);
+        let state = domain.initial_story_state(&problem);
+        assert!(state.get_atom(&sym("alcie")).is_none());
+        assert_eq!(state.facts().count(), 1);
+        assert_eq!(
+            domain.validate_story(&problem, Vec::new()).unwrap_err(),
+            StoryError::UnknownInitialSymbols {
+                symbols: vec![sym("alcie")],
+            }
+        );
+    }
+
+    #[test]
+    fn believes_nothing_about_unknown_facts_under_negation() {
+        let domain = domain_with(&[("armed", 0), ("angry", 0)], &[]);
+        let mut problem = problem_with(&domain, &["alice"], "true");
+        let told = parse_logic_expr(&domain, "angry()").unwrap();
+        problem.initial_state.beliefs.push((sym("alice"), told));
+        let state = domain.initial_story_state(&problem);
+
+        // Alice does not know whether the guard is armed.
+        for (belief, holds) in [
+            ("not (armed() and angry())", false),
+            ("not (armed() or not angry())", false),
+            ("not (armed() and not angry())", true),
+            ("not not angry()", true),
+        ] {
+            let expr = parse_logic_expr(&domain, &format!("believes(alice, {belief})")).unwrap();
+            assert_eq!(state.evaluate(&expr, &Bindings::new()), holds, "{belief}");
+        }
+    }
+
+    #[test]
+    fn observes_derived_facts_after_the_effect() {
+        let mut domain = domain_with(&[("torch", 0)], &[]);
+        let lit = PredicateSignature::new(sym("lit"), 0);
+        let axiom = Axiom {
+            variables: Vec::new(),
+            body: parse_logic_expr(&domain, "torch()").unwrap(),
+        };
+        domain.axioms.insert(lit.clone(), axiom);
+        add_actions(
+            &mut domain,
+            &[("light", "true", "torch() and observe(?c, lit())")],
+        );
+
+        let problem = problem_with(&domain, &["alice"], "true");
+        let state = domain.initial_story_state(&problem);
+        let state = domain
+            .apply_step(&state, &step("light", &["alice"]))
+            .unwrap();
+        let alice = state.get_atom(&sym("alice")).unwrap();
+        assert_eq!(state.belief(alice, &lit, &[]), Some(true));
+    }
 }
@@ -0,0 +1,328 @@
+use std::fmt::Write;
+
+use crate::{
+    partial_order::{CausalLink, PlanNode},
+    story::{Bindings, PredicateDomain, PredicateProblem, PredicateStory},
+};
+
+/// Size of a node when the graph is drawn, in pixels.
+pub const NODE_SIZE: [f32; 2] = [180., 36.];
+/// Space between the nodes of neighbouring columns and rows when the graph is drawn, in pixels.
+pub const NODE_SPACING: [f32; 2] = [90., 30.];
+
+/// Why one node of a story graph comes before another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The first node establishes the fact with the given label, which the second relies on.
+    Causal(String),
+    /// The first step has to happen before the second, e.g. so it does not undo a fact the
+    /// second relies on.
+    Ordering,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// The steps of a story as nodes, between a start and a goal node, with edges for the causal and
+/// ordering dependencies between them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoryGraph {
+    /// Labels of the start node, then of each step, then of the goal node.
+    pub nodes: Vec<String>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl StoryGraph {
+    /// The index of a node of the story's plan.
+    pub fn node_index(&self, node: PlanNode) -> usize {
+        match node {
+            PlanNode::Start => 0,
+            PlanNode::Step(step) => step + 1,
+            PlanNode::Goal => self.nodes.len() - 1,
+        }
+    }
+
+    /// The column and row of each node, with every node in a column after those it depends on.
+    pub fn layout(&self) -> Vec<[usize; 2]> {
+        // Edges always point to later steps, so one pass in order finds the longest paths.
+        let mut columns = vec![0; self.nodes.len()];
+        for node in 1..self.nodes.len() {
+            columns[node] = self
+                .edges
+                .iter()
+                .filter(|edge| edge.to == node)
+                .map(|edge| columns[edge.from] + 1)
+                .max()
+                .unwrap_or(1);
+        }
+        if let Some(goal) = columns.len().checked_sub(1) {
+            columns[goal] = columns[..goal].iter().copied().max().unwrap_or_default() + 1;
+        }
+
+        let mut rows = Vec::new();
+        columns
+            .iter()
+            .map(|column| {
+                if rows.len() <= *column {
+                    rows.resize(column + 1, 0);
+                }
+                rows[*column] += 1;
+                [*column, rows[*column] - 1]
+            })
+            .collect()
+    }
+
+    /// Formats the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph story {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for (i, label) in self.nodes.iter().enumerate() {
+            let _ = writeln!(dot, "    n{i} [label=\"{}\"];", escape_dot(label));
+        }
+        for edge in &self.edges {
+            let _ = match &edge.kind {
+                EdgeKind::Causal(label) => writeln!(
+                    dot,
+                    "    n{} -> n{} [label=\"{}\"];",
+                    edge.from,
+                    edge.to,
+                    escape_dot(label)
+                ),
+                EdgeKind::Ordering => {
+                    writeln!(dot, "    n{} -> n{} [style=dashed];", edge.from, edge.to)
+                }
+            };
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Draws the graph as an SVG image, laid out with `layout`.
+    pub fn to_svg(&self) -> String {
+        let layout = self.layout();
+        let position = |node: usize| {
+            let [column, row] = layout[node];
+            [
+                20. + column as f32 * (NODE_SIZE[0] + NODE_SPACING[0]),
+                20. + row as f32 * (NODE_SIZE[1] + NODE_SPACING[1]),
+            ]
+        };
+        let columns = layout
+            .iter()
+            .map(|[column, _]| column + 1)
+            .max()
+            .unwrap_or(1);
+        let rows = layout.iter().map(|[_, row]| row + 1).max().unwrap_or(1);
+        let width = 40. + columns as f32 * (NODE_SIZE[0] + NODE_SPACING[0]) - NODE_SPACING[0];
+        let height = 40. + rows as f32 * (NODE_SIZE[1] + NODE_SPACING[1]) - NODE_SPACING[1];
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             font-family=\"sans-serif\" font-size=\"12\">"
+        );
+        svg.push_str(
+            "  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\
+             <path d=\"M0,0 L10,5 L0,10 z\"/></marker></defs>\n",
+        );
+
+        for edge in &self.edges {
+            let [x1, y1] = position(edge.from);
+            let [x2, y2] = position(edge.to);
+            let (x1, y1) = (x1 + NODE_SIZE[0], y1 + NODE_SIZE[1] / 2.);
+            let y2 = y2 + NODE_SIZE[1] / 2.;
+
+            let (dash, label) = match &edge.kind {
+                EdgeKind::Causal(label) => ("", label.as_str()),
+                EdgeKind::Ordering => (" stroke-dasharray=\"4 4\"", ""),
+            };
+            let _ = writeln!(
+                svg,
+                "  <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"black\"{dash} \
+                 marker-end=\"url(#arrow)\"/>"
+            );
+            if !label.is_empty() {
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"#555\">{}</text>",
+                    (x1 + x2) / 2.,
+                    (y1 + y2) / 2. - 4.,
+                    escape_xml(label)
+                );
+            }
+        }
+
+        for (i, label) in self.nodes.iter().enumerate() {
+            let [x, y] = position(i);
+            let _ = writeln!(
+                svg,
+                "  <rect x=\"{x}\" y=\"{y}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"white\" \
+                 stroke=\"black\"/>",
+                NODE_SIZE[0], NODE_SIZE[1]
+            );
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x + NODE_SIZE[0] / 2.,
+                y + NODE_SIZE[1] / 2. + 4.,
+                escape_xml(label)
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl PredicateDomain {
+    /// The causal links of the story: those of its plan if it was planned, otherwise from each
+    /// fact a step or the goal relies on to the last step that established it.
+    pub fn causal_links(
+        &self,
+        problem: &PredicateProblem,
+        story: &PredicateStory,
+    ) -> Vec<CausalLink> {
+        if let Some(plan) = &story.plan {
+            return plan.causal_links.clone();
+        }
+
+        let produced: Vec<_> = story
+            .states
+            .windows(2)
+            .map(|states| states[1].changed_facts(&states[0]))
+            .collect();
+        let producer = |fact: &_, before: usize| {
+            (0..before)
+                .rev()
+                .find(|i| produced[*i].contains(fact))
+                .map_or(PlanNode::Start, PlanNode::Step)
+        };
+
+        let mut links = Vec::new();
+        for (i, (step, state)) in story.steps.iter().zip(&story.states).enumerate() {
+            for fact in self.relied_on_facts(step, state) {
+                links.push(CausalLink {
+                    producer: producer(&fact, i),
+                    consumer: PlanNode::Step(i),
+                    fact,
+                });
+            }
+        }
+
+        if let Some(last) = story.states.last() {
+            let mut facts = Vec::new();
+            last.supporting_facts(&problem.goal, &Bindings::new(), &mut facts);
+            for fact in facts {
+                links.push(CausalLink {
+                    producer: producer(&fact, story.steps.len()),
+                    consumer: PlanNode::Goal,
+                    fact,
+                });
+            }
+        }
+
+        links
+    }
+
+    /// Builds the graph of the story's steps and the dependencies between them.
+    pub fn story_graph(&self, problem: &PredicateProblem, story: &PredicateStory) -> StoryGraph {
+        let mut graph = StoryGraph {
+            nodes: Vec::with_capacity(story.steps.len() + 2),
+            edges: Vec::new(),
+        };
+        graph.nodes.push("Start".into());
+        graph.nodes.extend(
+            story
+                .steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| format!("{}. {step}", i + 1)),
+        );
+        graph.nodes.push("Goal".into());
+
+        let Some(state) = story.states.first() else {
+            return graph;
+        };
+
+        for link in self.causal_links(problem, story) {
+            let edge = GraphEdge {
+                from: graph.node_index(link.producer),
+                to: graph.node_index(link.consumer),
+                kind: EdgeKind::Causal(link.fact_label(state)),
+            };
+            if !graph.edges.contains(&edge) {
+                graph.edges.push(edge);
+            }
+        }
+
+        // Orderings already implied by a causal link would only clutter the graph.
+        for (before, after) in story.plan.iter().flat_map(|plan| &plan.orderings) {
+            let (from, to) = (before + 1, after + 1);
+            if !graph
+                .edges
+                .iter()
+                .any(|edge| edge.from == from && edge.to == to)
+            {
+                graph.edges.push(GraphEdge {
+                    from,
+                    to,
+                    kind: EdgeKind::Ordering,
+                });
+            }
+        }
+
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{domain_with, problem_with, step};
+
+    #[test]
+    fn links_steps_of_an_improvised_story() {
+        let domain = domain_with(
+            &[("has-key", 1), ("open", 1)],
+            &[
+                ("take-key", "true", "has-key(?c)"),
+                ("open-door", "has-key(?c)", "open(?c)"),
+            ],
+        );
+        let problem = problem_with(&domain, &["alice"], "open(alice)");
+
+        let steps = vec![step("take-key", &["alice"]), step("open-door", &["alice"])];
+        let story = domain.validate_story(&problem, steps).unwrap();
+        let graph = domain.story_graph(&problem, &story);
+
+        assert_eq!(
+            graph.nodes,
+            ["Start", "1. take-key(alice)", "2. open-door(alice)", "Goal"]
+        );
+        assert_eq!(graph.layout(), [[0, 0], [1, 0], [2, 0], [3, 0]]);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph story {\n    rankdir=LR;\n    node [shape=box];\n    \
+             n0 [label=\"Start\"];\n    n1 [label=\"1. take-key(alice)\"];\n    \
+             n2 [label=\"2. open-door(alice)\"];\n    n3 [label=\"Goal\"];\n    \
+             n1 -> n2 [label=\"has-key(alice)\"];\n    n2 -> n3 [label=\"open(alice)\"];\n}\n"
+        );
+    }
+}
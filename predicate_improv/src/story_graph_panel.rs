@@ -0,0 +1,145 @@
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Shape, Stroke, Vec2};
+
+use crate::story_graph::{EdgeKind, NODE_SIZE, NODE_SPACING, StoryGraph};
+
+#[derive(Debug, Default)]
+pub struct StoryGraphPanel {
+    /// The outcome of the last export.
+    export_result: Option<Result<String, String>>,
+}
+
+impl StoryGraphPanel {
+    /// Shows the graph of the inspected story. Takes the parts of the app it needs because the
+    /// story stays borrowed by the inspector.
+    pub fn show(
+        ui: &mut egui::Ui,
+        panel: &mut StoryGraphPanel,
+        graph: &StoryGraph,
+        selected_step: &mut usize,
+    ) {
+        ui.horizontal(|ui| {
+            if ui.button("Export DOT").clicked() {
+                panel.export_result = export(&graph.to_dot(), "Graphviz", "dot");
+            }
+
+            if ui.button("Export SVG").clicked() {
+                panel.export_result = export(&graph.to_svg(), "SVG image", "svg");
+            }
+
+            match &panel.export_result {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::LIGHT_RED, err);
+                }
+                None => {}
+            }
+        });
+
+        egui::ScrollArea::both()
+            .id_salt("story_graph")
+            .max_height(ui.available_height() / 2.)
+            .show(ui, |ui| draw_graph(ui, graph, selected_step));
+    }
+}
+
+/// Asks where to save the file and saves it, returning `None` if the user cancelled.
+fn export(contents: &str, kind: &str, extension: &str) -> Option<Result<String, String>> {
+    let path = rfd::FileDialog::new()
+        .add_filter(kind, &[extension])
+        .set_file_name(format!("story.{extension}"))
+        .save_file()?;
+
+    Some(
+        std::fs::write(&path, contents)
+            .map(|()| format!("Saved {}", path.display()))
+            .map_err(|err| format!("Could not save {}: {err}", path.display())),
+    )
+}
+
+/// Draws the graph with the layout of the SVG export. Hovering a node highlights its edges and
+/// their facts, and clicking a step selects it in the timeline.
+fn draw_graph(ui: &mut egui::Ui, graph: &StoryGraph, selected_step: &mut usize) {
+    let layout = graph.layout();
+    let cell = Vec2::from(NODE_SIZE) + Vec2::from(NODE_SPACING);
+    let columns = layout
+        .iter()
+        .map(|[column, _]| column + 1)
+        .max()
+        .unwrap_or(1);
+    let rows = layout.iter().map(|[_, row]| row + 1).max().unwrap_or(1);
+    let size = Vec2::new(columns as f32, rows as f32) * cell - Vec2::from(NODE_SPACING);
+
+    let (response, painter) = ui.allocate_painter(size + Vec2::splat(40.), Sense::click());
+    let origin = response.rect.min + Vec2::splat(20.);
+    let node_rect = |node: usize| {
+        let [column, row] = layout[node];
+        Rect::from_min_size(
+            origin + Vec2::new(column as f32, row as f32) * cell,
+            Vec2::from(NODE_SIZE),
+        )
+    };
+    let hovered = response
+        .hover_pos()
+        .and_then(|pos| (0..graph.nodes.len()).find(|node| node_rect(*node).contains(pos)));
+
+    let visuals = ui.visuals();
+    let font = FontId::proportional(14.);
+
+    for edge in &graph.edges {
+        let highlighted = hovered.is_some_and(|node| node == edge.from || node == edge.to);
+        let color = if highlighted {
+            visuals.selection.stroke.color
+        } else {
+            visuals.weak_text_color()
+        };
+        let stroke = Stroke::new(if highlighted { 2. } else { 1. }, color);
+
+        let from = node_rect(edge.from).right_center();
+        let to = node_rect(edge.to).left_center();
+        let head = (to - from).normalized() * 8.;
+
+        match &edge.kind {
+            EdgeKind::Causal(label) => {
+                painter.line_segment([from, to - head], stroke);
+                if highlighted {
+                    let middle = Pos2::new((from.x + to.x) / 2., (from.y + to.y) / 2. - 4.);
+                    painter.text(middle, Align2::CENTER_BOTTOM, label, font.clone(), color);
+                }
+            }
+            EdgeKind::Ordering => {
+                painter.extend(Shape::dashed_line(&[from, to - head], stroke, 4., 4.));
+            }
+        }
+        painter.arrow(to - head, head, stroke);
+    }
+
+    for (node, label) in graph.nodes.iter().enumerate() {
+        let rect = node_rect(node);
+        let selected = node == *selected_step;
+        let fill = if selected {
+            visuals.selection.bg_fill
+        } else {
+            Color32::from_rgb(36, 36, 36)
+        };
+
+        painter.rect_filled(rect, 4., fill);
+        painter.rect_stroke(rect, 4., visuals.window_stroke(), egui::StrokeKind::Inside);
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            label,
+            font.clone(),
+            visuals.text_color(),
+        );
+    }
+
+    // The goal node has no state of its own to select.
+    if response.clicked()
+        && let Some(node) = hovered
+        && node + 1 < graph.nodes.len()
+    {
+        *selected_step = node;
+    }
+}
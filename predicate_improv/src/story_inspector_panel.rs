@@ -0,0 +1,359 @@
+use eframe::egui::{self, Color32, Frame, Margin, RichText, Sense, Shape, Stroke, Vec2};
+
+use crate::{
+    app::PredicateImprovApp,
+    partial_order::{CausalLink, PlanNode},
+    story::{PredicateStory, StoryState},
+    story_graph_panel::StoryGraphPanel,
+    tension::{Tension, normalized},
+};
+
+#[derive(Debug, Default)]
+pub struct StoryInspectorPanel {
+    /// Index into `PredicateStory::states`; 0 is the state before the first step.
+    pub selected_step: usize,
+    view: StoryView,
+}
+
+/// How the inspector shows the story.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum StoryView {
+    #[default]
+    Timeline,
+    /// The steps as a graph of their dependencies.
+    Graph,
+    /// The stories found by the last generation side by side.
+    Compare,
+}
+
+impl StoryInspectorPanel {
+    pub fn show(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+        let Some(story) = &app.story else {
+            ui.label("No story was generated.");
+            return;
+        };
+
+        ui.spacing_mut().item_spacing = [6., 12.].into();
+
+        let view = &mut app.story_inspector_panel.view;
+        let can_compare = app.stories.len() > 1;
+        if *view == StoryView::Compare && can_compare {
+            view_tabs(ui, view, can_compare);
+            if let Some(selected) = compare(ui, app) {
+                app.select_story(selected);
+            }
+            return;
+        }
+
+        let problem = &app.problems[app.selected_problem];
+        ui.vertical(|ui| {
+            ui.spacing_mut().item_spacing.y = 4.;
+            ui.label(&story.text);
+            let score = app.domain.score_story(problem, story, &app.ranking);
+            ui.label(RichText::new(score.to_string()).weak());
+        });
+
+        let selected_step = &mut app.story_inspector_panel.selected_step;
+        *selected_step = (*selected_step).min(story.states.len().saturating_sub(1));
+
+        if !problem.tension.is_empty()
+            && let Some(step) = tension_plot(ui, &problem.tension, story, *selected_step)
+        {
+            *selected_step = step;
+        }
+        view_tabs(ui, view, can_compare);
+
+        if *view == StoryView::Graph {
+            let graph = app.domain.story_graph(problem, story);
+            StoryGraphPanel::show(ui, &mut app.story_graph_panel, &graph, selected_step);
+        } else {
+            egui::ScrollArea::horizontal()
+                .id_salt("story_timeline")
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(*selected_step == 0, "Start").clicked() {
+                            *selected_step = 0;
+                        }
+
+                        for (i, step) in story.steps.iter().enumerate() {
+                            ui.label(egui_material_icons::icons::ICON_ARROW_FORWARD);
+
+                            if ui
+                                .selectable_label(
+                                    *selected_step == i + 1,
+                                    format!("{}. {step}", i + 1),
+                                )
+                                .clicked()
+                            {
+                                *selected_step = i + 1;
+                            }
+                        }
+                    });
+                });
+        }
+
+        let Some(state) = story.states.get(*selected_step) else {
+            return;
+        };
+
+        if let Some(step) = selected_step.checked_sub(1) {
+            causal_links(ui, &app.domain.causal_links(problem, story), step, state);
+        }
+        let previous = selected_step
+            .checked_sub(1)
+            .and_then(|step| story.states.get(step));
+
+        ui.label(RichText::new("State").text_style(egui::TextStyle::Name("Heading2".into())));
+
+        Frame::canvas(ui.style())
+            .inner_margin(Margin::same(8))
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("story_state")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.spacing_mut().item_spacing.y = 6.;
+
+                        for (label, change) in state_diff(state, previous) {
+                            let text =
+                                match change {
+                                    FactChange::Unchanged => RichText::new(label),
+                                    FactChange::Added => RichText::new(format!("+ {label}"))
+                                        .color(Color32::LIGHT_GREEN),
+                                    FactChange::Changed => RichText::new(format!("~ {label}"))
+                                        .color(Color32::LIGHT_YELLOW),
+                                    FactChange::Removed => RichText::new(format!("- {label}"))
+                                        .color(Color32::LIGHT_RED)
+                                        .strikethrough(),
+                                };
+
+                            Frame::new()
+                                .fill(Color32::from_rgb(36, 36, 36))
+                                .corner_radius(4.0)
+                                .inner_margin(Margin::same(4))
+                                .show(ui, |ui| {
+                                    ui.set_width(ui.available_width());
+                                    ui.label(text);
+                                });
+                        }
+                    });
+            });
+    }
+}
+
+/// Plots the tension of each state of the story over the target arc, both scaled to the height
+/// of the plot. Returns the state the user clicked.
+fn tension_plot(
+    ui: &mut egui::Ui,
+    tension: &Tension,
+    story: &PredicateStory,
+    selected_step: usize,
+) -> Option<usize> {
+    let curve = tension.curve(story);
+    let values: Vec<_> = curve.iter().map(i64::to_string).collect();
+    ui.label(
+        RichText::new(format!(
+            "Tension {} against a {} arc",
+            values.join(" → "),
+            tension.arc.to_string().to_lowercase()
+        ))
+        .weak(),
+    );
+
+    let size = Vec2::new(ui.available_width(), 80.);
+    let (response, painter) = ui.allocate_painter(size, Sense::click());
+    painter.rect_filled(response.rect, 4., ui.visuals().extreme_bg_color);
+
+    let rect = response.rect.shrink(8.);
+    let last = curve.len().saturating_sub(1).max(1) as f32;
+    let point = |(i, value): (usize, f32)| {
+        rect.left_bottom() + Vec2::new(rect.width() * i as f32 / last, -rect.height() * value)
+    };
+
+    let target: Vec<_> = tension
+        .targets(curve.len())
+        .enumerate()
+        .map(point)
+        .collect();
+    painter.extend(Shape::dashed_line(
+        &target,
+        Stroke::new(1., Color32::GRAY),
+        4.,
+        4.,
+    ));
+
+    let points: Vec<_> = normalized(&curve).enumerate().map(point).collect();
+    painter.add(Shape::line(
+        points.clone(),
+        Stroke::new(2., Color32::LIGHT_RED),
+    ));
+    for (i, point) in points.iter().enumerate() {
+        let radius = if i == selected_step { 5. } else { 3. };
+        painter.circle_filled(*point, radius, Color32::LIGHT_RED);
+    }
+
+    let pointer = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked())?;
+    points
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (a.x - pointer.x).abs().total_cmp(&(b.x - pointer.x).abs()))
+        .map(|(i, _)| i)
+}
+
+fn view_tabs(ui: &mut egui::Ui, view: &mut StoryView, can_compare: bool) {
+    ui.horizontal(|ui| {
+        let views = [
+            (StoryView::Timeline, "Timeline"),
+            (StoryView::Graph, "Graph"),
+            (StoryView::Compare, "Compare"),
+        ];
+        for (value, label) in views {
+            if value == StoryView::Compare && !can_compare {
+                continue;
+            }
+            ui.selectable_value(
+                view,
+                value,
+                RichText::new(label).text_style(egui::TextStyle::Name("Heading2".into())),
+            );
+        }
+    });
+}
+
+/// Shows the stories of the last generation in columns, each with its metrics and its distance to
+/// the selected one. Returns the story the user picked.
+fn compare(ui: &mut egui::Ui, app: &PredicateImprovApp) -> Option<usize> {
+    let (domain, problem) = app.generated_for.as_ref()?;
+    let distance = app.diversity.distance;
+    let selected = app.stories.get(app.selected_story)?;
+    let mut picked = None;
+
+    egui::ScrollArea::both()
+        .id_salt("story_comparison")
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            ui.columns(app.stories.len(), |columns| {
+                for (i, (ui, story)) in columns.iter_mut().zip(&app.stories).enumerate() {
+                    if ui
+                        .selectable_label(
+                            i == app.selected_story,
+                            RichText::new(format!("Story {}", i + 1))
+                                .text_style(egui::TextStyle::Name("Heading2".into())),
+                        )
+                        .clicked()
+                    {
+                        picked = Some(i);
+                    }
+                    let score = domain.score_story(problem, story, &app.ranking);
+                    ui.label(RichText::new(score.to_string()).weak());
+                    if i != app.selected_story {
+                        ui.label(
+                            RichText::new(format!(
+                                "{:.2} from story {}",
+                                distance.between(story, selected),
+                                app.selected_story + 1
+                            ))
+                            .weak(),
+                        );
+                    }
+
+                    Frame::canvas(ui.style())
+                        .inner_margin(Margin::same(8))
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.label(&story.text);
+                        });
+                }
+            });
+        });
+
+    picked
+}
+
+/// Lists the facts the step relies on along with the step that established each of them.
+fn causal_links(ui: &mut egui::Ui, links: &[CausalLink], step: usize, state: &StoryState) {
+    let links: Vec<_> = links
+        .iter()
+        .filter(|link| link.consumer == PlanNode::Step(step))
+        .collect();
+    if links.is_empty() {
+        return;
+    }
+
+    ui.label(RichText::new("Because").text_style(egui::TextStyle::Name("Heading2".into())));
+
+    for link in links {
+        let source = match link.producer {
+            PlanNode::Step(producer) => format!("step {}", producer + 1),
+            PlanNode::Start | PlanNode::Goal => "the start".into(),
+        };
+        ui.label(format!("{}, since {source}", link.fact_label(state)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FactChange {
+    Unchanged,
+    Added,
+    /// A fluent whose value changed, or a belief that was reversed.
+    Changed,
+    Removed,
+}
+
+/// Lists the facts of `state`, the values of its fluents and the beliefs of its characters along
+/// with the facts of `previous` that no longer hold, sorted by label.
+fn state_diff(state: &StoryState, previous: Option<&StoryState>) -> Vec<(String, FactChange)> {
+    let mut facts: Vec<(String, FactChange)> = state
+        .facts()
+        .map(|(signature, arguments)| {
+            let change = match previous {
+                Some(previous) if !previous.holds(signature, arguments) => FactChange::Added,
+                _ => FactChange::Unchanged,
+            };
+
+            (state.fact_label(signature, arguments), change)
+        })
+        .collect();
+
+    if let Some(previous) = previous {
+        facts.extend(
+            previous
+                .facts()
+                .filter(|(signature, arguments)| !state.holds(signature, arguments))
+                .map(|(signature, arguments)| {
+                    (
+                        previous.fact_label(signature, arguments),
+                        FactChange::Removed,
+                    )
+                }),
+        );
+    }
+
+    facts.extend(state.fluent_values().map(|(signature, arguments, value)| {
+        let label = format!("{} = {value}", state.fact_label(signature, arguments));
+
+        match previous.map(|previous| previous.fluent_value(signature, arguments)) {
+            Some(old) if old != value => (format!("{label} (was {old})"), FactChange::Changed),
+            _ => (label, FactChange::Unchanged),
+        }
+    }));
+
+    facts.extend(
+        state
+            .beliefs()
+            .map(|(character, signature, arguments, value)| {
+                let label = state.belief_label(character, signature, arguments, value);
+
+                match previous.map(|previous| previous.belief(character, signature, arguments)) {
+                    Some(None) => (label, FactChange::Added),
+                    Some(Some(old)) if old != value => (label, FactChange::Changed),
+                    _ => (label, FactChange::Unchanged),
+                }
+            }),
+    );
+
+    facts.sort();
+    facts
+}
@@ -0,0 +1,332 @@
+use std::mem::take;
+
+use eframe::egui::{self, Color32, Frame, Margin, RichText, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action_panel::LogicExprEditor,
+    app::{PredicateImprovApp, name_taken, typed_object_input},
+    history::{Change, Edit},
+    story::{CompoundTask, LogicExpr, Method, Symbol, TaskCall, TypeName, TypedSymbol},
+    validator::parse_story,
+};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TaskDraft {
+    pub name: String,
+    pub parameters: Vec<(String, String)>,
+    pub methods: Vec<MethodDraft>,
+}
+
+impl TaskDraft {
+    pub fn into_task(self) -> (Symbol, CompoundTask) {
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|(var, ty)| TypedSymbol {
+                name: Symbol(var),
+                r#type: TypeName(ty),
+            })
+            .collect();
+        let methods = self
+            .methods
+            .into_iter()
+            .map(|method| Method {
+                name: method.name,
+                precondition: method.precondition,
+                subtasks: method.subtasks,
+            })
+            .collect();
+
+        (
+            Symbol(self.name),
+            CompoundTask {
+                parameters,
+                methods,
+            },
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MethodDraft {
+    pub name: String,
+    pub precondition: LogicExpr,
+    pub subtasks: Vec<TaskCall>,
+    #[serde(skip)]
+    precondition_editor: LogicExprEditor,
+    #[serde(skip)]
+    subtasks_editor: TaskCallsEditor,
+}
+
+/// A text field for a list of task calls, one per line, kept in sync with the list the same way
+/// `LogicExprEditor` is.
+#[derive(Debug, Default, Clone)]
+pub struct TaskCallsEditor {
+    text: String,
+    /// The printed form of the list when the text was last synced with it.
+    synced: String,
+    error: Option<String>,
+}
+
+impl TaskCallsEditor {
+    pub fn show(&mut self, ui: &mut egui::Ui, calls: &mut Vec<TaskCall>, hint: &str) {
+        let printed: String = calls.iter().map(|call| format!("{call}\n")).collect();
+        if printed != self.synced {
+            self.text = printed.clone();
+            self.synced = printed;
+            self.error = None;
+        }
+
+        let response = ui.add(
+            egui::TextEdit::multiline(&mut self.text)
+                .hint_text(hint)
+                .desired_width(f32::INFINITY)
+                .desired_rows(2),
+        );
+
+        if response.changed() {
+            match parse_story(&self.text) {
+                Ok(steps) => {
+                    *calls = steps.into_iter().map(|(_, step)| step.into()).collect();
+                    self.synced = calls.iter().map(|call| format!("{call}\n")).collect();
+                    self.error = None;
+                }
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+
+        if let Some(err) = &self.error {
+            ui.label(RichText::new(err).color(Color32::LIGHT_RED));
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TaskPanel {
+    pub task_draft: TaskDraft,
+    pub backup_task_draft: Option<TaskDraft>,
+    pub add_task: bool,
+    show_task_modal: bool,
+}
+
+impl TaskPanel {
+    pub fn show(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+        ui.spacing_mut().item_spacing = [6., 12.].into();
+
+        ui.label(RichText::new("Tasks").text_style(egui::TextStyle::Name("Heading2".into())));
+
+        ui.add_space(16.);
+
+        if ui
+            .button("New task")
+            .on_hover_text("A task performed by one of several sequences of actions and tasks")
+            .clicked()
+        {
+            app.task_panel.show_task_modal = true;
+        }
+
+        Frame::canvas(ui.style())
+            .inner_margin(Margin::same(8))
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.spacing_mut().item_spacing.y = 6.;
+
+                        app.domain.tasks.retain(|name, task| {
+                            Frame::new()
+                                .fill(Color32::from_rgb(36, 36, 36))
+                                .corner_radius(4.0)
+                                .inner_margin(Margin::same(4))
+                                .show(ui, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(RichText::new(name.0.clone()).italics());
+
+                                        ui.label("(");
+                                        for var in &task.parameters {
+                                            Frame::new()
+                                                .corner_radius(2.0)
+                                                .stroke(ui.style().visuals.window_stroke())
+                                                .show(ui, |ui| {
+                                                    ui.label(format!(
+                                                        " {}: {} ",
+                                                        var.name.0, var.r#type.0
+                                                    ));
+                                                });
+                                        }
+                                        ui.label(")");
+
+                                        let methods = task.methods.len();
+                                        ui.label(
+                                            RichText::new(match methods {
+                                                1 => "1 method".into(),
+                                                n => format!("{n} methods"),
+                                            })
+                                            .weak(),
+                                        );
+
+                                        ui.allocate_space(
+                                            [(ui.available_width() - 40.).max(0.), 0.].into(),
+                                        );
+
+                                        ui.menu_button("…", |ui| {
+                                            let mut retain = if ui.button("Edit").clicked() {
+                                                app.task_panel.show_task_modal = true;
+                                                app.task_panel.task_draft = task_draft(name, task);
+                                                app.task_panel.backup_task_draft =
+                                                    Some(app.task_panel.task_draft.clone());
+
+                                                false
+                                            } else {
+                                                true
+                                            };
+
+                                            if ui.button("Delete").clicked() {
+                                                app.history.record(Edit::Task(Box::new(
+                                                    Change::removed((name.clone(), task.clone())),
+                                                )));
+                                                retain = false;
+                                            }
+
+                                            retain
+                                        })
+                                        .inner
+                                        .unwrap_or(true)
+                                    })
+                                    .inner
+                                })
+                                .inner
+                        });
+                    });
+            });
+
+        if app.task_panel.show_task_modal {
+            egui::Modal::new("task_modal".into()).show(ui.ctx(), |ui| task_modal(app, ui));
+        }
+    }
+}
+
+fn task_draft(name: &Symbol, task: &mut CompoundTask) -> TaskDraft {
+    TaskDraft {
+        name: name.0.clone(),
+        parameters: task
+            .parameters
+            .iter_mut()
+            .map(|v| (take(&mut v.name.0), take(&mut v.r#type.0)))
+            .collect(),
+        methods: task
+            .methods
+            .iter_mut()
+            .map(|method| MethodDraft {
+                name: take(&mut method.name),
+                precondition: take(&mut method.precondition),
+                subtasks: take(&mut method.subtasks),
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+fn task_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.label("name:");
+        ui.text_edit_singleline(&mut app.task_panel.task_draft.name);
+    });
+
+    {
+        let mut i = 0;
+        app.task_panel
+            .task_draft
+            .parameters
+            .retain_mut(|(var, ty)| {
+                i += 1;
+                typed_object_input(&app.domain, ui, var, ty, format!("param {i}"))
+            });
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Add parameter");
+        if ui.button(egui_material_icons::icons::ICON_ADD).clicked()
+            && app.task_panel.task_draft.parameters.len() < 5
+        {
+            app.task_panel
+                .task_draft
+                .parameters
+                .push((String::new(), String::new()));
+        }
+    });
+
+    app.task_panel.task_draft.methods.retain_mut(|method| {
+        Frame::new()
+            .fill(Color32::from_rgb(36, 36, 36))
+            .corner_radius(4.0)
+            .inner_margin(Margin::same(4))
+            .show(ui, |ui| {
+                let retain = ui
+                    .horizontal(|ui| {
+                        ui.label("method:");
+                        ui.text_edit_singleline(&mut method.name);
+                        !ui.button(egui_material_icons::icons::ICON_REMOVE).clicked()
+                    })
+                    .inner;
+
+                ui.label("precondition:");
+                method
+                    .precondition_editor
+                    .show(ui, &app.domain, &mut method.precondition);
+
+                ui.label("subtasks:");
+                method.subtasks_editor.show(
+                    ui,
+                    &mut method.subtasks,
+                    "take(?hero, ?sword)\nslay(?hero, ?villain)",
+                );
+
+                retain
+            })
+            .inner
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Add method");
+        if ui.button(egui_material_icons::icons::ICON_ADD).clicked() {
+            app.task_panel
+                .task_draft
+                .methods
+                .push(MethodDraft::default());
+        }
+    });
+
+    let name = Symbol(app.task_panel.task_draft.name.clone());
+    let taken = name_taken(
+        ui,
+        app.domain.tasks.contains_key(&name) || app.domain.actions.contains_key(&name),
+        name.0,
+    );
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                !taken,
+                egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)),
+            )
+            .clicked()
+        {
+            app.task_panel.show_task_modal = false;
+            app.task_panel.add_task = true;
+        }
+
+        if ui
+            .add(egui::Button::new("Cancel").min_size(Vec2::new(ui.available_width(), 0.)))
+            .clicked()
+        {
+            app.task_panel.show_task_modal = false;
+
+            if let Some(draft) = app.task_panel.backup_task_draft.clone() {
+                app.task_panel.task_draft = draft;
+                app.task_panel.add_task = true;
+            }
+        }
+    });
+}
@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::story::{Bindings, LogicExpr, PredicateStory, StoryState};
+
+/// A condition that adds its weight to the tension of every state it holds in, e.g. 3 while
+/// `at(dragon, village)`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TensionTerm {
+    pub weight: i64,
+    pub condition: LogicExpr,
+}
+
+/// The shape the tension of a story should follow from its start to its end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TensionArc {
+    /// Tension keeps building up to the end.
+    Rising,
+    /// Rising action up to a climax, then falling action.
+    #[default]
+    Climax,
+    /// Tension eases from the start.
+    Falling,
+}
+
+impl TensionArc {
+    pub const ALL: [TensionArc; 3] = [TensionArc::Rising, TensionArc::Climax, TensionArc::Falling];
+}
+
+impl Display for TensionArc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TensionArc::Rising => write!(f, "Rising"),
+            TensionArc::Climax => write!(f, "Climax"),
+            TensionArc::Falling => write!(f, "Falling"),
+        }
+    }
+}
+
+/// How tense each state of a story is and how tension should evolve over the story.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tension {
+    pub terms: Vec<TensionTerm>,
+    pub arc: TensionArc,
+    /// How far into the story the climax comes, in percent.
+    pub climax: u8,
+}
+
+impl Default for Tension {
+    fn default() -> Self {
+        Self {
+            terms: Vec::new(),
+            arc: TensionArc::default(),
+            climax: 70,
+        }
+    }
+}
+
+impl Tension {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The sum of the weights of the terms whose condition holds in the state.
+    pub fn of(&self, state: &StoryState) -> i64 {
+        let no_bindings = Bindings::new();
+        self.terms
+            .iter()
+            .filter(|term| state.evaluate(&term.condition, &no_bindings))
+            .map(|term| term.weight)
+            .sum()
+    }
+
+    /// The tension of each state of the story, from the state before the first step.
+    pub fn curve(&self, story: &PredicateStory) -> Vec<i64> {
+        story.states.iter().map(|state| self.of(state)).collect()
+    }
+
+    /// The tension the arc calls for at `progress` through the story, both from 0 to 1.
+    pub fn target(&self, progress: f32) -> f32 {
+        match self.arc {
+            TensionArc::Rising => progress,
+            TensionArc::Falling => 1. - progress,
+            TensionArc::Climax => {
+                let climax = (self.climax.min(100) as f32 / 100.).clamp(f32::EPSILON, 1.);
+                if progress <= climax {
+                    progress / climax
+                } else {
+                    (1. - progress) / (1. - climax)
+                }
+            }
+        }
+    }
+
+    /// How closely the curve follows the arc, from 0 to 1. The curve is scaled so that its lowest
+    /// point is 0 and its highest 1, so only its shape matters; a flat curve is all 0.
+    pub fn fit(&self, curve: &[i64]) -> f32 {
+        if self.is_empty() || curve.is_empty() {
+            return 1.;
+        }
+
+        let targets = self.targets(curve.len());
+        let error: f32 = normalized(curve)
+            .zip(targets)
+            .map(|(tension, target)| (tension - target).abs())
+            .sum();
+        1. - error / curve.len() as f32
+    }
+
+    /// The target of each of `count` evenly spread states.
+    pub fn targets(&self, count: usize) -> impl Iterator<Item = f32> {
+        let last = count.saturating_sub(1).max(1) as f32;
+        (0..count).map(move |i| self.target(i as f32 / last))
+    }
+}
+
+/// The curve scaled from 0 at its lowest to 1 at its highest.
+pub fn normalized(curve: &[i64]) -> impl Iterator<Item = f32> {
+    let min = curve.iter().copied().min().unwrap_or_default();
+    let max = curve.iter().copied().max().unwrap_or_default();
+    curve.iter().map(move |tension| {
+        if max == min {
+            0.
+        } else {
+            (tension - min) as f32 / (max - min) as f32
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic_parser::parse_logic_expr,
+        story::{PredicateProblem, PredicateSignature},
+        test_util::{domain_with, sym},
+    };
+
+    #[test]
+    fn scores_curves_against_the_arc() {
+        let domain = domain_with(&[("danger", 0), ("fire", 0)], &[]);
+        let problem = PredicateProblem::default();
+        let mut state = domain.initial_story_state(&problem);
+
+        let mut tension = Tension {
+            terms: vec![
+                TensionTerm {
+                    weight: 2,
+                    condition: parse_logic_expr(&domain, "danger()").unwrap(),
+                },
+                TensionTerm {
+                    weight: 3,
+                    condition: parse_logic_expr(&domain, "danger() and fire()").unwrap(),
+                },
+            ],
+            arc: TensionArc::Climax,
+            climax: 50,
+        };
+        assert_eq!(tension.of(&state), 0);
+        state.set_fact(&PredicateSignature::new(sym("danger"), 0), Vec::new(), true);
+        state.set_fact(&PredicateSignature::new(sym("fire"), 0), Vec::new(), true);
+        assert_eq!(tension.of(&state), 5);
+
+        assert_eq!(
+            tension.targets(5).collect::<Vec<_>>(),
+            [0., 0.5, 1., 0.5, 0.]
+        );
+        assert_eq!(tension.fit(&[0, 2, 4, 2, 0]), 1.);
+        assert_eq!(tension.fit(&[1, 1, 1, 1, 1]), 0.6);
+
+        tension.arc = TensionArc::Rising;
+        assert_eq!(tension.fit(&[0, 1, 2, 3, 4]), 1.);
+        assert_eq!(tension.fit(&[4, 0]), 0.);
+    }
+}
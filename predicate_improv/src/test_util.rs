@@ -0,0 +1,93 @@
+//! Fixtures shared by the tests of the other modules.
+
+use crate::{
+    logic_parser::parse_logic_expr,
+    story::{
+        Action, PredicateDomain, PredicateProblem, PredicateSignature, PredicateStory, StoryStep,
+        Symbol, TypeName, TypedSymbol,
+    },
+};
+
+pub fn sym(name: &str) -> Symbol {
+    Symbol(name.into())
+}
+
+/// A variable or object of the root type `object`.
+pub fn untyped(name: &str) -> TypedSymbol {
+    TypedSymbol {
+        name: sym(name),
+        r#type: TypeName::default(),
+    }
+}
+
+pub fn objects(names: &[&str]) -> Vec<TypedSymbol> {
+    names.iter().map(|name| untyped(name)).collect()
+}
+
+pub fn step(action: &str, arguments: &[&str]) -> StoryStep {
+    StoryStep {
+        action: sym(action),
+        arguments: arguments.iter().map(|arg| sym(arg)).collect(),
+    }
+}
+
+/// An action without agents over untyped parameters, whose precondition and effect are parsed
+/// against the predicates of the domain.
+pub fn action(
+    domain: &PredicateDomain,
+    parameters: &[&str],
+    precondition: &str,
+    effect: &str,
+) -> Action {
+    Action {
+        parameters: objects(parameters),
+        agents: Vec::new(),
+        precondition: parse_logic_expr(domain, precondition).unwrap(),
+        effect: parse_logic_expr(domain, effect).unwrap(),
+    }
+}
+
+/// A domain with the predicates, given by name and arity, and the actions, given by name,
+/// precondition and effect, each taking a single parameter `?c`.
+pub fn domain_with(predicates: &[(&str, u32)], actions: &[(&str, &str, &str)]) -> PredicateDomain {
+    let mut domain = PredicateDomain::default();
+    for (name, arity) in predicates {
+        domain
+            .predicates
+            .insert(PredicateSignature::new(sym(name), *arity), Vec::new());
+    }
+    add_actions(&mut domain, actions);
+    domain
+}
+
+/// Adds actions given by name, precondition and effect, each taking a single parameter `?c`.
+pub fn add_actions(domain: &mut PredicateDomain, actions: &[(&str, &str, &str)]) {
+    for (name, precondition, effect) in actions {
+        let action = action(domain, &["?c"], precondition, effect);
+        domain.actions.insert(sym(name), action);
+    }
+}
+
+/// A problem over the untyped objects whose goal is parsed against the domain.
+pub fn problem_with(domain: &PredicateDomain, names: &[&str], goal: &str) -> PredicateProblem {
+    PredicateProblem {
+        objects: objects(names),
+        goal: parse_logic_expr(domain, goal).unwrap(),
+        ..Default::default()
+    }
+}
+
+/// Applies the steps one after the other without validating the story, so steps may be
+/// unexplained or the goal unmet.
+pub fn story_of(
+    domain: &PredicateDomain,
+    problem: &PredicateProblem,
+    steps: Vec<StoryStep>,
+) -> PredicateStory {
+    let mut states = vec![domain.initial_story_state(problem)];
+    for step in &steps {
+        let state = domain.apply_step(states.last().unwrap(), step).unwrap();
+        states.push(state);
+    }
+    PredicateStory::new(steps, states)
+}
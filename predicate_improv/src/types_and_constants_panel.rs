@@ -1,29 +1,46 @@
 use std::mem::take;
 
 use eframe::egui::{self, Color32, Frame, Margin, RichText, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
 
-use crate::
-    app::{PredicateImprovApp, typed_object_input, untyped_object_input}
-;
+use crate::{
+    app::{PredicateImprovApp, RenameDraft, name_taken, typed_object_input, untyped_object_input},
+    history::{Change, Edit},
+    rename::RenameTarget,
+    story::{Symbol, TypeName},
+};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct TypeDraft {
     pub name: String,
     pub supertypes: Vec<String>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ConstantDraft {
     pub name: String,
     pub r#type: String,
 }
 
+impl TypeDraft {
+    pub fn into_type(self) -> (TypeName, Vec<TypeName>) {
+        let supertypes = self.supertypes.into_iter().map(TypeName).collect();
+        (TypeName(self.name), supertypes)
+    }
+}
+
+impl ConstantDraft {
+    pub fn into_constant(self) -> (Symbol, TypeName) {
+        (Symbol(self.name), TypeName(self.r#type))
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct TypesAndConstantsPanel {
     pub type_draft: TypeDraft,
     pub constant_draft: ConstantDraft,
-    backup_type_draft: Option<TypeDraft>,
-    backup_constant_draft: Option<ConstantDraft>,
+    pub backup_type_draft: Option<TypeDraft>,
+    pub backup_constant_draft: Option<ConstantDraft>,
     pub add_type: bool,
     pub add_constant: bool,
     show_type_modal: bool,
@@ -111,8 +128,21 @@ impl TypesAndConstantsPanel {
                                                         true
                                                     };
 
-                                                    retain =
-                                                        retain && !ui.button("Delete").clicked();
+                                                    if ui.button("Rename…").clicked() {
+                                                        app.rename_draft = Some(RenameDraft::new(
+                                                            RenameTarget::Type(name.clone()),
+                                                        ));
+                                                    }
+
+                                                    if ui.button("Delete").clicked() {
+                                                        app.history.record(Edit::Type(
+                                                            Change::removed((
+                                                                name.clone(),
+                                                                supertypes.clone(),
+                                                            )),
+                                                        ));
+                                                        retain = false;
+                                                    }
 
                                                     retain
                                                 })
@@ -172,7 +202,7 @@ impl TypesAndConstantsPanel {
                                                     let mut retain = if ui.button("Edit").clicked()
                                                     {
                                                         app.types_and_constants_panel
-                                                            .show_type_modal = true;
+                                                            .show_constant_modal = true;
 
                                                         app.types_and_constants_panel
                                                             .constant_draft
@@ -193,8 +223,21 @@ impl TypesAndConstantsPanel {
                                                         true
                                                     };
 
-                                                    retain =
-                                                        retain && !ui.button("Delete").clicked();
+                                                    if ui.button("Rename…").clicked() {
+                                                        app.rename_draft = Some(RenameDraft::new(
+                                                            RenameTarget::Constant(name.clone()),
+                                                        ));
+                                                    }
+
+                                                    if ui.button("Delete").clicked() {
+                                                        app.history.record(Edit::Constant(
+                                                            Change::removed((
+                                                                name.clone(),
+                                                                r#type.clone(),
+                                                            )),
+                                                        ));
+                                                        retain = false;
+                                                    }
 
                                                     retain
                                                 })
@@ -226,13 +269,16 @@ fn type_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
     });
 
     {
+        let mut types: Vec<String> = app.domain.types.keys().map(|ty| ty.0.clone()).collect();
+        types.sort();
+
         let mut i = 0;
         app.types_and_constants_panel
             .type_draft
             .supertypes
             .retain_mut(|ty| {
                 i += 1;
-                untyped_object_input(ui, ty, format!("supertype {i}"))
+                untyped_object_input(ui, ty, &types, format!("supertype {i}"))
             });
     }
 
@@ -248,9 +294,19 @@ fn type_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         }
     });
 
+    let name = app.types_and_constants_panel.type_draft.name.clone();
+    let taken = name_taken(
+        ui,
+        app.domain.types.contains_key(&TypeName(name.clone())),
+        name,
+    );
+
     ui.horizontal(|ui| {
         if ui
-            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .add_enabled(
+                !taken,
+                egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)),
+            )
             .clicked()
         {
             app.types_and_constants_panel.show_type_modal = false;
@@ -263,7 +319,7 @@ fn type_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         {
             app.types_and_constants_panel.show_type_modal = false;
 
-            if let Some(draft) = take(&mut app.types_and_constants_panel.backup_type_draft) {
+            if let Some(draft) = app.types_and_constants_panel.backup_type_draft.clone() {
                 app.types_and_constants_panel.type_draft = draft;
                 app.types_and_constants_panel.add_type = true;
             }
@@ -280,9 +336,19 @@ fn constant_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         "constant:",
     );
 
+    let name = app.types_and_constants_panel.constant_draft.name.clone();
+    let taken = name_taken(
+        ui,
+        app.domain.constants.contains_key(&Symbol(name.clone())),
+        name,
+    );
+
     ui.horizontal(|ui| {
         if ui
-            .add(egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)))
+            .add_enabled(
+                !taken,
+                egui::Button::new("Submit").min_size(Vec2::new(ui.available_width() / 2., 0.)),
+            )
             .clicked()
         {
             app.types_and_constants_panel.show_constant_modal = false;
@@ -295,7 +361,7 @@ fn constant_modal(app: &mut PredicateImprovApp, ui: &mut egui::Ui) {
         {
             app.types_and_constants_panel.show_constant_modal = false;
 
-            if let Some(draft) = take(&mut app.types_and_constants_panel.backup_constant_draft) {
+            if let Some(draft) = app.types_and_constants_panel.backup_constant_draft.clone() {
                 app.types_and_constants_panel.constant_draft = draft;
                 app.types_and_constants_panel.add_constant = true;
             }
@@ -0,0 +1,582 @@
+use std::fmt::Display;
+
+use crate::{
+    axioms::AxiomError,
+    story::{
+        Bindings, LogicExpr, PredicateDomain, PredicateProblem, PredicateStory, StoryState,
+        StoryStep, Symbol, TypeName,
+    },
+};
+
+/// Why a hand-written story was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoryError {
+    /// A line could not be read as `action(arg1, arg2, ...)`. Lines and columns start at 1.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The step with the given index (starting at 0) cannot be applied, or one of its agents has
+    /// no reason to take part.
+    Step { step: usize, kind: StepError },
+    /// The story ends without satisfying the goal of the problem.
+    GoalNotSatisfied { unsatisfied: Vec<String> },
+    /// The story has fewer steps than the problem requires.
+    TooShort { length: usize, min: usize },
+    /// The story breaks one of the constraints of the problem.
+    ConstraintViolated { constraint: String },
+    /// The initial state of the problem names things that are neither constants nor objects.
+    UnknownInitialSymbols { symbols: Vec<Symbol> },
+    /// The derived predicates of the domain are not well defined.
+    Axioms(AxiomError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepError {
+    UnknownAction(Symbol),
+    WrongArity {
+        expected: usize,
+        found: usize,
+    },
+    UnknownObject {
+        argument: usize,
+        name: Symbol,
+    },
+    TypeMismatch {
+        argument: usize,
+        expected: TypeName,
+        found: TypeName,
+    },
+    /// Lists the literals of the precondition that do not hold.
+    PreconditionNotSatisfied {
+        unsatisfied: Vec<String>,
+    },
+    /// The character takes part in the step without it serving any of their goals.
+    Unexplained {
+        character: Symbol,
+    },
+}
+
+impl Display for StoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoryError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+            StoryError::Step { step, kind } => write!(f, "step {}: {kind}", step + 1),
+            StoryError::GoalNotSatisfied { unsatisfied } => {
+                write!(f, "the goal is not satisfied: {}", unsatisfied.join(", "))
+            }
+            StoryError::TooShort { length, min } => {
+                write!(f, "the story has {length} steps but needs at least {min}")
+            }
+            StoryError::ConstraintViolated { constraint } => {
+                write!(f, "the constraint `{constraint}` is not satisfied")
+            }
+            StoryError::UnknownInitialSymbols { symbols } => {
+                let symbols: Vec<_> = symbols.iter().map(|symbol| symbol.as_str()).collect();
+                write!(
+                    f,
+                    "the initial state names unknown objects: {}",
+                    symbols.join(", ")
+                )
+            }
+            StoryError::Axioms(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Display for StepError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepError::UnknownAction(name) => write!(f, "unknown action `{}`", name.0),
+            StepError::WrongArity { expected, found } => {
+                write!(f, "expected {expected} arguments, found {found}")
+            }
+            StepError::UnknownObject { argument, name } => {
+                write!(f, "argument {}: unknown object `{}`", argument + 1, name.0)
+            }
+            StepError::TypeMismatch {
+                argument,
+                expected,
+                found,
+            } => write!(
+                f,
+                "argument {}: expected type `{}`, found `{}`",
+                argument + 1,
+                type_label(expected),
+                type_label(found)
+            ),
+            StepError::PreconditionNotSatisfied { unsatisfied } => {
+                write!(f, "precondition not satisfied: {}", unsatisfied.join(", "))
+            }
+            StepError::Unexplained { character } => {
+                write!(f, "nothing explains why `{}` takes part", character.0)
+            }
+        }
+    }
+}
+
+impl StepError {
+    /// The index of the argument the error is about, if it is about a single argument.
+    pub fn argument(&self) -> Option<usize> {
+        match self {
+            StepError::UnknownObject { argument, .. }
+            | StepError::TypeMismatch { argument, .. } => Some(*argument),
+            _ => None,
+        }
+    }
+}
+
+fn type_label(ty: &TypeName) -> &str {
+    if ty.is_empty() { "object" } else { &ty.0 }
+}
+
+/// Parses one step per non-empty line, written as `action(arg1, arg2, ...)`. The parentheses may
+/// be omitted for actions without parameters. Returns each step along with its line number.
+pub fn parse_story(text: &str) -> Result<Vec<(usize, StoryStep)>, StoryError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            parse_step(line)
+                .map(|step| (i + 1, step))
+                .map_err(|(column, message)| StoryError::Syntax {
+                    line: i + 1,
+                    column,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// The column (starting at 1) where the step written on the line starts, or where its argument
+/// with the given index starts.
+pub fn step_column(line: &str, argument: Option<usize>) -> usize {
+    let offset = argument
+        .and_then(|argument| {
+            let open = line.find('(')?;
+            let mut arguments = line[open + 1..].split_inclusive(',');
+            let before: usize = arguments.by_ref().take(argument).map(str::len).sum();
+            let text = arguments.next()?;
+            Some(open + 1 + before + text.len() - text.trim_start().len())
+        })
+        .unwrap_or(line.len() - line.trim_start().len());
+    char_column(line, offset)
+}
+
+/// The column (starting at 1) of the byte offset, counting characters rather than bytes.
+fn char_column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Parses a single step, returning the column (starting at 1) and message on failure.
+fn parse_step(line: &str) -> Result<StoryStep, (usize, String)> {
+    let column = |rest: &str| char_column(line, line.len() - rest.len());
+    let is_symbol_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '?');
+
+    let rest = line.trim_start();
+    let name_len = rest.find(|c| !is_symbol_char(c)).unwrap_or(rest.len());
+    if name_len == 0 {
+        return Err((column(rest), "expected an action name".into()));
+    }
+    let action = Symbol(rest[..name_len].into());
+
+    let rest = rest[name_len..].trim_start();
+    if rest.is_empty() {
+        return Ok(StoryStep {
+            action,
+            arguments: Vec::new(),
+        });
+    }
+
+    let Some(mut rest) = rest.strip_prefix('(') else {
+        return Err((column(rest), "expected `(`".into()));
+    };
+
+    let mut arguments = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if arguments.is_empty()
+            && let Some(after) = rest.strip_prefix(')')
+        {
+            rest = after;
+            break;
+        }
+
+        let arg_len = rest.find(|c| !is_symbol_char(c)).unwrap_or(rest.len());
+        if arg_len == 0 {
+            return Err((column(rest), "expected an argument".into()));
+        }
+        arguments.push(Symbol(rest[..arg_len].into()));
+
+        rest = rest[arg_len..].trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(')') {
+            rest = after;
+            break;
+        } else {
+            return Err((column(rest), "expected `,` or `)`".into()));
+        }
+    }
+
+    if !rest.trim().is_empty() {
+        return Err((
+            column(rest.trim_start()),
+            "unexpected text after `)`".into(),
+        ));
+    }
+
+    Ok(StoryStep { action, arguments })
+}
+
+impl PredicateDomain {
+    /// Replays the steps from the initial state of the problem, checking that every step is
+    /// applicable, that the goal holds at the end, that the story is long enough and meets the
+    /// constraints, and that every character has a reason to act.
+    /// On success, returns the story along with every intermediate state.
+    pub fn validate_story(
+        &self,
+        problem: &PredicateProblem,
+        steps: Vec<StoryStep>,
+    ) -> Result<PredicateStory, StoryError> {
+        self.check_axioms().map_err(StoryError::Axioms)?;
+        let symbols = self.unknown_initial_symbols(problem);
+        if !symbols.is_empty() {
+            return Err(StoryError::UnknownInitialSymbols { symbols });
+        }
+
+        let mut state = self.initial_story_state(problem);
+        let mut states = vec![state.clone()];
+
+        for (i, step) in steps.iter().enumerate() {
+            state = self
+                .check_step(&state, step)
+                .map_err(|kind| StoryError::Step { step: i, kind })?;
+            states.push(state.clone());
+        }
+
+        let unsatisfied = unsatisfied_literals(&state, &problem.goal, &Bindings::new());
+        if !unsatisfied.is_empty() {
+            return Err(StoryError::GoalNotSatisfied { unsatisfied });
+        }
+
+        let min = problem.min_story_length as usize;
+        if steps.len() < min {
+            return Err(StoryError::TooShort {
+                length: steps.len(),
+                min,
+            });
+        }
+        if let Some(constraint) = problem.violated_constraint(&steps, &states) {
+            return Err(StoryError::ConstraintViolated {
+                constraint: constraint.to_string(),
+            });
+        }
+
+        let story = PredicateStory::new(steps, states);
+        if let Some((step, character)) = self.unexplained_steps(problem, &story).into_iter().next()
+        {
+            return Err(StoryError::Step {
+                step,
+                kind: StepError::Unexplained { character },
+            });
+        }
+
+        Ok(story)
+    }
+
+    /// Like `apply_step`, but explains why the step cannot be applied.
+    pub fn check_step(
+        &self,
+        state: &StoryState,
+        step: &StoryStep,
+    ) -> Result<StoryState, StepError> {
+        let action = self
+            .actions
+            .get(&step.action)
+            .ok_or_else(|| StepError::UnknownAction(step.action.clone()))?;
+
+        if action.parameters.len() != step.arguments.len() {
+            return Err(StepError::WrongArity {
+                expected: action.parameters.len(),
+                found: step.arguments.len(),
+            });
+        }
+
+        let mut arguments = Vec::with_capacity(step.arguments.len());
+        for (i, (param, name)) in action.parameters.iter().zip(&step.arguments).enumerate() {
+            let atom = state
+                .get_atom(name)
+                .ok_or_else(|| StepError::UnknownObject {
+                    argument: i,
+                    name: name.clone(),
+                })?;
+
+            let r#type = state.get_atom_type(atom).unwrap_or(&param.r#type);
+            if !self.is_subtype(r#type, &param.r#type) {
+                return Err(StepError::TypeMismatch {
+                    argument: i,
+                    expected: param.r#type.clone(),
+                    found: r#type.clone(),
+                });
+            }
+
+            arguments.push(atom);
+        }
+
+        let bindings = action.bind(&arguments);
+        let unsatisfied = unsatisfied_literals(state, &action.precondition, &bindings);
+        if !unsatisfied.is_empty() {
+            return Err(StepError::PreconditionNotSatisfied { unsatisfied });
+        }
+
+        let mut next = state.clone();
+        next.apply_effect_deriving(&action.effect, &bindings, |state| self.derive(state));
+        self.derive(&mut next);
+        Ok(next)
+    }
+}
+
+/// Explains why an expression does not hold by listing its false literals. Returns an empty list
+/// if the expression holds.
+fn unsatisfied_literals(state: &StoryState, expr: &LogicExpr, bindings: &Bindings) -> Vec<String> {
+    if state.evaluate(expr, bindings) {
+        return Vec::new();
+    }
+
+    match expr {
+        LogicExpr::True | LogicExpr::Update(..) | LogicExpr::Learn(..) => Vec::new(),
+        LogicExpr::Compare(..) | LogicExpr::Believes(..) => {
+            let name = |symbol: &mut Symbol| {
+                if let Some(name) = bindings.get(symbol).and_then(|a| state.get_atom_name(*a)) {
+                    *symbol = Symbol(name.into());
+                }
+            };
+
+            let mut bound = expr.clone();
+            bound.for_each_fluent_mut(&mut |_, symbols| symbols.iter_mut().for_each(name));
+            bound.for_each_predicate_mut(&mut |_, symbols| symbols.iter_mut().for_each(name));
+            bound.for_each_character_mut(&mut |symbol| name(symbol));
+            vec![bound.to_string()]
+        }
+        LogicExpr::Predicate(signature, symbols) => {
+            vec![literal_label(
+                signature.function.as_str(),
+                symbols,
+                state,
+                bindings,
+            )]
+        }
+        LogicExpr::Not(inner) => match &**inner {
+            LogicExpr::Predicate(signature, symbols) => vec![format!(
+                "not {}",
+                literal_label(signature.function.as_str(), symbols, state, bindings)
+            )],
+            _ => vec![String::from("a negated condition")],
+        },
+        LogicExpr::And(lhs, rhs) => {
+            let mut unsatisfied = unsatisfied_literals(state, lhs, bindings);
+            unsatisfied.extend(unsatisfied_literals(state, rhs, bindings));
+            unsatisfied
+        }
+        LogicExpr::Or(lhs, rhs) => {
+            let lhs = unsatisfied_literals(state, lhs, bindings).join(" and ");
+            let rhs = unsatisfied_literals(state, rhs, bindings).join(" and ");
+            vec![format!("({lhs}) or ({rhs})")]
+        }
+    }
+}
+
+/// Formats a predicate with its parameters replaced by the objects they are bound to.
+fn literal_label(
+    function: &str,
+    symbols: &[Symbol],
+    state: &StoryState,
+    bindings: &Bindings,
+) -> String {
+    let arguments: Vec<&str> = symbols
+        .iter()
+        .map(|symbol| {
+            bindings
+                .get(symbol)
+                .and_then(|atom| state.get_atom_name(*atom))
+                .unwrap_or(symbol.as_str())
+        })
+        .collect();
+
+    format!("{function}({})", arguments.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        story::PredicateSignature,
+        test_util::{action, domain_with, objects, sym},
+    };
+
+    #[test]
+    fn parses_steps_with_line_numbers() {
+        let steps = parse_story("move(alice, forest, castle)\n\n  rest\nsleep( bob )").unwrap();
+
+        let lines: Vec<usize> = steps.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 3, 4]);
+        assert_eq!(steps[0].1.to_string(), "move(alice, forest, castle)");
+        assert!(steps[1].1.arguments.is_empty());
+        assert_eq!(steps[2].1.arguments, [sym("bob")]);
+    }
+
+    #[test]
+    fn tracks_numeric_fluents() {
+        let gold = PredicateSignature::new(sym("gold"), 1);
+        let mut domain = PredicateDomain::default();
+        domain.fluents.insert(gold.clone(), Vec::new());
+
+        let pay = action(
+            &domain,
+            &["?a", "?b"],
+            "gold(?a) >= 5",
+            "decrease(gold(?a), 5) and increase(gold(?b), 5)",
+        );
+        domain.actions.insert(sym("pay"), pay);
+
+        let mut problem = PredicateProblem {
+            objects: objects(&["alice", "bob"]),
+            ..Default::default()
+        };
+        problem
+            .initial_state
+            .fluent_values
+            .push((gold.clone(), vec![sym("alice")], 7));
+
+        let steps: Vec<StoryStep> = parse_story("pay(alice, bob)\npay(alice, bob)")
+            .unwrap()
+            .into_iter()
+            .map(|(_, step)| step)
+            .collect();
+
+        let story = domain
+            .validate_story(&problem, steps[..1].to_vec())
+            .unwrap();
+        let end = &story.states[1];
+        let alice = end.get_atom(&sym("alice")).unwrap();
+        let bob = end.get_atom(&sym("bob")).unwrap();
+        assert_eq!(end.fluent_value(&gold, &[alice]), 2);
+        assert_eq!(end.fluent_value(&gold, &[bob]), 5);
+
+        let err = domain.validate_story(&problem, steps).unwrap_err();
+        assert_eq!(
+            err,
+            StoryError::Step {
+                step: 1,
+                kind: StepError::PreconditionNotSatisfied {
+                    unsatisfied: vec!["gold(alice) >= 5".into()],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn tracks_beliefs() {
+        let mut domain = domain_with(&[("in_vault", 0), ("in_cellar", 0)], &[]);
+        let actions = [
+            (
+                "lie",
+                action(&domain, &["?a", "?b"], "true", "inform(?b, in_cellar())"),
+            ),
+            (
+                "peek",
+                action(
+                    &domain,
+                    &["?a"],
+                    "true",
+                    "observe(?a, in_vault() and in_cellar())",
+                ),
+            ),
+            (
+                "search_cellar",
+                action(&domain, &["?a"], "believes(?a, in_cellar())", "true"),
+            ),
+        ];
+        for (name, action) in actions {
+            domain.actions.insert(sym(name), action);
+        }
+
+        let mut problem = PredicateProblem {
+            objects: objects(&["alice", "bob"]),
+            ..Default::default()
+        };
+        let in_vault = PredicateSignature::new(sym("in_vault"), 0);
+        problem
+            .initial_state
+            .bound_predicates
+            .push((in_vault.clone(), Vec::new()));
+
+        let steps = |text| {
+            parse_story(text)
+                .unwrap()
+                .into_iter()
+                .map(|(_, step)| step)
+                .collect()
+        };
+
+        // Bob is wrong about where the gem is, so he searches the cellar.
+        let story = domain
+            .validate_story(&problem, steps("lie(alice, bob)\nsearch_cellar(bob)"))
+            .unwrap();
+        let bob = story.states[1].get_atom(&sym("bob")).unwrap();
+        assert_eq!(story.states[1].belief(bob, &in_vault, &[]), None);
+
+        let err = domain
+            .validate_story(
+                &problem,
+                steps("lie(alice, bob)\npeek(bob)\nsearch_cellar(bob)"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StoryError::Step {
+                step: 2,
+                kind: StepError::PreconditionNotSatisfied {
+                    unsatisfied: vec!["believes(bob, in_cellar())".into()],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn reports_syntax_error_location() {
+        let err = parse_story("rest()\nmove(alice forest)").unwrap_err();
+
+        assert_eq!(
+            err,
+            StoryError::Syntax {
+                line: 2,
+                column: 12,
+                message: "expected `,` or `)`".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let err = parse_story("gehe(ä forest)").unwrap_err();
+        assert_eq!(
+            err,
+            StoryError::Syntax {
+                line: 1,
+                column: 8,
+                message: "expected `,` or `)`".into(),
+            }
+        );
+
+        assert_eq!(step_column("  move(ä, förest)", None), 3);
+        assert_eq!(step_column("  move(ä, förest)", Some(1)), 11);
+        assert_eq!(step_column("rest", Some(0)), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use eframe::egui::{self, Color32, RichText};
+
+use crate::{
+    app::PredicateImprovApp,
+    story::{PredicateDomain, PredicateProblem},
+    validator::{StoryError, parse_story, step_column},
+};
+
+#[derive(Debug, Default)]
+pub struct ValidatorPanel {
+    /// The hand-written story, one step per line.
+    pub story_draft: String,
+    /// The outcome of the last check, with errors already pointing at a line of the draft.
+    result: Option<Result<(), String>>,
+    /// The domain, problem and draft the last check was made against, so that its result can be
+    /// dropped once any of them changes.
+    checked: Option<(PredicateDomain, PredicateProblem, String)>,
+}
+
+impl ValidatorPanel {
+    pub fn show(ui: &mut egui::Ui, app: &mut PredicateImprovApp) {
+        ui.label(
+            RichText::new("Check a story").text_style(egui::TextStyle::Name("Heading2".into())),
+        );
+
+        ui.add(
+            egui::TextEdit::multiline(&mut app.validator_panel.story_draft)
+                .hint_text("move(alice, forest, castle)")
+                .desired_width(f32::INFINITY)
+                .desired_rows(6),
+        );
+
+        let panel = &app.validator_panel;
+        if panel
+            .checked
+            .as_ref()
+            .is_some_and(|(domain, problem, draft)| {
+                domain != &app.domain || problem != app.problem() || draft != &panel.story_draft
+            })
+        {
+            app.validator_panel.result = None;
+            app.validator_panel.checked = None;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Check").clicked() {
+                app.validator_panel.result = Some(check_story(app));
+                app.validator_panel.checked = Some((
+                    app.domain.clone(),
+                    app.problem().clone(),
+                    app.validator_panel.story_draft.clone(),
+                ));
+            }
+
+            match &app.validator_panel.result {
+                Some(Ok(())) => {
+                    ui.label(RichText::new("The story is valid.").color(Color32::LIGHT_GREEN));
+                    if ui.button("View").clicked() {
+                        app.story_inspector_panel.selected_step = 0;
+                        app.viewing_story = true;
+                    }
+                }
+                Some(Err(message)) => {
+                    ui.label(RichText::new(message).color(Color32::LIGHT_RED));
+                }
+                None => {}
+            }
+        });
+    }
+}
+
+/// Validates the draft against the domain and the selected problem, replacing the current story on
+/// success.
+fn check_story(app: &mut PredicateImprovApp) -> Result<(), String> {
+    let draft = &app.validator_panel.story_draft;
+    let steps = parse_story(draft).map_err(|err| err.to_string())?;
+    let lines: Vec<usize> = steps.iter().map(|(line, _)| *line).collect();
+
+    let story = app
+        .domain
+        .validate_story(
+            app.problem(),
+            steps.into_iter().map(|(_, step)| step).collect(),
+        )
+        .map_err(|err| match err {
+            StoryError::Step { step, kind } => {
+                let line = lines[step];
+                let text = draft.lines().nth(line - 1).unwrap_or_default();
+                let column = step_column(text, kind.argument());
+                format!("line {line}, column {column}: {kind}")
+            }
+            err => err.to_string(),
+        })?;
+
+    app.story = Some(story);
+    Ok(())
+}